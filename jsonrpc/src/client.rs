@@ -1,4 +1,7 @@
-use crate::types::{Notification, Request, Response};
+use crate::{
+    metrics::Metrics,
+    types::{Notification, Request, Response},
+};
 use anyhow::{Context, Result};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
@@ -22,6 +25,7 @@ pub struct Client {
     pending_responses: Arc<Mutex<HashMap<i64, oneshot::Sender<Value>>>>,
     response_resolver_handle: JoinHandle<()>,
     request_id_counter: AtomicI64,
+    metrics: Metrics,
 }
 
 impl Drop for Client {
@@ -70,6 +74,7 @@ impl Client {
             pending_responses,
             response_resolver_handle,
             request_id_counter,
+            metrics: Metrics::default(),
         }
     }
 
@@ -115,6 +120,7 @@ impl Client {
             id: self.request_id_counter.fetch_add(1, Relaxed),
         };
 
+        let timer = self.metrics.start_request(&request.method);
         let (response_tx, response_rx) = oneshot::channel();
 
         drop(
@@ -131,6 +137,8 @@ impl Client {
             .context("failed to send request")?;
 
         let response = response_rx.await.context("failed to await response")?;
+        timer.finish(response.get("error").is_some());
+
         serde_json::from_value(response).context("failed to parse response")
     }
 
@@ -146,6 +154,14 @@ impl Client {
 
         self.client_tx
             .send(notification_str)
-            .context("failed to send notification")
+            .context("failed to send notification")?;
+
+        self.metrics.record_notification(&notification.method);
+
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
     }
 }
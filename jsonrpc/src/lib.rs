@@ -1,2 +1,3 @@
 pub mod client;
+pub mod metrics;
 pub mod types;
@@ -0,0 +1,166 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicI64, Ordering::Relaxed},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use serde::Serialize;
+
+/// Upper bounds (inclusive) of the request latency histogram buckets.
+///
+/// Requests slower than the last bound are counted in an extra overflow bucket.
+pub const LATENCY_BUCKETS_MS: &[f64] = &[1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0, 5000.0];
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MethodMetrics {
+    pub requests: u64,
+    pub notifications: u64,
+    /// Requests that got an error response, or got no response at all.
+    pub errors: u64,
+    pub total_latency_ms: f64,
+    pub max_latency_ms: f64,
+    /// Request counts per bucket of [`LATENCY_BUCKETS_MS`], plus an overflow bucket.
+    pub latency_histogram: Vec<u64>,
+}
+
+impl Default for MethodMetrics {
+    fn default() -> Self {
+        Self {
+            requests: 0,
+            notifications: 0,
+            errors: 0,
+            total_latency_ms: 0.0,
+            max_latency_ms: 0.0,
+            latency_histogram: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub in_flight: i64,
+    pub latency_buckets_ms: Vec<f64>,
+    pub methods: BTreeMap<String, MethodMetrics>,
+}
+
+impl MetricsSnapshot {
+    /// Add the traffic of `other`, e.g. of another client of the same scan.
+    pub fn merge(&mut self, other: &MetricsSnapshot) {
+        self.in_flight += other.in_flight;
+        for (method, metrics) in &other.methods {
+            let merged = self.methods.entry(method.clone()).or_default();
+            merged.requests += metrics.requests;
+            merged.notifications += metrics.notifications;
+            merged.errors += metrics.errors;
+            merged.total_latency_ms += metrics.total_latency_ms;
+            merged.max_latency_ms = merged.max_latency_ms.max(metrics.max_latency_ms);
+            for (bucket, count) in merged
+                .latency_histogram
+                .iter_mut()
+                .zip(&metrics.latency_histogram)
+            {
+                *bucket += count;
+            }
+        }
+    }
+}
+
+/// Per-method traffic statistics of a [`crate::client::Client`].
+#[derive(Default)]
+pub struct Metrics {
+    methods: Mutex<HashMap<String, MethodMetrics>>,
+    in_flight: AtomicI64,
+}
+
+impl Metrics {
+    pub fn start_request(&self, method: &str) -> RequestTimer<'_> {
+        self.in_flight.fetch_add(1, Relaxed);
+
+        RequestTimer {
+            metrics: self,
+            method: method.to_string(),
+            start: Instant::now(),
+            finished: false,
+        }
+    }
+
+    pub fn record_notification(&self, method: &str) {
+        self.methods
+            .lock()
+            .expect("failed to acquire lock")
+            .entry(method.to_string())
+            .or_default()
+            .notifications += 1;
+    }
+
+    pub fn in_flight(&self) -> i64 {
+        self.in_flight.load(Relaxed)
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            in_flight: self.in_flight(),
+            latency_buckets_ms: LATENCY_BUCKETS_MS.to_vec(),
+            methods: self
+                .methods
+                .lock()
+                .expect("failed to acquire lock")
+                .iter()
+                .map(|(method, metrics)| (method.clone(), metrics.clone()))
+                .collect(),
+        }
+    }
+
+    fn record_request(&self, method: &str, latency_ms: f64, error: bool) {
+        let mut methods = self.methods.lock().expect("failed to acquire lock");
+        let metrics = methods.entry(method.to_string()).or_default();
+
+        metrics.requests += 1;
+        if error {
+            metrics.errors += 1;
+        }
+        metrics.total_latency_ms += latency_ms;
+        metrics.max_latency_ms = metrics.max_latency_ms.max(latency_ms);
+
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        metrics.latency_histogram[bucket] += 1;
+    }
+}
+
+/// Measures a single request, counting it as in flight until it is finished
+/// or dropped. A timer dropped before being finished is recorded as an error.
+pub struct RequestTimer<'a> {
+    metrics: &'a Metrics,
+    method: String,
+    start: Instant,
+    finished: bool,
+}
+
+impl RequestTimer<'_> {
+    pub fn finish(mut self, error: bool) {
+        self.record(error);
+    }
+
+    fn record(&mut self, error: bool) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let latency_ms = self.start.elapsed().as_secs_f64() * 1000.0;
+        self.metrics.in_flight.fetch_sub(1, Relaxed);
+        self.metrics.record_request(&self.method, latency_ms, error);
+    }
+}
+
+impl Drop for RequestTimer<'_> {
+    fn drop(&mut self) {
+        self.record(true);
+    }
+}
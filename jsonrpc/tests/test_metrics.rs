@@ -0,0 +1,84 @@
+use jsonrpc::{client::Client, metrics::LATENCY_BUCKETS_MS, types::JsonRpcResult};
+use serde_json::{json, Value};
+use tokio::{
+    join,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+async fn fake_jsonrpc_server(
+    mut client_rx: UnboundedReceiver<String>,
+    server_tx: UnboundedSender<String>,
+) {
+    while let Some(msg) = client_rx.recv().await {
+        let msg: Value = serde_json::from_str(&msg).expect("got invalid json");
+        let Some(id) = msg.get("id") else {
+            continue;
+        };
+
+        let response = match msg["method"].as_str() {
+            Some("ok") => json!({"jsonrpc": "2.0", "result": 1, "id": id}),
+            _ => json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32601, "message": "Method not found"},
+                "id": id,
+            }),
+        };
+
+        server_tx
+            .send(response.to_string())
+            .expect("failed to send response");
+    }
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let (client_tx, client_rx) = unbounded_channel();
+    let (server_tx, server_rx) = unbounded_channel();
+
+    let server_handle = tokio::spawn(fake_jsonrpc_server(client_rx, server_tx));
+
+    let client = Client::new(client_tx, server_rx);
+
+    let (r1, r2, r3) = join!(
+        client.request::<(), i64, ()>("ok".to_string(), None),
+        client.request::<(), i64, ()>("ok".to_string(), None),
+        client.request::<(), i64, ()>("missing".to_string(), None),
+    );
+    assert!(matches!(r1.unwrap().result, JsonRpcResult::Result(1)));
+    assert!(matches!(r2.unwrap().result, JsonRpcResult::Result(1)));
+    assert!(matches!(r3.unwrap().result, JsonRpcResult::Error(_)));
+
+    client.notify::<()>("notify".to_string(), None).unwrap();
+
+    let snapshot = client.metrics().snapshot();
+    assert_eq!(snapshot.in_flight, 0);
+
+    let ok = &snapshot.methods["ok"];
+    assert_eq!((ok.requests, ok.errors, ok.notifications), (2, 0, 0));
+    assert_eq!(ok.latency_histogram.len(), LATENCY_BUCKETS_MS.len() + 1);
+    assert_eq!(ok.latency_histogram.iter().sum::<u64>(), 2);
+
+    let missing = &snapshot.methods["missing"];
+    assert_eq!((missing.requests, missing.errors), (1, 1));
+
+    let notify = &snapshot.methods["notify"];
+    assert_eq!((notify.requests, notify.notifications), (0, 1));
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_metrics_dropped_request() {
+    let (client_tx, _client_rx) = unbounded_channel();
+    let (_server_tx, server_rx) = unbounded_channel();
+
+    let client = Client::new(client_tx, server_rx);
+
+    let request = client.request::<(), i64, ()>("never".to_string(), None);
+    let timeout = tokio::time::timeout(std::time::Duration::from_millis(10), request).await;
+    assert!(timeout.is_err());
+
+    let snapshot = client.metrics().snapshot();
+    assert_eq!(snapshot.in_flight, 0);
+    assert_eq!(snapshot.methods["never"].errors, 1);
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Mutex,
+    },
+};
+
+use anyhow::Result;
+use jsonrpc::metrics::{Metrics, MetricsSnapshot};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification,
+    },
+    request::{
+        DocumentSymbolRequest, GotoDeclaration, GotoDefinition, GotoImplementation,
+        GotoTypeDefinition, HoverRequest, References, Request,
+    },
+    Url,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    client::Client,
+    middleware::{Direction, Middleware, Response},
+};
+
+/// Requests that only read server state, and are therefore safe to cache
+/// as long as no document changes.
+pub const DEFAULT_CACHED_METHODS: &[&str] = &[
+    GotoDefinition::METHOD,
+    GotoDeclaration::METHOD,
+    GotoTypeDefinition::METHOD,
+    GotoImplementation::METHOD,
+    References::METHOD,
+    HoverRequest::METHOD,
+    DocumentSymbolRequest::METHOD,
+];
+
+/// Requests a [`RequestCache`] answered itself, and ones it passed on to
+/// the server.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Traffic of a client, see [`Client::metrics`], along with the stats of
+/// its [`RequestCache`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CachedMetrics {
+    #[serde(flatten)]
+    pub traffic: MetricsSnapshot,
+    pub cache: CacheStats,
+}
+
+impl Default for CachedMetrics {
+    fn default() -> Self {
+        Self {
+            traffic: Metrics::default().snapshot(),
+            cache: CacheStats::default(),
+        }
+    }
+}
+
+impl CachedMetrics {
+    pub fn new(client: &Client, cache: &RequestCache) -> Self {
+        Self {
+            traffic: client.metrics().snapshot(),
+            cache: cache.stats(),
+        }
+    }
+
+    /// Add the metrics of `other`, e.g. of another client of the same scan.
+    pub fn merge(&mut self, other: &CachedMetrics) {
+        self.traffic.merge(&other.traffic);
+        self.cache.hits += other.cache.hits;
+        self.cache.misses += other.cache.misses;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: String,
+    params: String,
+    version: Option<i32>,
+}
+
+/// Cache of successful responses, keyed by method, params and the version
+/// of the document the request targets.
+///
+/// Any `didChange`/`didSave` notification invalidates the whole cache, since
+/// a change in one document can move the results of requests on another
+/// (e.g. a definition in an imported file).
+pub struct RequestCache {
+    methods: HashSet<String>,
+    entries: Mutex<HashMap<CacheKey, Value>>,
+    versions: Mutex<HashMap<Url, i32>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl Default for RequestCache {
+    fn default() -> Self {
+        Self::with_methods(DEFAULT_CACHED_METHODS)
+    }
+}
+
+impl RequestCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_methods(methods: &[&str]) -> Self {
+        Self {
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            entries: Mutex::new(HashMap::new()),
+            versions: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_cached(&self, method: &str) -> bool {
+        self.methods.contains(method)
+    }
+
+    /// Get the cached result of a request, counting a hit or a miss.
+    ///
+    /// Returns `None` without counting anything if `method` isn't cached.
+    pub fn get(&self, method: &str, params: &Value) -> Option<Value> {
+        if !self.is_cached(method) {
+            return None;
+        }
+
+        let key = self.key(method, params);
+        let result = self
+            .entries
+            .lock()
+            .expect("failed to acquire lock")
+            .get(&key)
+            .cloned();
+
+        match result {
+            Some(_) => self.hits.fetch_add(1, Relaxed),
+            None => self.misses.fetch_add(1, Relaxed),
+        };
+
+        result
+    }
+
+    pub fn insert(&self, method: &str, params: &Value, result: Value) {
+        if !self.is_cached(method) {
+            return;
+        }
+
+        let key = self.key(method, params);
+        self.entries
+            .lock()
+            .expect("failed to acquire lock")
+            .insert(key, result);
+    }
+
+    /// Track document versions and invalidate the cache according to
+    /// a notification sent to the server.
+    pub fn notified(&self, method: &str, params: &Value) {
+        let uri = document_uri(params);
+        let version = params
+            .pointer("/textDocument/version")
+            .and_then(Value::as_i64)
+            .map(|v| v as i32);
+
+        let mut versions = self.versions.lock().expect("failed to acquire lock");
+        match (method, uri) {
+            (DidOpenTextDocument::METHOD, Some(uri)) => {
+                if let Some(version) = version {
+                    versions.insert(uri, version);
+                }
+            }
+            (DidChangeTextDocument::METHOD, uri) => {
+                if let (Some(uri), Some(version)) = (uri, version) {
+                    versions.insert(uri, version);
+                }
+                self.clear();
+            }
+            (DidSaveTextDocument::METHOD, _) => self.clear(),
+            (DidCloseTextDocument::METHOD, Some(uri)) => {
+                versions.remove(&uri);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().expect("failed to acquire lock").clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("failed to acquire lock").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Relaxed)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits() as u64,
+            misses: self.misses() as u64,
+        }
+    }
+
+    fn key(&self, method: &str, params: &Value) -> CacheKey {
+        let version = document_uri(params).and_then(|uri| {
+            self.versions
+                .lock()
+                .expect("failed to acquire lock")
+                .get(&uri)
+                .copied()
+        });
+
+        CacheKey {
+            method: method.to_string(),
+            params: params.to_string(),
+            version,
+        }
+    }
+}
+
+fn document_uri(params: &Value) -> Option<Url> {
+    params
+        .pointer("/textDocument/uri")
+        .and_then(Value::as_str)
+        .and_then(|uri| Url::parse(uri).ok())
+}
+
+impl Middleware for RequestCache {
+    fn before_request(&self, method: &str, params: &mut Value) -> Result<Option<Response>> {
+        Ok(self.get(method, params).map(Ok))
+    }
+
+    fn after_response(&self, method: &str, params: &Value, response: &mut Response) -> Result<()> {
+        if let Ok(result) = response {
+            self.insert(method, params, result.clone());
+        }
+        Ok(())
+    }
+
+    fn on_notification(
+        &self,
+        direction: Direction,
+        method: &str,
+        params: &mut Value,
+    ) -> Result<()> {
+        if direction == Direction::ToServer {
+            self.notified(method, params);
+        }
+        Ok(())
+    }
+}
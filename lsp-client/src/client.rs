@@ -1,6 +1,7 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use jsonrpc::{
     client::Client as JsonRpcClient,
+    metrics::Metrics,
     types::{JsonRpcError, Notification},
 };
 use lsp_types::{
@@ -8,14 +9,23 @@ use lsp_types::{
     PublishDiagnosticsParams,
 };
 use serde_json::Value;
+use std::sync::{Arc, RwLock};
 use tokio::{
     sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     task::JoinHandle,
 };
 
+use crate::{
+    framing::encode_message,
+    middleware::{Direction, Middleware, Response},
+};
+
+type Middlewares = Arc<RwLock<Vec<Arc<dyn Middleware>>>>;
+
 pub struct Client {
     jsonrpc_client: JsonRpcClient,
     encoder_handle: JoinHandle<()>,
+    middlewares: Middlewares,
 }
 
 impl Drop for Client {
@@ -27,12 +37,20 @@ impl Drop for Client {
 impl Client {
     pub fn new(client_tx: UnboundedSender<String>, server_rx: UnboundedReceiver<String>) -> Self {
         let (jsonrpc_client_tx, jsonrpc_client_rx) = unbounded_channel();
+        let middlewares: Middlewares = Arc::new(RwLock::new(vec![]));
+        let handler_middlewares = Arc::clone(&middlewares);
 
         Self {
             jsonrpc_client: JsonRpcClient::with_handler(
                 jsonrpc_client_tx,
                 server_rx,
-                |notification| -> Result<()> {
+                move |mut notification| -> Result<()> {
+                    Client::notification_middlewares(
+                        &handler_middlewares,
+                        Direction::FromServer,
+                        &mut notification,
+                    )?;
+
                     let diagnostics: Notification<PublishDiagnosticsParams> =
                         serde_json::from_value(notification)?;
                     let diagnostics = diagnostics
@@ -58,14 +76,64 @@ impl Client {
                 },
             ),
             encoder_handle: tokio::spawn(Client::lsp_encode(jsonrpc_client_rx, client_tx)),
+            middlewares,
         }
     }
 
+    /// Add a middleware to the end of the chain, see [`Middleware`].
+    pub fn with_middleware<M: Middleware + 'static>(self, middleware: Arc<M>) -> Self {
+        self.middlewares
+            .write()
+            .expect("failed to acquire lock")
+            .push(middleware);
+        self
+    }
+
+    /// Statistics of the traffic that reached the server, requests answered
+    /// by a middleware are not counted.
+    pub fn metrics(&self) -> &Metrics {
+        self.jsonrpc_client.metrics()
+    }
+
+    fn middlewares(&self) -> Vec<Arc<dyn Middleware>> {
+        self.middlewares
+            .read()
+            .expect("failed to acquire lock")
+            .clone()
+    }
+
+    fn notification_middlewares(
+        middlewares: &Middlewares,
+        direction: Direction,
+        notification: &mut Value,
+    ) -> Result<()> {
+        let Some(notification) = notification.as_object_mut() else {
+            return Ok(());
+        };
+        let method = notification
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let params = notification.entry("params").or_insert(Value::Null);
+
+        let mut middlewares = middlewares.read().expect("failed to acquire lock").clone();
+        // server notifications unwind the chain, like responses do
+        if direction == Direction::FromServer {
+            middlewares.reverse();
+        }
+
+        for middleware in &middlewares {
+            middleware.on_notification(direction, &method, params)?;
+        }
+
+        Ok(())
+    }
+
     async fn lsp_encode(mut rx: UnboundedReceiver<String>, tx: UnboundedSender<String>) {
         while let Some(msg) = rx.recv().await {
-            let len = msg.as_bytes().len();
-            let msg = format!("Content-Length: {}\r\n\r\n{}", len, msg);
-            tx.send(msg).expect("failed to send message");
+            tx.send(encode_message(&msg))
+                .expect("failed to send message");
         }
     }
 
@@ -76,18 +144,48 @@ impl Client {
     where
         R: LspRequest,
     {
-        Ok(self
-            .jsonrpc_client
-            .request(R::METHOD.to_string(), Some(params))
-            .await?
-            .result
-            .as_result())
+        let middlewares = self.middlewares();
+        let mut params = serde_json::to_value(params).context("failed to serialize params")?;
+
+        let mut short_circuit = None;
+        let mut entered = 0;
+        for middleware in &middlewares {
+            short_circuit = middleware.before_request(R::METHOD, &mut params)?;
+            if short_circuit.is_some() {
+                break;
+            }
+            entered += 1;
+        }
+
+        let mut response: Response = match short_circuit {
+            Some(response) => response,
+            None => self
+                .jsonrpc_client
+                .request(R::METHOD.to_string(), Some(&params))
+                .await?
+                .result
+                .as_result(),
+        };
+
+        for middleware in middlewares[..entered].iter().rev() {
+            middleware.after_response(R::METHOD, &params, &mut response)?;
+        }
+
+        Ok(match response {
+            Ok(result) => Ok(serde_json::from_value(result).context("failed to parse result")?),
+            Err(error) => Err(error),
+        })
     }
 
     pub fn notify<R>(&self, params: R::Params) -> Result<()>
     where
         R: LspNotification,
     {
+        let mut params = serde_json::to_value(params).context("failed to serialize params")?;
+        for middleware in self.middlewares() {
+            middleware.on_notification(Direction::ToServer, R::METHOD, &mut params)?;
+        }
+
         self.jsonrpc_client
             .notify(R::METHOD.to_string(), Some(params))
     }
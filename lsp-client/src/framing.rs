@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Context, Result};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// Wrap message content with the LSP base protocol header.
+pub fn encode_message(content: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+}
+
+/// Read the content of the next LSP base protocol message.
+///
+/// Returns `None` if the reader reached EOF before a new message started.
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<String>> {
+    let mut content_length = None;
+    let mut read_header = false;
+
+    loop {
+        let mut line = String::new();
+        let read_bytes = reader
+            .read_line(&mut line)
+            .await
+            .context("failed to read header")?;
+
+        if read_bytes == 0 {
+            return match read_header {
+                false => Ok(None),
+                true => Err(anyhow!("got EOF inside message header")),
+            };
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+
+        read_header = true;
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("got invalid header: {:?}", line))?;
+
+        if name.trim().eq_ignore_ascii_case("Content-Length") {
+            content_length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .context(format!("got invalid content length: {:?}", value))?,
+            );
+        }
+    }
+
+    let mut content = vec![0; content_length.expect("content length must be set")];
+    reader
+        .read_exact(&mut content)
+        .await
+        .context("failed to read content")?;
+
+    Ok(Some(
+        String::from_utf8(content).context("got non-utf8 content")?,
+    ))
+}
@@ -1,2 +1,8 @@
+pub mod cache;
 pub mod client;
 pub mod clients;
+pub mod framing;
+pub mod middleware;
+pub mod presets;
+pub mod semantic_tokens;
+pub mod symbols;
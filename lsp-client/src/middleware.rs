@@ -0,0 +1,142 @@
+use anyhow::Result;
+use jsonrpc::types::JsonRpcError;
+use serde_json::Value;
+
+pub type Response = std::result::Result<Value, JsonRpcError<Value>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ToServer,
+    FromServer,
+}
+
+/// Hooks into the traffic between a [`crate::client::Client`] and the server.
+///
+/// Middlewares are stacked in the order they are added: `before_request`
+/// and notifications to the server run from first to last, `after_response`
+/// and notifications from the server from last to first. A middleware can
+/// answer a request itself by returning a response from `before_request`,
+/// in which case the server and the rest of the chain are skipped, and only
+/// the middlewares before it see the response.
+pub trait Middleware: Send + Sync {
+    fn before_request(&self, _method: &str, _params: &mut Value) -> Result<Option<Response>> {
+        Ok(None)
+    }
+
+    fn after_response(
+        &self,
+        _method: &str,
+        _params: &Value,
+        _response: &mut Response,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_notification(
+        &self,
+        _direction: Direction,
+        _method: &str,
+        _params: &mut Value,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Print all requests, responses and notifications to stderr.
+pub struct Logger;
+
+impl Middleware for Logger {
+    fn before_request(&self, method: &str, params: &mut Value) -> Result<Option<Response>> {
+        eprintln!("--> {}: {}", method, params);
+        Ok(None)
+    }
+
+    fn after_response(&self, method: &str, _params: &Value, response: &mut Response) -> Result<()> {
+        match response {
+            Ok(result) => eprintln!("<-- {}: {}", method, result),
+            Err(error) => eprintln!("<-- {}: {}", method, error),
+        }
+        Ok(())
+    }
+
+    fn on_notification(
+        &self,
+        direction: Direction,
+        method: &str,
+        params: &mut Value,
+    ) -> Result<()> {
+        let arrow = match direction {
+            Direction::ToServer => "-->",
+            Direction::FromServer => "<--",
+        };
+        eprintln!("{} {}: {}", arrow, method, params);
+        Ok(())
+    }
+}
+
+/// Rewrite uris between the client's and the server's view of the file system,
+/// e.g. when the server runs in a container with the project mounted elsewhere.
+///
+/// Every string value starting with `client_prefix` that is sent to the server
+/// has it replaced with `server_prefix`, and vice versa for responses and
+/// notifications from the server.
+pub struct UriRewriter {
+    client_prefix: String,
+    server_prefix: String,
+}
+
+impl UriRewriter {
+    pub fn new(client_prefix: impl Into<String>, server_prefix: impl Into<String>) -> Self {
+        Self {
+            client_prefix: client_prefix.into(),
+            server_prefix: server_prefix.into(),
+        }
+    }
+
+    fn rewrite(value: &mut Value, from: &str, to: &str) {
+        match value {
+            Value::String(s) => {
+                if let Some(rest) = s.strip_prefix(from) {
+                    *s = format!("{}{}", to, rest);
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|v| Self::rewrite(v, from, to)),
+            Value::Object(map) => map.values_mut().for_each(|v| Self::rewrite(v, from, to)),
+            _ => {}
+        }
+    }
+}
+
+impl Middleware for UriRewriter {
+    fn before_request(&self, _method: &str, params: &mut Value) -> Result<Option<Response>> {
+        Self::rewrite(params, &self.client_prefix, &self.server_prefix);
+        Ok(None)
+    }
+
+    fn after_response(
+        &self,
+        _method: &str,
+        _params: &Value,
+        response: &mut Response,
+    ) -> Result<()> {
+        if let Ok(result) = response {
+            Self::rewrite(result, &self.server_prefix, &self.client_prefix);
+        }
+        Ok(())
+    }
+
+    fn on_notification(
+        &self,
+        direction: Direction,
+        _method: &str,
+        params: &mut Value,
+    ) -> Result<()> {
+        match direction {
+            Direction::ToServer => Self::rewrite(params, &self.client_prefix, &self.server_prefix),
+            Direction::FromServer => {
+                Self::rewrite(params, &self.server_prefix, &self.client_prefix)
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::process::Stdio;
+
+use tokio::process::{Child, Command};
+
+/// Command line of a known language server, speaking LSP over stdio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerPreset {
+    pub name: &'static str,
+    pub command: &'static str,
+    pub args: &'static [&'static str],
+}
+
+pub const PRESETS: &[ServerPreset] = &[
+    ServerPreset {
+        name: "rust-analyzer",
+        command: "rust-analyzer",
+        args: &[],
+    },
+    ServerPreset {
+        name: "solidity-ls",
+        command: "solidity-ls",
+        args: &["--stdio"],
+    },
+    ServerPreset {
+        name: "solc",
+        command: "solc",
+        args: &["--lsp"],
+    },
+    ServerPreset {
+        name: "pyls",
+        command: "pyls",
+        args: &[],
+    },
+    ServerPreset {
+        name: "pylsp",
+        command: "pylsp",
+        args: &[],
+    },
+    ServerPreset {
+        name: "jedi-language-server",
+        command: "jedi-language-server",
+        args: &[],
+    },
+    ServerPreset {
+        name: "typescript-language-server",
+        command: "typescript-language-server",
+        args: &["--stdio"],
+    },
+    ServerPreset {
+        name: "gopls",
+        command: "gopls",
+        args: &[],
+    },
+];
+
+impl ServerPreset {
+    pub fn find(name: &str) -> Option<&'static ServerPreset> {
+        PRESETS.iter().find(|preset| preset.name == name)
+    }
+
+    pub fn spawn(&self) -> std::io::Result<Child> {
+        Command::new(self.command)
+            .args(self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+}
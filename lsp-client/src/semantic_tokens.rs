@@ -0,0 +1,160 @@
+use lsp_types::{
+    Position, Range, SemanticToken, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensLegend, SemanticTokensRangeResult, SemanticTokensResult,
+};
+
+/// A semantic token with an absolute range and resolved legend names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbsoluteToken {
+    pub range: Range,
+    pub token_type: String,
+    pub modifiers: Vec<String>,
+}
+
+/// Decode relative semantic tokens, as returned by both full and range requests.
+///
+/// Token types missing from the legend are named `"unknown"`, and unknown
+/// modifier bits are ignored. Tokens are assumed not to span multiple lines.
+pub fn decode_tokens(
+    tokens: &[SemanticToken],
+    legend: &SemanticTokensLegend,
+) -> Vec<AbsoluteToken> {
+    let mut line = 0;
+    let mut start = 0;
+
+    tokens
+        .iter()
+        .map(|token| {
+            if token.delta_line > 0 {
+                line += token.delta_line;
+                start = token.delta_start;
+            } else {
+                start += token.delta_start;
+            }
+
+            let token_type = legend
+                .token_types
+                .get(token.token_type as usize)
+                .map(|t| t.as_str().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            let modifiers = legend
+                .token_modifiers
+                .iter()
+                .enumerate()
+                .filter(|(bit, _)| *bit < 32 && token.token_modifiers_bitset & (1 << bit) != 0)
+                .map(|(_, modifier)| modifier.as_str().to_string())
+                .collect();
+
+            AbsoluteToken {
+                range: Range::new(
+                    Position::new(line, start),
+                    Position::new(line, start + token.length),
+                ),
+                token_type,
+                modifiers,
+            }
+        })
+        .collect()
+}
+
+/// Apply delta edits to the previous tokens of a document.
+///
+/// Edit offsets count integers of the encoded array (5 per token), and all
+/// refer to the previous tokens, so they are applied from last to first.
+pub fn apply_edits(tokens: &[SemanticToken], edits: &[SemanticTokensEdit]) -> Vec<SemanticToken> {
+    let mut data = tokens
+        .iter()
+        .flat_map(|t| {
+            [
+                t.delta_line,
+                t.delta_start,
+                t.length,
+                t.token_type,
+                t.token_modifiers_bitset,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+
+    for edit in edits {
+        let start = (edit.start as usize).min(data.len());
+        let end = (start + edit.delete_count as usize).min(data.len());
+        let inserted = edit.data.iter().flatten().flat_map(|t| {
+            [
+                t.delta_line,
+                t.delta_start,
+                t.length,
+                t.token_type,
+                t.token_modifiers_bitset,
+            ]
+        });
+
+        data.splice(start..end, inserted);
+    }
+
+    data.chunks_exact(5)
+        .map(|chunk| SemanticToken {
+            delta_line: chunk[0],
+            delta_start: chunk[1],
+            length: chunk[2],
+            token_type: chunk[3],
+            token_modifiers_bitset: chunk[4],
+        })
+        .collect()
+}
+
+/// Semantic tokens of a single document, kept up to date across full,
+/// delta and range responses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentTokens {
+    /// Pass this as `previous_result_id` of the next delta request.
+    pub result_id: Option<String>,
+    pub tokens: Vec<SemanticToken>,
+}
+
+impl DocumentTokens {
+    pub fn update_full(&mut self, result: SemanticTokensResult) {
+        match result {
+            SemanticTokensResult::Tokens(tokens) => {
+                self.result_id = tokens.result_id;
+                self.tokens = tokens.data;
+            }
+            SemanticTokensResult::Partial(partial) => self.tokens.extend(partial.data),
+        }
+    }
+
+    pub fn update_delta(&mut self, result: SemanticTokensFullDeltaResult) {
+        match result {
+            SemanticTokensFullDeltaResult::Tokens(tokens) => {
+                self.result_id = tokens.result_id;
+                self.tokens = tokens.data;
+            }
+            SemanticTokensFullDeltaResult::TokensDelta(delta) => {
+                self.result_id = delta.result_id;
+                self.tokens = apply_edits(&self.tokens, &delta.edits);
+            }
+            SemanticTokensFullDeltaResult::PartialTokensDelta { edits } => {
+                self.tokens = apply_edits(&self.tokens, &edits);
+            }
+        }
+    }
+
+    pub fn decode(&self, legend: &SemanticTokensLegend) -> Vec<AbsoluteToken> {
+        decode_tokens(&self.tokens, legend)
+    }
+}
+
+/// Decode the tokens of a range request, which are relative to the start of
+/// the document like full responses, and don't update any document state.
+pub fn decode_range(
+    result: SemanticTokensRangeResult,
+    legend: &SemanticTokensLegend,
+) -> Vec<AbsoluteToken> {
+    match result {
+        SemanticTokensRangeResult::Tokens(tokens) => decode_tokens(&tokens.data, legend),
+        SemanticTokensRangeResult::Partial(partial) => decode_tokens(&partial.data, legend),
+    }
+}
@@ -0,0 +1,117 @@
+use lsp_types::{DocumentSymbol, DocumentSymbolResponse, Location, Range, SymbolKind, Url};
+
+/// A symbol of a flattened document symbol tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+    /// Names of the enclosing symbols, outermost first.
+    pub container: Vec<String>,
+    /// The whole symbol, including its body.
+    pub location: Location,
+    /// The symbol's name, or the whole symbol for flat responses.
+    pub selection_range: Range,
+}
+
+/// Flatten a document symbol response, hierarchical or flat, in pre-order.
+pub fn flatten_symbols(uri: &Url, response: DocumentSymbolResponse) -> Vec<LocatedSymbol> {
+    fn visit(
+        uri: &Url,
+        symbol: DocumentSymbol,
+        container: &mut Vec<String>,
+        symbols: &mut Vec<LocatedSymbol>,
+    ) {
+        symbols.push(LocatedSymbol {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            detail: symbol.detail,
+            container: container.clone(),
+            location: Location::new(uri.clone(), symbol.range),
+            selection_range: symbol.selection_range,
+        });
+
+        container.push(symbol.name);
+        for child in symbol.children.into_iter().flatten() {
+            visit(uri, child, container, symbols);
+        }
+        container.pop();
+    }
+
+    let mut symbols = vec![];
+    match response {
+        DocumentSymbolResponse::Flat(flat) => {
+            for symbol in flat {
+                symbols.push(LocatedSymbol {
+                    name: symbol.name,
+                    kind: symbol.kind,
+                    detail: None,
+                    container: symbol.container_name.into_iter().collect(),
+                    selection_range: symbol.location.range,
+                    location: symbol.location,
+                })
+            }
+        }
+        DocumentSymbolResponse::Nested(nested) => {
+            for symbol in nested {
+                visit(uri, symbol, &mut vec![], &mut symbols);
+            }
+        }
+    }
+
+    symbols
+}
+
+fn contains(outer: &Range, inner: &Range) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// Symbols of many documents, searchable by location and name.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    symbols: Vec<LocatedSymbol>,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the symbols of `uri` with those of a new response.
+    pub fn insert(&mut self, uri: &Url, response: DocumentSymbolResponse) {
+        self.symbols.retain(|s| &s.location.uri != uri);
+        self.symbols.extend(flatten_symbols(uri, response));
+    }
+
+    pub fn symbols(&self) -> &[LocatedSymbol] {
+        &self.symbols
+    }
+
+    /// The innermost symbol containing `location`, e.g. the function
+    /// a parameter reference is in.
+    pub fn enclosing(&self, location: &Location) -> Option<&LocatedSymbol> {
+        self.symbols
+            .iter()
+            .filter(|s| {
+                s.location.uri == location.uri && contains(&s.location.range, &location.range)
+            })
+            .min_by_key(|s| {
+                let range = s.location.range;
+                (
+                    range.end.line - range.start.line,
+                    range.end.character.abs_diff(range.start.character),
+                )
+            })
+    }
+
+    /// The symbol whose name is at `location`.
+    pub fn at(&self, location: &Location) -> Option<&LocatedSymbol> {
+        self.symbols.iter().find(|s| {
+            s.location.uri == location.uri && contains(&s.selection_range, &location.range)
+        })
+    }
+
+    pub fn by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a LocatedSymbol> + 'a {
+        self.symbols.iter().filter(move |s| s.name == name)
+    }
+}
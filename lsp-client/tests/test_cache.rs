@@ -0,0 +1,134 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering::Relaxed},
+    Arc,
+};
+
+use lsp_client::{
+    cache::{CacheStats, CachedMetrics, RequestCache},
+    client::Client,
+};
+use lsp_types::{
+    notification::DidChangeTextDocument, request::GotoDefinition, DidChangeTextDocumentParams,
+    GotoDefinitionParams, GotoDefinitionResponse, Location, Position, Range,
+    TextDocumentIdentifier, TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
+};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+async fn fake_lsp_server(
+    mut client_rx: UnboundedReceiver<String>,
+    server_tx: UnboundedSender<String>,
+    requests: Arc<AtomicUsize>,
+) {
+    while let Some(msg) = client_rx.recv().await {
+        let (_header, content) = msg
+            .split_once("\r\n\r\n")
+            .expect("got message without header");
+        let msg: Value = serde_json::from_str(content).expect("got invalid json");
+
+        let Some(id) = msg.get("id") else {
+            continue;
+        };
+
+        requests.fetch_add(1, Relaxed);
+
+        let response = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "uri": "file:///contract.sol",
+                "range": {
+                    "start": { "line": 1, "character": 2 },
+                    "end": { "line": 1, "character": 8 },
+                },
+            },
+            "id": id,
+        });
+
+        server_tx
+            .send(response.to_string())
+            .expect("failed to send response");
+    }
+}
+
+fn definition_params(line: u32) -> GotoDefinitionParams {
+    GotoDefinitionParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::parse("file:///contract.sol").unwrap(),
+            },
+            position: Position::new(line, 4),
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_cache() {
+    let (client_tx, client_rx) = unbounded_channel();
+    let (server_tx, server_rx) = unbounded_channel();
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let server_handle = tokio::spawn(fake_lsp_server(client_rx, server_tx, Arc::clone(&requests)));
+
+    let cache = Arc::new(RequestCache::new());
+    let client = Client::new(client_tx, server_rx).with_middleware(Arc::clone(&cache));
+
+    let expected = GotoDefinitionResponse::Scalar(Location::new(
+        Url::parse("file:///contract.sol").unwrap(),
+        Range::new(Position::new(1, 2), Position::new(1, 8)),
+    ));
+
+    for _ in 0..3 {
+        let definition = client
+            .request::<GotoDefinition>(definition_params(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(definition, Some(expected.clone()));
+    }
+
+    client
+        .request::<GotoDefinition>(definition_params(6))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(requests.load(Relaxed), 2);
+    assert_eq!((cache.hits(), cache.misses(), cache.len()), (2, 2, 2));
+
+    client
+        .notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: Url::parse("file:///contract.sol").unwrap(),
+                version: 2,
+            },
+            content_changes: vec![],
+        })
+        .unwrap();
+
+    assert!(cache.is_empty());
+
+    client
+        .request::<GotoDefinition>(definition_params(5))
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(requests.load(Relaxed), 3);
+    assert_eq!((cache.hits(), cache.misses()), (2, 3));
+
+    // only requests that reached the server count as traffic
+    let metrics = CachedMetrics::new(&client, &cache);
+    assert_eq!(metrics.cache, CacheStats { hits: 2, misses: 3 });
+    assert_eq!(
+        metrics.traffic.methods["textDocument/definition"].requests,
+        3
+    );
+
+    let json = serde_json::to_value(&metrics).unwrap();
+    assert_eq!(json["cache"], json!({ "hits": 2, "misses": 3 }));
+    assert!(json["methods"].is_object());
+
+    server_handle.abort();
+}
@@ -0,0 +1,171 @@
+use lsp_client::{
+    semantic_tokens::{apply_edits, decode_tokens, DocumentTokens},
+    symbols::{flatten_symbols, SymbolIndex},
+};
+use lsp_types::{
+    DocumentSymbolResponse, Location, Position, Range, SemanticTokenModifier, SemanticTokenType,
+    SemanticTokens, SemanticTokensDelta, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensLegend, SemanticTokensResult, Url,
+};
+use serde_json::json;
+
+fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::FUNCTION,
+            SemanticTokenType::PARAMETER,
+            SemanticTokenType::VARIABLE,
+        ],
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::READONLY,
+        ],
+    }
+}
+
+fn display(tokens: &DocumentTokens) -> Vec<String> {
+    tokens
+        .decode(&legend())
+        .iter()
+        .map(|t| {
+            format!(
+                "{}:{}-{} {} {:?}",
+                t.range.start.line,
+                t.range.start.character,
+                t.range.end.character,
+                t.token_type,
+                t.modifiers
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_decode_tokens() {
+    let tokens: SemanticTokens = serde_json::from_value(json!({
+        "resultId": "1",
+        "data": [
+            1, 9, 8, 0, 1,
+            0, 9, 5, 1, 3,
+            2, 4, 3, 2, 0,
+            0, 6, 5, 7, 0,
+        ],
+    }))
+    .unwrap();
+
+    let mut document = DocumentTokens::default();
+    document.update_full(SemanticTokensResult::Tokens(tokens));
+
+    assert_eq!(document.result_id.as_deref(), Some("1"));
+    insta::assert_debug_snapshot!(display(&document), @r###"
+    [
+        "1:9-17 function [\"declaration\"]",
+        "1:18-23 parameter [\"declaration\", \"readonly\"]",
+        "3:4-7 variable []",
+        "3:10-15 unknown []",
+    ]
+    "###);
+
+    // replace the type of the second token, and delete the last one
+    document.update_delta(SemanticTokensFullDeltaResult::TokensDelta(
+        serde_json::from_value::<SemanticTokensDelta>(json!({
+            "resultId": "2",
+            "edits": [
+                { "start": 5, "deleteCount": 5, "data": [0, 9, 5, 2, 3] },
+                { "start": 15, "deleteCount": 5 },
+            ],
+        }))
+        .unwrap(),
+    ));
+
+    assert_eq!(document.result_id.as_deref(), Some("2"));
+    insta::assert_debug_snapshot!(display(&document), @r###"
+    [
+        "1:9-17 function [\"declaration\"]",
+        "1:18-23 variable [\"declaration\", \"readonly\"]",
+        "3:4-7 variable []",
+    ]
+    "###);
+}
+
+#[test]
+fn test_apply_edits_out_of_order() {
+    let tokens = decode_tokens(&[], &legend());
+    assert!(tokens.is_empty());
+
+    let edits: Vec<SemanticTokensEdit> = serde_json::from_value(json!([
+        { "start": 0, "deleteCount": 0, "data": [0, 0, 1, 0, 0] },
+        { "start": 0, "deleteCount": 0, "data": [1, 0, 2, 1, 0] },
+    ]))
+    .unwrap();
+
+    let tokens = apply_edits(&[], &edits);
+    assert_eq!(tokens.len(), 2);
+}
+
+#[test]
+fn test_symbol_index() {
+    let uri = Url::parse("file:///contract.sol").unwrap();
+    let response: DocumentSymbolResponse = serde_json::from_value(json!([
+        {
+            "name": "Contract",
+            "kind": 5,
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 10, "character": 1 } },
+            "selectionRange": { "start": { "line": 0, "character": 9 }, "end": { "line": 0, "character": 17 } },
+            "children": [
+                {
+                    "name": "withdraw",
+                    "kind": 6,
+                    "range": { "start": { "line": 2, "character": 4 }, "end": { "line": 6, "character": 5 } },
+                    "selectionRange": { "start": { "line": 2, "character": 13 }, "end": { "line": 2, "character": 21 } },
+                    "children": [
+                        {
+                            "name": "target",
+                            "kind": 13,
+                            "range": { "start": { "line": 3, "character": 8 }, "end": { "line": 3, "character": 22 } },
+                            "selectionRange": { "start": { "line": 3, "character": 16 }, "end": { "line": 3, "character": 22 } },
+                        },
+                    ],
+                },
+            ],
+        },
+    ]))
+    .unwrap();
+
+    let flat = flatten_symbols(&uri, response.clone())
+        .into_iter()
+        .map(|s| format!("{:?} {}", s.container, s.name))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        flat,
+        vec![
+            "[] Contract",
+            "[\"Contract\"] withdraw",
+            "[\"Contract\", \"withdraw\"] target",
+        ]
+    );
+
+    let mut index = SymbolIndex::new();
+    index.insert(&uri, response.clone());
+    index.insert(&uri, response);
+    assert_eq!(index.symbols().len(), 3);
+
+    let location = |line, start, end| {
+        Location::new(
+            uri.clone(),
+            Range::new(Position::new(line, start), Position::new(line, end)),
+        )
+    };
+
+    assert_eq!(
+        index.enclosing(&location(5, 8, 9)).unwrap().name,
+        "withdraw"
+    );
+    assert_eq!(
+        index.enclosing(&location(8, 0, 1)).unwrap().name,
+        "Contract"
+    );
+    assert_eq!(index.at(&location(3, 16, 22)).unwrap().name, "target");
+    assert!(index.at(&location(4, 0, 1)).is_none());
+    assert_eq!(index.by_name("withdraw").count(), 1);
+}
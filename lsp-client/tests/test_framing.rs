@@ -0,0 +1,33 @@
+use lsp_client::framing::{encode_message, read_message};
+use tokio::io::BufReader;
+
+#[tokio::test]
+async fn test_framing() {
+    let first = r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#;
+    let second = r#"{"jsonrpc":"2.0","result":"ünïcödé","id":0}"#;
+    let input = format!(
+        "{}Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}",
+        encode_message(first),
+        second.len(),
+        second
+    );
+
+    let mut reader = BufReader::new(input.as_bytes());
+
+    assert_eq!(
+        read_message(&mut reader).await.unwrap().as_deref(),
+        Some(first)
+    );
+    assert_eq!(
+        read_message(&mut reader).await.unwrap().as_deref(),
+        Some(second)
+    );
+    assert_eq!(read_message(&mut reader).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_framing_truncated_header() {
+    let mut reader = BufReader::new("Content-Length: 10\r\n".as_bytes());
+
+    assert!(read_message(&mut reader).await.is_err());
+}
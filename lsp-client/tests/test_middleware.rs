@@ -0,0 +1,230 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use jsonrpc::types::JsonRpcError;
+use lsp_client::{
+    client::Client,
+    middleware::{Direction, Middleware, Response, UriRewriter},
+};
+use lsp_types::{
+    notification::DidSaveTextDocument, request::GotoDefinition, DidSaveTextDocumentParams,
+    GotoDefinitionParams, GotoDefinitionResponse, Position, TextDocumentIdentifier,
+    TextDocumentPositionParams, Url,
+};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Answers every request with a location in the requested document,
+/// and echoes every notification back to the client.
+async fn fake_lsp_server(
+    mut client_rx: UnboundedReceiver<String>,
+    server_tx: UnboundedSender<String>,
+    received: Arc<Mutex<Vec<Value>>>,
+) {
+    while let Some(msg) = client_rx.recv().await {
+        let (_header, content) = msg
+            .split_once("\r\n\r\n")
+            .expect("got message without header");
+        let msg: Value = serde_json::from_str(content).expect("got invalid json");
+        received.lock().unwrap().push(msg.clone());
+
+        let response = match msg.get("id") {
+            Some(id) => json!({
+                "jsonrpc": "2.0",
+                "result": {
+                    "uri": msg["params"]["textDocument"]["uri"],
+                    "range": {
+                        "start": { "line": 0, "character": 0 },
+                        "end": { "line": 0, "character": 1 },
+                    },
+                },
+                "id": id,
+            }),
+            None => msg,
+        };
+
+        server_tx
+            .send(response.to_string())
+            .expect("failed to send response");
+    }
+}
+
+struct Recorder {
+    name: &'static str,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl Middleware for Recorder {
+    fn before_request(&self, method: &str, _params: &mut Value) -> Result<Option<Response>> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("{} before {}", self.name, method));
+        Ok(None)
+    }
+
+    fn after_response(
+        &self,
+        method: &str,
+        _params: &Value,
+        _response: &mut Response,
+    ) -> Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("{} after {}", self.name, method));
+        Ok(())
+    }
+
+    fn on_notification(
+        &self,
+        direction: Direction,
+        method: &str,
+        params: &mut Value,
+    ) -> Result<()> {
+        self.calls.lock().unwrap().push(format!(
+            "{} {:?} {} {}",
+            self.name, direction, method, params["textDocument"]["uri"]
+        ));
+        Ok(())
+    }
+}
+
+struct FaultInjection;
+
+impl Middleware for FaultInjection {
+    fn before_request(&self, _method: &str, _params: &mut Value) -> Result<Option<Response>> {
+        Ok(Some(Err(JsonRpcError {
+            code: -32603,
+            message: "injected fault".to_string(),
+            data: None,
+        })))
+    }
+}
+
+fn definition_params(uri: &str) -> GotoDefinitionParams {
+    GotoDefinitionParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::parse(uri).unwrap(),
+            },
+            position: Position::new(0, 0),
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_chain() {
+    let (client_tx, client_rx) = unbounded_channel();
+    let (server_tx, server_rx) = unbounded_channel();
+    let received = Arc::new(Mutex::new(vec![]));
+    let calls = Arc::new(Mutex::new(vec![]));
+
+    let server_handle = tokio::spawn(fake_lsp_server(client_rx, server_tx, Arc::clone(&received)));
+
+    let client = Client::new(client_tx, server_rx)
+        .with_middleware(Arc::new(Recorder {
+            name: "outer",
+            calls: Arc::clone(&calls),
+        }))
+        .with_middleware(Arc::new(UriRewriter::new(
+            "file:///home/user/project",
+            "file:///mnt/project",
+        )))
+        .with_middleware(Arc::new(Recorder {
+            name: "inner",
+            calls: Arc::clone(&calls),
+        }));
+
+    let definition = client
+        .request::<GotoDefinition>(definition_params("file:///home/user/project/a.sol"))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let Some(GotoDefinitionResponse::Scalar(definition)) = definition else {
+        panic!("got unexpected definition: {:?}", definition);
+    };
+    assert_eq!(definition.uri.as_str(), "file:///home/user/project/a.sol");
+
+    client
+        .notify::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::parse("file:///home/user/project/a.sol").unwrap(),
+            },
+            text: None,
+        })
+        .unwrap();
+
+    // wait for the echoed notification
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    let received_uris = received
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|msg| msg["params"]["textDocument"]["uri"].to_string())
+        .collect::<Vec<_>>();
+
+    insta::assert_debug_snapshot!((received_uris, calls.lock().unwrap().clone()), @r###"
+    (
+        [
+            "\"file:///mnt/project/a.sol\"",
+            "\"file:///mnt/project/a.sol\"",
+        ],
+        [
+            "outer before textDocument/definition",
+            "inner before textDocument/definition",
+            "inner after textDocument/definition",
+            "outer after textDocument/definition",
+            "outer ToServer textDocument/didSave \"file:///home/user/project/a.sol\"",
+            "inner ToServer textDocument/didSave \"file:///mnt/project/a.sol\"",
+            "inner FromServer textDocument/didSave \"file:///mnt/project/a.sol\"",
+            "outer FromServer textDocument/didSave \"file:///home/user/project/a.sol\"",
+        ],
+    )
+    "###);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_middleware_short_circuit() {
+    let (client_tx, client_rx) = unbounded_channel();
+    let (server_tx, server_rx) = unbounded_channel();
+    let received = Arc::new(Mutex::new(vec![]));
+    let calls = Arc::new(Mutex::new(vec![]));
+
+    let server_handle = tokio::spawn(fake_lsp_server(client_rx, server_tx, Arc::clone(&received)));
+
+    let client = Client::new(client_tx, server_rx)
+        .with_middleware(Arc::new(Recorder {
+            name: "outer",
+            calls: Arc::clone(&calls),
+        }))
+        .with_middleware(Arc::new(FaultInjection))
+        .with_middleware(Arc::new(Recorder {
+            name: "inner",
+            calls: Arc::clone(&calls),
+        }));
+
+    let error = client
+        .request::<GotoDefinition>(definition_params("file:///a.sol"))
+        .await
+        .unwrap()
+        .unwrap_err();
+
+    assert_eq!(error.message, "injected fault");
+    assert!(received.lock().unwrap().is_empty());
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![
+            "outer before textDocument/definition",
+            "outer after textDocument/definition",
+        ]
+    );
+
+    server_handle.abort();
+}
@@ -1,51 +1,19 @@
-use lsp_client::clients;
+use lsp_client::{clients, presets::ServerPreset};
 use lsp_types::{
     notification::Initialized, request::Initialize, InitializeParams, InitializedParams,
 };
-use std::process::Stdio;
-use tokio::process::{Child, Command};
+use tokio::process::Child;
 
-fn start_rust_analyzer() -> Child {
-    Command::new("rust-analyzer")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+fn start_server(name: &str) -> Child {
+    ServerPreset::find(name)
+        .expect("missing server preset")
         .spawn()
-        .expect("failed to start rust analyzer")
-}
-
-fn start_solidity_ls() -> Child {
-    Command::new("solidity-ls")
-        .arg("--stdio")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("failed to start rust analyzer")
-}
-
-fn start_solidity_solc_lsp() -> Child {
-    Command::new("solc")
-        .arg("--lsp")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("failed to start rust analyzer")
-}
-
-fn start_python_language_server() -> Child {
-    Command::new("pyls")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("failed to start rust analyzer")
+        .unwrap_or_else(|_| panic!("failed to start {}", name))
 }
 
 macro_rules! test_server {
-    ($start_server_fn:ident) => {
-        let mut child = $start_server_fn();
+    ($name:literal) => {
+        let mut child = start_server($name);
 
         let stdin = child.stdin.take().unwrap();
         let stdout = child.stdout.take().unwrap();
@@ -69,20 +37,20 @@ macro_rules! test_server {
 
 #[tokio::test]
 async fn test_rust_analyzer() {
-    test_server!(start_rust_analyzer);
+    test_server!("rust-analyzer");
 }
 
 #[tokio::test]
 async fn test_solidity_ls() {
-    test_server!(start_solidity_ls);
+    test_server!("solidity-ls");
 }
 
 #[tokio::test]
 async fn test_solidity_solc_lsp() {
-    test_server!(start_solidity_solc_lsp);
+    test_server!("solc");
 }
 
 #[tokio::test]
 async fn test_python_language_server() {
-    test_server!(start_python_language_server);
+    test_server!("pyls");
 }
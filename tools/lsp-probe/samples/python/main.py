@@ -0,0 +1,13 @@
+import subprocess
+
+
+def run(command):
+    return subprocess.run(command, shell=True)
+#                         ^^^^^^^ probe parameter
+
+
+def main():
+    command = input()
+    run(command)
+#   ^^^ probe function call
+#       ^^^^^^^ probe local variable
@@ -0,0 +1,17 @@
+use std::process::Command;
+
+struct Config {
+    program: String,
+}
+
+fn run(config: &Config) {
+    Command::new(&config.program).spawn().unwrap();
+    //                   ^^^^^^^ probe struct field
+}
+
+fn main() {
+    let program = std::env::args().nth(1).unwrap();
+    let config = Config { program };
+    run(&config);
+    //  ^^^^^^^ probe local variable
+}
@@ -0,0 +1,20 @@
+pragma solidity ^0.8.19;
+
+contract Vault {
+    mapping(address => uint) balances;
+
+    function balanceOf(address owner) public view returns (uint) {
+        return balances[owner];
+    //         ^^^^^^^^ probe state variable
+    }
+
+    function withdraw() public {
+        uint bal = balanceOf(msg.sender);
+    //             ^^^^^^^^^ probe function call
+        address target = msg.sender;
+
+        (bool sent, ) = target.call{value: bal}("");
+    //                  ^^^^^^ probe local variable
+        require(sent);
+    }
+}
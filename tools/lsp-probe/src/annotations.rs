@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use lsp_types::{Location, Position, Range, Url};
+
+pub const MARKER: &str = "^ probe";
+
+/// A position to probe, marked by carets on the line below it:
+///
+/// ```text
+/// address target = msg.sender;
+/// //      ^^^^^^ probe target variable
+/// ```
+///
+/// The text after the marker is used as the label, defaulting to `file:line:column`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub label: String,
+    pub location: Location,
+}
+
+pub fn parse_annotations(path: &Path, content: &str) -> Result<Vec<Annotation>> {
+    let uri = Url::from_file_path(path).map_err(|_| anyhow!("failed to convert path to uri"))?;
+    let filename = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut annotations = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let Some(marker_end) = line.find(MARKER) else {
+            continue;
+        };
+
+        if i == 0 {
+            return Err(anyhow!("{}: got probe marker on first line", filename));
+        }
+
+        let start = line[..=marker_end]
+            .rfind(|c| c != '^')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let end = marker_end + 1;
+
+        let range = Range::new(
+            Position::new((i - 1) as u32, start as u32),
+            Position::new((i - 1) as u32, end as u32),
+        );
+
+        let label = line[marker_end + MARKER.len()..].trim();
+        let label = match label {
+            "" => format!("{}:{}:{}", filename, i, start + 1),
+            label => label.to_string(),
+        };
+
+        annotations.push(Annotation {
+            label,
+            location: Location::new(uri.clone(), range),
+        });
+    }
+
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotations() {
+        let content = r#"
+def foo(a, b):
+    return a + b
+#          ^ probe parameter
+print(foo(1, 2))
+#     ^^^ probe
+"#;
+
+        let annotations = parse_annotations(Path::new("/project/main.py"), content).unwrap();
+        let annotations = annotations
+            .iter()
+            .map(|a| {
+                let range = a.location.range;
+                format!(
+                    "{} {}:{}-{}",
+                    a.label, range.start.line, range.start.character, range.end.character
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(annotations, vec!["parameter 2:11-12", "main.py:5:7 4:6-9"]);
+    }
+}
@@ -0,0 +1,175 @@
+mod annotations;
+mod probe;
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use lsp_client::presets::{ServerPreset, PRESETS};
+use lsp_types::Url;
+
+use crate::{
+    annotations::parse_annotations,
+    probe::{language_id, Outcome, Probe, ProbeResult},
+};
+
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+struct Args {
+    preset: &'static ServerPreset,
+    project_dir: PathBuf,
+    timeout: Duration,
+    json: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let usage = "usage: lsp-probe <preset> <project_dir> [--timeout <seconds>] [--json]";
+
+    let preset = args.next().context(usage)?;
+    let Some(preset) = ServerPreset::find(&preset) else {
+        bail!(
+            "got unknown preset: {}, known presets: {:?}",
+            preset,
+            PRESETS.iter().map(|p| p.name).collect::<Vec<_>>()
+        );
+    };
+    let project_dir: PathBuf = args.next().context(usage)?.into();
+
+    let mut timeout = Duration::from_secs(10);
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--timeout" => {
+                timeout = Duration::from_secs(
+                    args.next()
+                        .context("missing --timeout seconds")?
+                        .parse()
+                        .context("got invalid --timeout seconds")?,
+                )
+            }
+            "--json" => json = true,
+            other => bail!("got unexpected argument: {}", other),
+        }
+    }
+
+    Ok(Args {
+        preset,
+        project_dir: project_dir
+            .canonicalize()
+            .context("failed to find project dir")?,
+        timeout,
+        json,
+    })
+}
+
+fn project_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                project_files(&path, files)?;
+            }
+        } else if language_id(&path).is_some() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_report(results: &[ProbeResult]) {
+    let mut summary = BTreeMap::<&str, [usize; 4]>::new();
+
+    println!(
+        "{:<32} {:<36} {:<10} outcome",
+        "target", "method", "advertised"
+    );
+    for result in results {
+        let (outcome, index) = match &result.outcome {
+            Outcome::Results { count } => (format!("{} result(s)", count), 0),
+            Outcome::Empty => ("empty".to_string(), 1),
+            Outcome::Error { message } => (format!("error: {}", message), 2),
+            Outcome::Timeout => ("timeout".to_string(), 3),
+        };
+        summary.entry(result.method).or_default()[index] += 1;
+
+        println!(
+            "{:<32} {:<36} {:<10} {}",
+            result.target, result.method, result.advertised, outcome
+        );
+    }
+
+    println!();
+    println!(
+        "{:<36} {:>8} {:>8} {:>8} {:>8}",
+        "method", "results", "empty", "error", "timeout"
+    );
+    for (method, [results, empty, error, timeout]) in summary {
+        println!(
+            "{:<36} {:>8} {:>8} {:>8} {:>8}",
+            method, results, empty, error, timeout
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let mut files = vec![];
+    project_files(&args.project_dir, &mut files).context("failed to get project files")?;
+    files.sort();
+
+    let probe = Probe::start(args.preset, &args.project_dir, args.timeout).await?;
+
+    let mut annotations = vec![];
+    for file in &files {
+        let content = std::fs::read_to_string(file).context("failed to read project file")?;
+        annotations.extend(parse_annotations(file, &content)?);
+
+        probe.open(
+            file,
+            language_id(file).expect("files are filtered by language"),
+        )?;
+    }
+
+    if annotations.is_empty() {
+        eprintln!("found no probe annotations, only probing documents");
+    }
+
+    let mut results = vec![];
+    for file in &files {
+        let target = file
+            .strip_prefix(&args.project_dir)
+            .unwrap_or(file)
+            .display()
+            .to_string();
+        let uri = Url::from_file_path(file).expect("project files are absolute");
+        results.extend(probe.probe_document(&target, &uri).await);
+    }
+    for annotation in &annotations {
+        results.extend(probe.probe_position(annotation).await);
+    }
+
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "server": args.preset.name,
+                "capabilities": probe.capabilities(),
+                "results": results,
+            }))
+            .context("failed to serialize results")?
+        );
+    } else {
+        print_report(&results);
+    }
+
+    Ok(())
+}
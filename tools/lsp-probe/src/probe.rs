@@ -0,0 +1,293 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use lsp_client::{client::Client, clients::child_client, presets::ServerPreset};
+use lsp_types::{
+    notification::{DidOpenTextDocument, Initialized},
+    request::{
+        CallHierarchyPrepare, DocumentHighlightRequest, DocumentSymbolRequest, GotoDeclaration,
+        GotoDefinition, GotoImplementation, GotoTypeDefinition, HoverRequest, Initialize,
+        References, Request, SemanticTokensFullRequest,
+    },
+    CallHierarchyPrepareParams, ClientCapabilities, DidOpenTextDocumentParams,
+    DocumentHighlightParams, DocumentSymbolClientCapabilities, DocumentSymbolParams,
+    GotoDefinitionParams, HoverParams, InitializeParams, InitializedParams, ReferenceContext,
+    ReferenceParams, SemanticTokensClientCapabilities, SemanticTokensClientCapabilitiesRequests,
+    SemanticTokensFullOptions, SemanticTokensParams, TextDocumentClientCapabilities,
+    TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, TokenFormat, Url,
+};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::annotations::Annotation;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Results { count: usize },
+    Empty,
+    Error { message: String },
+    Timeout,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProbeResult {
+    pub target: String,
+    pub method: &'static str,
+    /// Whether the server advertised the matching capability on initialization.
+    pub advertised: bool,
+    pub outcome: Outcome,
+}
+
+pub fn language_id(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "sol" => Some("solidity"),
+        "py" => Some("python"),
+        "rs" => Some("rust"),
+        "go" => Some("go"),
+        "ts" => Some("typescript"),
+        "tsx" => Some("typescriptreact"),
+        "js" => Some("javascript"),
+        "jsx" => Some("javascriptreact"),
+        _ => None,
+    }
+}
+
+fn capability_key(method: &str) -> &'static str {
+    match method {
+        GotoDefinition::METHOD => "definitionProvider",
+        GotoDeclaration::METHOD => "declarationProvider",
+        GotoTypeDefinition::METHOD => "typeDefinitionProvider",
+        GotoImplementation::METHOD => "implementationProvider",
+        References::METHOD => "referencesProvider",
+        HoverRequest::METHOD => "hoverProvider",
+        DocumentHighlightRequest::METHOD => "documentHighlightProvider",
+        CallHierarchyPrepare::METHOD => "callHierarchyProvider",
+        DocumentSymbolRequest::METHOD => "documentSymbolProvider",
+        SemanticTokensFullRequest::METHOD => "semanticTokensProvider",
+        _ => unreachable!("no capability for method {}", method),
+    }
+}
+
+fn classify(result: Value) -> Outcome {
+    let count = match &result {
+        Value::Null => 0,
+        Value::Array(items) => items.len(),
+        Value::Object(object) => match object.get("data") {
+            // semantic tokens are encoded as 5 integers per token
+            Some(Value::Array(data)) => data.len() / 5,
+            _ => 1,
+        },
+        _ => 1,
+    };
+
+    match count {
+        0 => Outcome::Empty,
+        count => Outcome::Results { count },
+    }
+}
+
+pub struct Probe {
+    client: Client,
+    handles: Vec<JoinHandle<()>>,
+    capabilities: Value,
+    timeout: Duration,
+}
+
+impl Drop for Probe {
+    fn drop(&mut self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+impl Probe {
+    pub async fn start(preset: &ServerPreset, root_dir: &Path, timeout: Duration) -> Result<Self> {
+        let child = preset
+            .spawn()
+            .context(format!("failed to start {}", preset.name))?;
+        let (client, handles) = child_client(child);
+
+        let capabilities = ClientCapabilities {
+            text_document: Some(TextDocumentClientCapabilities {
+                document_symbol: Some(DocumentSymbolClientCapabilities {
+                    hierarchical_document_symbol_support: Some(true),
+                    ..Default::default()
+                }),
+                semantic_tokens: Some(SemanticTokensClientCapabilities {
+                    requests: SemanticTokensClientCapabilitiesRequests {
+                        range: Some(true),
+                        full: Some(SemanticTokensFullOptions::Bool(true)),
+                    },
+                    formats: vec![TokenFormat::RELATIVE],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let init = tokio::time::timeout(
+            timeout,
+            client.request::<Initialize>(InitializeParams {
+                root_uri: Some(
+                    Url::from_file_path(root_dir)
+                        .map_err(|_| anyhow!("failed to convert root dir to url"))?,
+                ),
+                capabilities,
+                ..Default::default()
+            }),
+        )
+        .await
+        .context("timed out waiting for initialize response")???;
+
+        client.notify::<Initialized>(InitializedParams {})?;
+
+        Ok(Self {
+            client,
+            handles,
+            capabilities: serde_json::to_value(init.capabilities)
+                .context("failed to serialize capabilities")?,
+            timeout,
+        })
+    }
+
+    pub fn capabilities(&self) -> &Value {
+        &self.capabilities
+    }
+
+    pub fn open(&self, path: &Path, language_id: &str) -> Result<()> {
+        let text = std::fs::read_to_string(path).context("failed to read file")?;
+
+        self.client
+            .notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: Url::from_file_path(path)
+                        .map_err(|_| anyhow!("failed to convert path to url"))?,
+                    language_id: language_id.to_string(),
+                    version: 0,
+                    text,
+                },
+            })
+    }
+
+    pub async fn probe_position(&self, annotation: &Annotation) -> Vec<ProbeResult> {
+        let position = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: annotation.location.uri.clone(),
+            },
+            position: annotation.location.range.start,
+        };
+        let goto = GotoDefinitionParams {
+            text_document_position_params: position.clone(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let target = &annotation.label;
+
+        vec![
+            self.probe::<GotoDefinition>(target, goto.clone()).await,
+            self.probe::<GotoDeclaration>(target, goto.clone()).await,
+            self.probe::<GotoTypeDefinition>(target, goto.clone()).await,
+            self.probe::<GotoImplementation>(target, goto).await,
+            self.probe::<References>(
+                target,
+                ReferenceParams {
+                    text_document_position: position.clone(),
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                    context: ReferenceContext {
+                        include_declaration: false,
+                    },
+                },
+            )
+            .await,
+            self.probe::<HoverRequest>(
+                target,
+                HoverParams {
+                    text_document_position_params: position.clone(),
+                    work_done_progress_params: Default::default(),
+                },
+            )
+            .await,
+            self.probe::<DocumentHighlightRequest>(
+                target,
+                DocumentHighlightParams {
+                    text_document_position_params: position.clone(),
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                },
+            )
+            .await,
+            self.probe::<CallHierarchyPrepare>(
+                target,
+                CallHierarchyPrepareParams {
+                    text_document_position_params: position,
+                    work_done_progress_params: Default::default(),
+                },
+            )
+            .await,
+        ]
+    }
+
+    pub async fn probe_document(&self, target: &str, uri: &Url) -> Vec<ProbeResult> {
+        let text_document = TextDocumentIdentifier { uri: uri.clone() };
+
+        vec![
+            self.probe::<DocumentSymbolRequest>(
+                target,
+                DocumentSymbolParams {
+                    text_document: text_document.clone(),
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                },
+            )
+            .await,
+            self.probe::<SemanticTokensFullRequest>(
+                target,
+                SemanticTokensParams {
+                    text_document,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                },
+            )
+            .await,
+        ]
+    }
+
+    async fn probe<R>(&self, target: &str, params: R::Params) -> ProbeResult
+    where
+        R: Request,
+    {
+        let outcome =
+            match tokio::time::timeout(self.timeout, self.client.request::<R>(params)).await {
+                Err(_) => Outcome::Timeout,
+                Ok(Err(error)) => Outcome::Error {
+                    message: format!("{:#}", error),
+                },
+                Ok(Ok(Err(error))) => Outcome::Error {
+                    message: error.to_string(),
+                },
+                Ok(Ok(Ok(result))) => match serde_json::to_value(result) {
+                    Ok(result) => classify(result),
+                    Err(error) => Outcome::Error {
+                        message: error.to_string(),
+                    },
+                },
+            };
+
+        let advertised = !matches!(
+            self.capabilities.get(capability_key(R::METHOD)),
+            None | Some(Value::Null) | Some(Value::Bool(false))
+        );
+
+        ProbeResult {
+            target: target.to_string(),
+            method: R::METHOD,
+            advertised,
+            outcome,
+        }
+    }
+}
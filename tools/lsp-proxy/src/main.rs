@@ -0,0 +1,117 @@
+mod traffic;
+
+use std::{
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Context, Result};
+use lsp_client::framing::{encode_message, read_message};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    process::Command,
+};
+use traffic::{Direction, TrafficLog};
+
+type SharedLog = Arc<Mutex<TrafficLog<Box<dyn Write + Send>>>>;
+
+struct Args {
+    log: Option<PathBuf>,
+    pretty: bool,
+    server: String,
+    server_args: Vec<String>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut log = None;
+    let mut pretty = false;
+
+    loop {
+        match args.next().as_deref() {
+            Some("--log") => log = Some(args.next().context("missing --log path")?.into()),
+            Some("--pretty") => pretty = true,
+            Some("--") => break,
+            Some(other) => bail!("got unexpected argument: {}", other),
+            None => bail!("usage: lsp-proxy [--log <file>] [--pretty] -- <server> [args...]"),
+        }
+    }
+
+    let server = args.next().context("missing server command")?;
+
+    Ok(Args {
+        log,
+        pretty,
+        server,
+        server_args: args.collect(),
+    })
+}
+
+async fn forward<R, W>(reader: R, mut writer: W, direction: Direction, log: SharedLog) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    while let Some(content) = read_message(&mut reader).await? {
+        log.lock()
+            .expect("failed to acquire lock")
+            .record(direction, &content)?;
+
+        writer
+            .write_all(encode_message(&content).as_bytes())
+            .await
+            .context("failed to forward message")?;
+        writer.flush().await.context("failed to flush message")?;
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let mut server = Command::new(&args.server)
+        .args(&args.server_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context(format!("failed to start server: {}", args.server))?;
+
+    let log_writer: Box<dyn Write + Send> = match &args.log {
+        Some(path) => Box::new(File::create(path).context("failed to create log file")?),
+        None => Box::new(std::io::stderr()),
+    };
+    let log: SharedLog = Arc::new(Mutex::new(TrafficLog::new(log_writer, args.pretty)));
+
+    let server_stdin = server.stdin.take().unwrap();
+    let server_stdout = server.stdout.take().unwrap();
+
+    let editor_to_server = tokio::spawn(forward(
+        tokio::io::stdin(),
+        server_stdin,
+        Direction::EditorToServer,
+        Arc::clone(&log),
+    ));
+    let server_to_editor = tokio::spawn(forward(
+        server_stdout,
+        tokio::io::stdout(),
+        Direction::ServerToEditor,
+        Arc::clone(&log),
+    ));
+
+    let status = server.wait().await.context("failed to wait for server")?;
+
+    // the server might have written messages right before exiting
+    server_to_editor
+        .await
+        .context("failed to join server output task")??;
+    editor_to_server.abort();
+
+    std::process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    EditorToServer,
+    ServerToEditor,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::EditorToServer => "editor->server",
+            Direction::ServerToEditor => "server->editor",
+        }
+    }
+
+    fn reverse(&self) -> Self {
+        match self {
+            Direction::EditorToServer => Direction::ServerToEditor,
+            Direction::ServerToEditor => Direction::EditorToServer,
+        }
+    }
+}
+
+struct PendingRequest {
+    method: String,
+    params: Value,
+    sent: Instant,
+}
+
+/// Writes every message as a JSON line, pairing responses with the request
+/// they answer.
+///
+/// Requests can go both ways (e.g. `workspace/configuration` is sent by the
+/// server), so pending requests are keyed by direction and id.
+pub struct TrafficLog<W: Write> {
+    writer: W,
+    pretty: bool,
+    pending: HashMap<(Direction, String), PendingRequest>,
+}
+
+impl<W: Write> TrafficLog<W> {
+    pub fn new(writer: W, pretty: bool) -> Self {
+        Self {
+            writer,
+            pretty,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, direction: Direction, content: &str) -> Result<()> {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("got time before unix epoch")?
+            .as_millis();
+
+        // log invalid messages too, the editor/server might be the broken party
+        let message = serde_json::from_str::<Value>(content)
+            .unwrap_or_else(|_| Value::String(content.to_string()));
+
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let id = message.get("id").map(Value::to_string);
+
+        let mut entry = json!({
+            "timestamp_ms": timestamp_ms,
+            "direction": direction.as_str(),
+        });
+
+        match (method, id) {
+            (Some(method), Some(id)) => {
+                entry["kind"] = json!("request");
+                entry["method"] = json!(method);
+                self.pending.insert(
+                    (direction, id),
+                    PendingRequest {
+                        method,
+                        params: message.get("params").cloned().unwrap_or(Value::Null),
+                        sent: Instant::now(),
+                    },
+                );
+            }
+            (Some(method), None) => {
+                entry["kind"] = json!("notification");
+                entry["method"] = json!(method);
+            }
+            (None, Some(id)) => {
+                entry["kind"] = json!("response");
+                if let Some(request) = self.pending.remove(&(direction.reverse(), id.clone())) {
+                    let latency_ms = request.sent.elapsed().as_secs_f64() * 1000.0;
+                    entry["method"] = json!(request.method);
+                    entry["latency_ms"] = json!(latency_ms);
+
+                    if self.pretty {
+                        Self::print_pair(direction, &id, &request, &message, latency_ms)?;
+                    }
+                }
+            }
+            (None, None) => entry["kind"] = json!("unknown"),
+        }
+
+        entry["message"] = message;
+
+        writeln!(self.writer, "{}", entry).context("failed to write log entry")?;
+        self.writer.flush().context("failed to flush log")
+    }
+
+    fn print_pair(
+        direction: Direction,
+        id: &str,
+        request: &PendingRequest,
+        response: &Value,
+        latency_ms: f64,
+    ) -> Result<()> {
+        let response = response
+            .get("result")
+            .or_else(|| response.get("error"))
+            .unwrap_or(response);
+
+        eprintln!(
+            "[{}] {} #{} ({:.1}ms)\n--> {}\n<-- {}\n",
+            direction.reverse().as_str(),
+            request.method,
+            id,
+            latency_ms,
+            serde_json::to_string_pretty(&request.params).context("failed to format params")?,
+            serde_json::to_string_pretty(response).context("failed to format response")?,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing() {
+        let mut log = TrafficLog::new(vec![], false);
+
+        let messages = [
+            (
+                Direction::EditorToServer,
+                r#"{"jsonrpc":"2.0","method":"textDocument/hover","params":{},"id":1}"#,
+            ),
+            (
+                Direction::ServerToEditor,
+                r#"{"jsonrpc":"2.0","method":"workspace/configuration","params":{},"id":1}"#,
+            ),
+            (
+                Direction::EditorToServer,
+                r#"{"jsonrpc":"2.0","result":[],"id":1}"#,
+            ),
+            (
+                Direction::ServerToEditor,
+                r#"{"jsonrpc":"2.0","method":"window/logMessage","params":{}}"#,
+            ),
+            (
+                Direction::ServerToEditor,
+                r#"{"jsonrpc":"2.0","result":null,"id":1}"#,
+            ),
+            (Direction::ServerToEditor, r#"not json"#),
+        ];
+
+        for (direction, content) in messages {
+            log.record(direction, content).unwrap();
+        }
+
+        let entries = String::from_utf8(log.writer)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let entry: Value = serde_json::from_str(line).unwrap();
+                assert!(entry["timestamp_ms"].is_u64());
+                assert_eq!(entry["latency_ms"].is_f64(), entry["kind"] == "response");
+
+                format!(
+                    "{} {} {}",
+                    entry["direction"], entry["kind"], entry["method"]
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                r#""editor->server" "request" "textDocument/hover""#,
+                r#""server->editor" "request" "workspace/configuration""#,
+                r#""editor->server" "response" "workspace/configuration""#,
+                r#""server->editor" "notification" "window/logMessage""#,
+                r#""server->editor" "response" "textDocument/hover""#,
+                r#""server->editor" "unknown" null"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+; Packages span files, so names not defined in a file are looked up in
+; the top level of all files.
+[
+  (function_declaration)
+  (method_declaration)
+  (func_literal)
+  (block)
+  (if_statement)
+  (for_statement)
+  (expression_switch_statement)
+  (type_switch_statement)
+  (select_statement)
+  (communication_case)
+] @local.scope
+
+(function_declaration name: (identifier) @local.definition)
+(method_declaration name: (field_identifier) @local.definition)
+(type_spec name: (type_identifier) @local.definition)
+(field_declaration (field_identifier) @local.definition)
+(method_spec name: (field_identifier) @local.definition)
+
+(parameter_declaration (identifier) @local.definition)
+(variadic_parameter_declaration name: (identifier) @local.definition)
+(var_spec (identifier) @local.definition)
+(const_spec (identifier) @local.definition)
+(short_var_declaration left: (expression_list (identifier) @local.definition))
+(range_clause left: (expression_list (identifier) @local.definition))
+(type_switch_statement alias: (expression_list (identifier) @local.definition))
+(receive_statement left: (expression_list (identifier) @local.definition))
+
+(import_spec path: (interpreted_string_literal) @local.import)
+
+(identifier) @local.reference
+(field_identifier) @local.reference
+(type_identifier) @local.reference
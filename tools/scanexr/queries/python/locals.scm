@@ -0,0 +1,37 @@
+; Classes aren't scopes, so methods are found by name like functions.
+[
+  (function_definition)
+  (lambda)
+  (list_comprehension)
+  (dictionary_comprehension)
+  (set_comprehension)
+  (generator_expression)
+] @local.scope
+
+(function_definition name: (identifier) @local.definition)
+(class_definition name: (identifier) @local.definition)
+
+(parameters (identifier) @local.definition)
+(lambda_parameters (identifier) @local.definition)
+(default_parameter name: (identifier) @local.definition)
+(typed_parameter (identifier) @local.definition)
+(typed_default_parameter name: (identifier) @local.definition)
+(list_splat_pattern (identifier) @local.definition)
+(dictionary_splat_pattern (identifier) @local.definition)
+
+(assignment left: (identifier) @local.definition)
+(assignment left: (pattern_list (identifier) @local.definition))
+(assignment left: (tuple_pattern (identifier) @local.definition))
+(augmented_assignment left: (identifier) @local.definition)
+(for_statement left: (identifier) @local.definition)
+(for_statement left: (pattern_list (identifier) @local.definition))
+(for_in_clause left: (identifier) @local.definition)
+(with_item value: (as_pattern alias: (as_pattern_target (identifier) @local.definition)))
+(except_clause (as_pattern alias: (as_pattern_target (identifier) @local.definition)))
+(named_expression name: (identifier) @local.definition)
+
+(import_statement name: (dotted_name) @local.import)
+(import_from_statement module_name: (dotted_name) @local.import)
+(import_from_statement module_name: (relative_import) @local.import)
+
+(identifier) @local.reference
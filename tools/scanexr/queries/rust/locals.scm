@@ -0,0 +1,46 @@
+; Impls and traits aren't scopes, so methods are found by name like
+; functions.
+[
+  (function_item)
+  (closure_expression)
+  (block)
+  (match_arm)
+  (for_expression)
+  (if_expression)
+  (while_expression)
+] @local.scope
+
+(function_item name: (identifier) @local.definition)
+(function_signature_item name: (identifier) @local.definition)
+(struct_item name: (type_identifier) @local.definition)
+(enum_item name: (type_identifier) @local.definition)
+(trait_item name: (type_identifier) @local.definition)
+(type_item name: (type_identifier) @local.definition)
+(const_item name: (identifier) @local.definition)
+(static_item name: (identifier) @local.definition)
+(field_declaration name: (field_identifier) @local.definition)
+
+(parameter pattern: (identifier) @local.definition)
+(self_parameter (self) @local.definition)
+(closure_parameters (identifier) @local.definition)
+(let_declaration pattern: (identifier) @local.definition)
+(let_condition pattern: (identifier) @local.definition)
+(for_expression pattern: (identifier) @local.definition)
+(tuple_pattern (identifier) @local.definition)
+(tuple_struct_pattern "(" (identifier) @local.definition)
+(slice_pattern (identifier) @local.definition)
+(field_pattern (shorthand_field_identifier) @local.definition)
+(field_pattern pattern: (identifier) @local.definition)
+(ref_pattern (identifier) @local.definition)
+(mut_pattern (identifier) @local.definition)
+(captured_pattern (identifier) @local.definition)
+
+(mod_item name: (identifier) @local.import !body)
+(use_declaration argument: (scoped_identifier path: (_) @local.import))
+(use_declaration argument: (scoped_use_list path: (_) @local.import))
+
+(identifier) @local.reference
+(field_identifier) @local.reference
+(type_identifier) @local.reference
+(shorthand_field_identifier) @local.reference
+(self) @local.reference
@@ -0,0 +1,37 @@
+; Classes aren't scopes, so methods are found by name like functions.
+[
+  (statement_block)
+  (function_declaration)
+  (generator_function_declaration)
+  (function_expression)
+  (generator_function)
+  (arrow_function)
+  (method_definition)
+  (for_statement)
+  (for_in_statement)
+  (catch_clause)
+] @local.scope
+
+(function_declaration name: (identifier) @local.definition)
+(generator_function_declaration name: (identifier) @local.definition)
+(class_declaration name: (type_identifier) @local.definition)
+(method_definition name: (property_identifier) @local.definition)
+(public_field_definition name: (property_identifier) @local.definition)
+
+(variable_declarator name: (identifier) @local.definition)
+(variable_declarator name: (object_pattern (shorthand_property_identifier_pattern) @local.definition))
+(variable_declarator name: (object_pattern (pair_pattern value: (identifier) @local.definition)))
+(variable_declarator name: (array_pattern (identifier) @local.definition))
+
+(required_parameter pattern: (identifier) @local.definition)
+(optional_parameter pattern: (identifier) @local.definition)
+(arrow_function parameter: (identifier) @local.definition)
+(for_in_statement left: (identifier) @local.definition)
+(catch_clause parameter: (identifier) @local.definition)
+
+(import_statement source: (string) @local.import)
+
+(identifier) @local.reference
+(property_identifier) @local.reference
+(shorthand_property_identifier) @local.reference
+(type_identifier) @local.reference
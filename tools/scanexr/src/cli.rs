@@ -0,0 +1,604 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use futures::future::try_join_all;
+use lsp_client::cache::CachedMetrics;
+use serde_json::{json, Value};
+
+use crate::{
+    engine::{Backend, EngineOptions, GuardMode, SourceReport, Strategy},
+    language_provider::{ScanMode, ScanOutput, Trace, TraceEnd},
+    registry::{DynLanguage, LanguageRegistry},
+    rules::{find_findings, load_rules, starts_and_sinks, Finding, Rule, RuleMatches},
+    utils::{location_json, visit_dirs},
+};
+
+fn trace_json(trace: &Trace) -> Value {
+    json!({
+        "steps": trace.steps.iter().map(location_json).collect::<Vec<_>>(),
+        "sink": trace.sink().map(location_json),
+        "cycle": trace.end == TraceEnd::Cycle,
+        "truncated": trace.end == TraceEnd::Truncated,
+        "sanitized": matches!(trace.end, TraceEnd::Sanitized(_)),
+        "guards": trace.guards.iter().map(location_json).collect::<Vec<_>>(),
+    })
+}
+
+fn finding_json(finding: &Finding) -> Value {
+    json!({
+        "rule": finding.rule.id,
+        "severity": finding.rule.severity,
+        "message": finding.rule.message,
+        "cwe": finding.rule.cwe,
+        "swc": finding.rule.swc,
+        "sanitized": finding.sanitized,
+        "stacktrace": trace_json(finding.trace),
+    })
+}
+
+fn source_json(report: &SourceReport) -> Value {
+    json!({
+        "start": location_json(&report.start),
+        "traces": report.traces,
+        "expanded": report.expanded,
+        "exceeded": report.exceeded,
+        "elapsed_ms": report.elapsed_ms,
+    })
+}
+
+/// Tag an entry of the report with the language it was found in.
+fn with_language(mut value: Value, language: &str) -> Value {
+    value["language"] = language.into();
+    value
+}
+
+/// Files and rules of one language of a scanned project.
+struct LanguageScan<'r> {
+    language: &'r DynLanguage,
+    files: Vec<PathBuf>,
+    rules: Vec<Rule>,
+    rule_matches: Vec<RuleMatches>,
+}
+
+enum GraphFormat {
+    Json,
+    Dot,
+    Mermaid,
+}
+
+fn warn_exceeded(sources: &[SourceReport]) {
+    for report in sources {
+        if !report.exceeded.is_empty() {
+            eprintln!(
+                "budget exceeded for start at {}:{}: {:?}",
+                report.start.uri.path(),
+                report.start.range.start.line + 1,
+                report.exceeded
+            );
+        }
+    }
+}
+
+/// Write `metrics` to `metrics_path`, snapshots by language.
+fn write_metrics(
+    metrics_path: Option<PathBuf>,
+    metrics: &BTreeMap<&str, &CachedMetrics>,
+) -> Result<()> {
+    if let Some(metrics_path) = metrics_path {
+        std::fs::write(
+            metrics_path,
+            serde_json::to_string_pretty(metrics).context("failed to serialize metrics")?,
+        )
+        .context("failed to write metrics")?;
+    }
+
+    Ok(())
+}
+
+/// Files and rules of each of `languages` in `project_files`, all rules
+/// of a language being its default rules without `loaded_rules`.
+///
+/// Unless `all_dirs`, files a language skips by default (e.g. tests or
+/// dependencies) are left out.
+fn plan_scans<'r>(
+    languages: Vec<&'r DynLanguage>,
+    root_dir: &Path,
+    project_files: &[PathBuf],
+    loaded_rules: Option<&[Rule]>,
+    all_dirs: bool,
+) -> Result<Vec<LanguageScan<'r>>> {
+    let mut scans = vec![];
+    for language in languages {
+        let rules = match loaded_rules {
+            Some(rules) => rules
+                .iter()
+                .filter(|rule| rule.language == language.name())
+                .cloned()
+                .collect(),
+            None => language.default_rules()?,
+        };
+        // files of no scanned language, e.g. READMEs or build artifacts, are left out
+        let files = match all_dirs {
+            true => project_files
+                .iter()
+                .filter(|f| language.is_project_file(f))
+                .cloned()
+                .collect::<Vec<_>>(),
+            false => language.project_files(root_dir, project_files)?,
+        };
+        let rule_matches = language.match_rules(&rules, &files)?;
+
+        scans.push(LanguageScan {
+            language,
+            files,
+            rules,
+            rule_matches,
+        });
+    }
+
+    Ok(scans)
+}
+
+/// Report of the `outputs` of all `scans`, each entry tagged with its
+/// language, and the metrics of each language. Stacktraces are left out
+/// when they were streamed.
+fn merge_outputs<'a>(
+    scans: &'a [LanguageScan],
+    outputs: &'a [ScanOutput],
+    mode: ScanMode,
+    stream: bool,
+) -> (Value, BTreeMap<&'a str, &'a CachedMetrics>) {
+    let mut json_stacktraces = vec![];
+    let mut json_findings = vec![];
+    let mut json_sources = vec![];
+    let mut metrics = BTreeMap::new();
+    for (scan, output) in scans.iter().zip(outputs) {
+        let language = scan.language.name();
+        warn_exceeded(&output.sources);
+
+        json_findings.extend(
+            find_findings(&scan.rules, &scan.rule_matches, &output.paths)
+                .iter()
+                .map(|finding| with_language(finding_json(finding), language)),
+        );
+        json_sources.extend(
+            output
+                .sources
+                .iter()
+                .map(|report| with_language(source_json(report), language)),
+        );
+        if !stream {
+            json_stacktraces.extend(
+                output
+                    .select(mode)
+                    .into_iter()
+                    .map(|trace| with_language(trace_json(trace), language)),
+            );
+        }
+        metrics.insert(language, &output.metrics);
+    }
+
+    let report = json!({
+        "stacktraces": json_stacktraces,
+        "findings": json_findings,
+        "sources": json_sources,
+    });
+
+    (report, metrics)
+}
+
+fn list_languages(registry: &LanguageRegistry) {
+    for language in registry.languages() {
+        let mut names = vec![language.name()];
+        names.extend(language.aliases());
+        println!("{}: {}", names.join(", "), language.extensions().join(" "));
+    }
+}
+
+/// Run the command line interface with the languages of `registry`, so
+/// crates adding languages can ship their own binary.
+///
+/// `args` don't include the binary name.
+pub async fn run(
+    registry: &LanguageRegistry,
+    args: impl IntoIterator<Item = String>,
+) -> Result<()> {
+    let mut args = args.into_iter();
+    let language = args.next().context("missing language")?;
+    if language == "languages" {
+        list_languages(registry);
+        return Ok(());
+    }
+    let root_dir: PathBuf = args.next().context("missing project dir")?.trim().into();
+
+    let mut metrics_path: Option<PathBuf> = None;
+    let mut mode = ScanMode::All;
+    let mut options = EngineOptions::default();
+    let mut stream = false;
+    let mut all_dirs = false;
+    let mut graph_format = None;
+    let mut rules_dir: Option<PathBuf> = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strategy" => {
+                options.strategy = match args.next().context("missing --strategy value")?.as_str() {
+                    "bfs" => Strategy::BreadthFirst,
+                    "dfs" => Strategy::DepthFirst,
+                    "best" => Strategy::BestFirst,
+                    other => bail!("got unsupported strategy: {}", other),
+                }
+            }
+            "--max-depth" => {
+                options.max_depth = Some(
+                    args.next()
+                        .context("missing --max-depth value")?
+                        .parse()
+                        .context("failed to parse --max-depth")?,
+                )
+            }
+            "--max-paths" => {
+                options.max_paths = Some(
+                    args.next()
+                        .context("missing --max-paths value")?
+                        .parse()
+                        .context("failed to parse --max-paths")?,
+                )
+            }
+            "--timeout" => {
+                options.timeout = Some(Duration::from_secs_f64(
+                    args.next()
+                        .context("missing --timeout seconds")?
+                        .parse()
+                        .context("failed to parse --timeout")?,
+                ))
+            }
+            "--jobs" => {
+                options.concurrency = args
+                    .next()
+                    .context("missing --jobs value")?
+                    .parse()
+                    .context("failed to parse --jobs")?
+            }
+            "--guards" => {
+                options.guards = match args.next().context("missing --guards value")?.as_str() {
+                    "mark" => GuardMode::Mark,
+                    "prune" => GuardMode::Prune,
+                    "ignore" => GuardMode::Ignore,
+                    other => bail!("got unsupported guards mode: {}", other),
+                }
+            }
+            "--backend" => {
+                options.backend = match args.next().context("missing --backend value")?.as_str() {
+                    "auto" => Backend::Auto,
+                    "lsp" => Backend::LanguageServer,
+                    "compiler" => Backend::Compiler,
+                    "tree-sitter" => Backend::TreeSitter,
+                    other => bail!("got unsupported backend: {}", other),
+                }
+            }
+            "--stream" => stream = true,
+            "--all-dirs" => all_dirs = true,
+            "--graph" => {
+                graph_format = match args.next().context("missing --graph format")?.as_str() {
+                    "json" => Some(GraphFormat::Json),
+                    "dot" => Some(GraphFormat::Dot),
+                    "mermaid" => Some(GraphFormat::Mermaid),
+                    other => bail!("got unsupported graph format: {}", other),
+                }
+            }
+            "--mode" => {
+                mode = match args.next().context("missing --mode value")?.as_str() {
+                    "all" => ScanMode::All,
+                    "sinks" => ScanMode::Sinks,
+                    "unreached" => ScanMode::Unreached,
+                    other => bail!("got unsupported mode: {}", other),
+                }
+            }
+            "--rules" => rules_dir = Some(args.next().context("missing --rules directory")?.into()),
+            "--metrics" => {
+                metrics_path = Some(args.next().context("missing --metrics path")?.into())
+            }
+            other => bail!("got unexpected argument: {}", other),
+        }
+    }
+
+    if stream && mode == ScanMode::Unreached {
+        bail!("--stream can't be used with --mode unreached");
+    }
+
+    let root_dir = root_dir.canonicalize().unwrap();
+    let mut project_files = vec![];
+    visit_dirs(root_dir.as_path(), &mut |f| project_files.push(f.path()))
+        .context("failed to get project files")?;
+
+    let supported = || {
+        registry
+            .languages()
+            .map(|language| language.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let languages = match language.trim() {
+        "auto" => {
+            let languages = registry.detect(&project_files);
+            if languages.is_empty() {
+                bail!("no files of a supported language in {}", root_dir.display());
+            }
+            eprintln!(
+                "detected languages: {}",
+                languages
+                    .iter()
+                    .map(|language| language.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            languages
+        }
+        names => names
+            .split(',')
+            .map(str::trim)
+            .map(|name| {
+                registry.get(name).with_context(|| {
+                    format!(
+                        "got unsupported language: {} (supported: {})",
+                        name,
+                        supported()
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    if graph_format.is_some() && languages.len() > 1 {
+        bail!("--graph can't be used with more than one language");
+    }
+
+    let loaded_rules = rules_dir
+        .map(|rules_dir| load_rules(&rules_dir))
+        .transpose()?;
+    let scans = plan_scans(
+        languages,
+        &root_dir,
+        &project_files,
+        loaded_rules.as_deref(),
+        all_dirs,
+    )?;
+
+    if let Some(graph_format) = graph_format {
+        let scan = &scans[0];
+        let (start_locations, end_locations) = starts_and_sinks(&scan.rule_matches);
+        let output = scan
+            .language
+            .find_graph(
+                &root_dir,
+                scan.files.clone(),
+                start_locations,
+                &end_locations,
+                &options,
+            )
+            .await?;
+
+        warn_exceeded(&output.sources);
+
+        match graph_format {
+            GraphFormat::Json => {
+                let mut json_graph = output.graph.to_json();
+                json_graph["sources"] = output.sources.iter().map(source_json).collect();
+                println!("{}", serde_json::to_string_pretty(&json_graph).unwrap());
+            }
+            GraphFormat::Dot => println!("{}", output.graph.to_dot()),
+            GraphFormat::Mermaid => println!("{}", output.graph.to_mermaid()),
+        }
+
+        return write_metrics(
+            metrics_path,
+            &BTreeMap::from([(scan.language.name(), &output.metrics)]),
+        );
+    }
+
+    // one language server session per language, all running concurrently
+    let (root_dir, options) = (&root_dir, &options);
+    let outputs = try_join_all(scans.iter().map(|scan| async move {
+        let language = scan.language.name();
+        let (start_locations, end_locations) = starts_and_sinks(&scan.rule_matches);
+        scan.language
+            .find_paths(
+                root_dir,
+                scan.files.clone(),
+                start_locations,
+                &end_locations,
+                options,
+                &mut |trace| {
+                    if stream && (mode == ScanMode::All || trace.sink().is_some()) {
+                        println!(
+                            "{}",
+                            json!({ "language": language, "stacktrace": trace_json(trace) })
+                        );
+                    }
+                },
+            )
+            .await
+            .with_context(|| format!("failed to scan {} files", language))
+    }))
+    .await?;
+
+    let (report, metrics) = merge_outputs(&scans, &outputs, mode, stream);
+
+    if stream {
+        println!("{}", json!({ "findings": report["findings"] }));
+        println!("{}", json!({ "sources": report["sources"] }));
+    } else {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    }
+
+    write_metrics(metrics_path, &metrics)
+}
+
+#[cfg(all(test, feature = "solidity", feature = "python"))]
+mod tests {
+    use lsp_types::{Location, Position, Range, Url};
+
+    use super::*;
+    use crate::rules::parse_rules;
+
+    const RULES: &str = r#"
+[[rule]]
+id = "python-names"
+language = "python"
+message = "python name"
+
+[rule.source]
+query = "(identifier) @id"
+capture = "id"
+
+[rule.sink]
+query = "(identifier) @id"
+capture = "id"
+
+[[rule]]
+id = "solidity-names"
+language = "solidity"
+message = "solidity name"
+
+[rule.source]
+query = "(identifier) @id"
+capture = "id"
+
+[rule.sink]
+query = "(identifier) @id"
+capture = "id"
+"#;
+
+    fn setup(files: &[&str]) -> (tempfile::TempDir, Vec<PathBuf>) {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = files
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, "x").unwrap();
+                path
+            })
+            .collect();
+
+        (dir, paths)
+    }
+
+    fn names(scan: &LanguageScan, root_dir: &Path) -> Vec<String> {
+        scan.files
+            .iter()
+            .map(|f| f.strip_prefix(root_dir).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_scans() {
+        let (dir, files) = setup(&[
+            "contracts/Vault.sol",
+            "test/Vault.t.sol",
+            "scripts/deploy.py",
+            "README.md",
+        ]);
+        let registry = LanguageRegistry::builtin();
+        let languages = registry.detect(&files);
+        let rules = parse_rules(RULES).unwrap();
+
+        let scans = plan_scans(languages.clone(), dir.path(), &files, Some(&rules), false).unwrap();
+        assert_eq!(scans.len(), 2);
+        for scan in &scans {
+            assert_eq!(scan.rules.len(), 1);
+            assert_eq!(scan.rules[0].language, scan.language.name());
+            assert_eq!(scan.rule_matches.len(), 1);
+        }
+
+        let solidity = scans
+            .iter()
+            .find(|s| s.language.name() == "solidity")
+            .unwrap();
+        assert_eq!(names(solidity, dir.path()), vec!["contracts/Vault.sol"]);
+        let python = scans
+            .iter()
+            .find(|s| s.language.name() == "python")
+            .unwrap();
+        assert_eq!(names(python, dir.path()), vec!["scripts/deploy.py"]);
+
+        let scans = plan_scans(languages, dir.path(), &files, Some(&rules), true).unwrap();
+        let solidity = scans
+            .iter()
+            .find(|s| s.language.name() == "solidity")
+            .unwrap();
+        let mut solidity_files = names(solidity, dir.path());
+        solidity_files.sort();
+        assert_eq!(
+            solidity_files,
+            vec!["contracts/Vault.sol", "test/Vault.t.sol"]
+        );
+    }
+
+    #[test]
+    fn test_merge_outputs() {
+        let registry = LanguageRegistry::builtin();
+        let rules = parse_rules(RULES).unwrap();
+        let location = |file: &str| {
+            Location::new(
+                Url::parse(&format!("file:///project/{}", file)).unwrap(),
+                Range::new(Position::new(0, 0), Position::new(0, 1)),
+            )
+        };
+
+        let scans = ["solidity", "python"]
+            .into_iter()
+            .zip([("Vault.sol", 1), ("deploy.py", 0)])
+            .map(|(name, (file, rule))| LanguageScan {
+                language: registry.get(name).unwrap(),
+                files: vec![],
+                rules: vec![rules[rule].clone()],
+                rule_matches: vec![RuleMatches {
+                    sources: vec![location(file)],
+                    sinks: vec![location(file)],
+                    sanitizers: vec![],
+                }],
+            })
+            .collect::<Vec<_>>();
+        let outputs = scans
+            .iter()
+            .map(|scan| {
+                let start = scan.rule_matches[0].sources[0].clone();
+                ScanOutput {
+                    paths: vec![Trace {
+                        steps: vec![start.clone()],
+                        end: TraceEnd::Sink(start),
+                        guards: vec![],
+                    }],
+                    sources: vec![],
+                    metrics: CachedMetrics::default(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let (report, metrics) = merge_outputs(&scans, &outputs, ScanMode::All, false);
+
+        let languages = |key: &str| {
+            report[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["language"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(languages("stacktraces"), vec!["solidity", "python"]);
+        assert_eq!(languages("findings"), vec!["solidity", "python"]);
+        assert_eq!(report["findings"][1]["rule"], "python-names");
+        assert_eq!(
+            metrics.keys().collect::<Vec<_>>(),
+            vec![&"python", &"solidity"]
+        );
+
+        let (report, _) = merge_outputs(&scans, &outputs, ScanMode::All, true);
+        assert!(report["stacktraces"].as_array().unwrap().is_empty());
+    }
+}
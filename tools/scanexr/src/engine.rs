@@ -0,0 +1,965 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use lsp_types::Location;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::{
+    graph::DataflowGraph,
+    language_provider::{LanguageAutomata, LspProvider, Trace, TraceEnd},
+    sources::SourceCache,
+    utils::{location_contains, LocationKey},
+};
+
+/// Order in which pending configurations are explored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    BreadthFirst,
+    /// Finds traces in the same order as a recursive search would.
+    #[default]
+    DepthFirst,
+    /// Lowest [`LanguageAutomata::cost`] first.
+    BestFirst,
+}
+
+/// What to do with configurations protected by a guard, see
+/// [`LanguageAutomata::find_guard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuardMode {
+    /// Keep exploring, listing the guard in [`Trace::guards`].
+    #[default]
+    Mark,
+    /// Stop exploring, ending the trace with [`TraceEnd::Sanitized`].
+    Prune,
+    /// Don't look for guards.
+    Ignore,
+}
+
+/// What answers the definition and reference queries of a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// The compiler's AST or the language server, or tree-sitter scopes if
+    /// neither can be started.
+    #[default]
+    Auto,
+    /// Only the language server.
+    LanguageServer,
+    /// Only the compiler's AST, for languages whose compiler exports one,
+    /// see [`SolcAst`].
+    ///
+    /// [`SolcAst`]: crate::languages::solc::SolcAst
+    Compiler,
+    /// Only tree-sitter scopes, see [`ScopeResolver`].
+    ///
+    /// [`ScopeResolver`]: crate::languages::scopes::ScopeResolver
+    TreeSitter,
+}
+
+/// Limits of a single start location's exploration, `None` is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct EngineOptions {
+    pub strategy: Strategy,
+    /// Maximum steps in a trace, traces reaching it without a sink or
+    /// a cycle end with [`TraceEnd::Truncated`].
+    pub max_depth: Option<usize>,
+    pub max_paths: Option<usize>,
+    pub timeout: Option<Duration>,
+    /// Configurations expanded at once, 0 is treated as 1.
+    ///
+    /// With more than 1, traces of a batch are found before the traces
+    /// of its successors, so they aren't strictly in strategy order.
+    pub concurrency: usize,
+    pub guards: GuardMode,
+    pub backend: Backend,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Budget {
+    Depth,
+    Paths,
+    Time,
+}
+
+/// Summary of a single start location's exploration.
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceReport {
+    pub start: Location,
+    pub traces: usize,
+    /// Configurations whose transitions were followed.
+    pub expanded: usize,
+    /// Budgets that cut the exploration short, each reported once.
+    pub exceeded: Vec<Budget>,
+    pub elapsed_ms: f64,
+}
+
+impl SourceReport {
+    fn exceed(&mut self, budget: Budget) {
+        if !self.exceeded.contains(&budget) {
+            self.exceeded.push(budget);
+        }
+    }
+}
+
+/// A partially explored trace.
+struct Item<S> {
+    steps: Vec<Location>,
+    guards: Vec<Location>,
+    /// Configuration of each step, for cycle detection.
+    configurations: Vec<(LocationKey, Vec<S>)>,
+    stack: Vec<S>,
+}
+
+/// A configuration revisits an earlier one if the same location was
+/// reached with a prefix of its stack and the same top item, meaning the
+/// automaton can only repeat itself from here, with the extra stack items
+/// left unconsumed.
+///
+/// A different top item, e.g. one pushed to resolve the location itself,
+/// makes the automaton take different transitions.
+fn revisits<S: PartialEq>(key: &LocationKey, stack: &[S], earlier: (&LocationKey, &[S])) -> bool {
+    let (earlier_key, earlier_stack) = earlier;
+    key == earlier_key && stack.starts_with(earlier_stack) && stack.last() == earlier_stack.last()
+}
+
+impl<S: PartialEq> Item<S> {
+    /// Whether the last configuration revisits the trace, see [`revisits`].
+    fn is_cycle(&self, key: &LocationKey) -> bool {
+        self.configurations[..self.configurations.len() - 1]
+            .iter()
+            .any(|(k, s)| revisits(key, &self.stack, (k, s)))
+    }
+}
+
+/// Node that `id` was first reached from, or `id` itself, which the
+/// configuration (`key`, `stack`) revisits, see [`revisits`].
+fn revisited_node<S: PartialEq>(
+    graph: &DataflowGraph<S>,
+    parents: &HashMap<usize, usize>,
+    mut id: usize,
+    key: &LocationKey,
+    stack: &[S],
+) -> Option<usize> {
+    loop {
+        let node = &graph.nodes[id];
+        if revisits(
+            key,
+            stack,
+            (&LocationKey::from(&node.location), &node.stack),
+        ) {
+            return Some(id);
+        }
+        id = *parents.get(&id)?;
+    }
+}
+
+struct Prioritized<T> {
+    cost: usize,
+    seq: usize,
+    item: T,
+}
+
+impl<T> PartialEq for Prioritized<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.cost, self.seq) == (other.cost, other.seq)
+    }
+}
+
+impl<T> Eq for Prioritized<T> {}
+
+impl<T> PartialOrd for Prioritized<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Prioritized<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // max heap, so the lowest cost (then the oldest item) is greatest
+        (Reverse(self.cost), Reverse(self.seq)).cmp(&(Reverse(other.cost), Reverse(other.seq)))
+    }
+}
+
+enum Worklist<T> {
+    Queue(VecDeque<T>),
+    Stack(Vec<T>),
+    Heap(BinaryHeap<Prioritized<T>>, usize),
+}
+
+impl<T> Worklist<T> {
+    fn new(strategy: Strategy) -> Self {
+        match strategy {
+            Strategy::BreadthFirst => Self::Queue(VecDeque::new()),
+            Strategy::DepthFirst => Self::Stack(vec![]),
+            Strategy::BestFirst => Self::Heap(BinaryHeap::new(), 0),
+        }
+    }
+
+    /// Push the successors of an item, so that equal priority successors
+    /// are popped in order.
+    fn extend(&mut self, items: Vec<(usize, T)>) {
+        match self {
+            Self::Queue(queue) => queue.extend(items.into_iter().map(|(_, item)| item)),
+            Self::Stack(stack) => stack.extend(items.into_iter().rev().map(|(_, item)| item)),
+            Self::Heap(heap, seq) => {
+                for (cost, item) in items {
+                    heap.push(Prioritized {
+                        cost,
+                        seq: *seq,
+                        item,
+                    });
+                    *seq += 1;
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        match self {
+            Self::Queue(queue) => queue.pop_front(),
+            Self::Stack(stack) => stack.pop(),
+            Self::Heap(heap, _) => heap.pop().map(|p| p.item),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Queue(queue) => queue.is_empty(),
+            Self::Stack(stack) => stack.is_empty(),
+            Self::Heap(heap, _) => heap.is_empty(),
+        }
+    }
+
+    /// Push the successors of each item of a batch, so that the batch's
+    /// successors are popped in the order of its items.
+    fn extend_batches(&mut self, batches: Vec<Vec<(usize, T)>>) {
+        match self {
+            Self::Stack(_) => batches.into_iter().rev().for_each(|b| self.extend(b)),
+            _ => batches.into_iter().for_each(|b| self.extend(b)),
+        }
+    }
+
+    fn into_items(self) -> Vec<T> {
+        match self {
+            Self::Queue(queue) => queue.into_iter().collect(),
+            Self::Stack(stack) => stack,
+            Self::Heap(heap, _) => heap.into_iter().map(|p| p.item).collect(),
+        }
+    }
+}
+
+type Transitions<S> = Vec<(Location, Vec<S>)>;
+
+/// Guard of a configuration, and its transitions.
+#[derive(Clone)]
+struct Expansion<S> {
+    guard: Option<Location>,
+    next_steps: Transitions<S>,
+}
+
+/// Look up the queries a transition needs concurrently, and apply it.
+///
+/// Pruned configurations aren't transitioned.
+async fn lookup<P>(
+    automata: &P,
+    lsp_provider: &P::LspProvider,
+    sources: &SourceCache,
+    guards: GuardMode,
+    location: Location,
+    head: P::Stack,
+) -> Result<Expansion<P::Stack>>
+where
+    P: LanguageAutomata + Sync,
+    P::LspProvider: Sync,
+{
+    let guard = match guards {
+        GuardMode::Ignore => None,
+        GuardMode::Mark | GuardMode::Prune => automata.find_guard(&location, sources)?,
+    };
+
+    if guards == GuardMode::Prune && guard.is_some() {
+        return Ok(Expansion {
+            guard,
+            next_steps: vec![],
+        });
+    }
+
+    let queries = automata.required_queries(&location, &head);
+
+    let (definitions, references, implementations) = tokio::join!(
+        async {
+            match queries.definitions {
+                true => lsp_provider.find_definitions(&location).await,
+                false => Err(anyhow!("definitions were not requested")),
+            }
+        },
+        async {
+            match queries.references {
+                true => lsp_provider.find_references(&location).await,
+                false => Err(anyhow!("references were not requested")),
+            }
+        },
+        async {
+            match queries.implementations {
+                true => Some(lsp_provider.find_implementations(&location).await),
+                false => None,
+            }
+        }
+    );
+
+    let definitions = match (queries.definitions, definitions, implementations) {
+        (_, definitions, None) => definitions,
+        (false, _, Some(implementations)) => implementations,
+        (true, Ok(mut definitions), Some(Ok(implementations))) => {
+            definitions.extend(implementations);
+            Ok(definitions)
+        }
+        (true, Err(e), _) | (true, _, Some(Err(e))) => Err(e),
+    };
+
+    Ok(Expansion {
+        guard,
+        next_steps: automata.transition(location, head, definitions, references, sources)?,
+    })
+}
+
+/// Explores the configurations reachable from start locations using
+/// an explicit worklist.
+///
+/// Up to [`EngineOptions::concurrency`] configurations are expanded at
+/// once. Transitions are cached by (location, stack head), so configurations
+/// reached by multiple paths only query the language server once, and
+/// the cache is shared across start locations, as are parsed files.
+pub struct Engine<'a, P: LanguageAutomata> {
+    automata: &'a P,
+    lsp_provider: &'a P::LspProvider,
+    options: EngineOptions,
+    sources: SourceCache,
+    transitions: HashMap<(LocationKey, P::Stack), Expansion<P::Stack>>,
+}
+
+impl<'a, P> Engine<'a, P>
+where
+    P: LanguageAutomata + Sync + Send,
+    P::Stack: Sync + Send + Clone + Hash + Eq,
+    P::LspProvider: Sync,
+{
+    pub fn new(automata: &'a P, lsp_provider: &'a P::LspProvider, options: EngineOptions) -> Self {
+        Self {
+            automata,
+            lsp_provider,
+            options,
+            sources: SourceCache::new(automata.get_language()),
+            transitions: HashMap::new(),
+        }
+    }
+
+    fn pop_batch<T>(&self, worklist: &mut Worklist<T>) -> Vec<T> {
+        let mut batch = vec![];
+        while batch.len() < self.options.concurrency.max(1) {
+            match worklist.pop() {
+                Some(item) => batch.push(item),
+                None => break,
+            }
+        }
+
+        batch
+    }
+
+    /// Explore all traces from `start`, passing each one to `on_trace`
+    /// as soon as it is found.
+    pub async fn explore(
+        &mut self,
+        start: Location,
+        stack: Vec<P::Stack>,
+        stop_at: &[Location],
+        on_trace: &mut (dyn FnMut(Trace) + Send),
+    ) -> Result<SourceReport> {
+        let started = Instant::now();
+        let deadline = self.options.timeout.map(|timeout| started + timeout);
+
+        let mut report = SourceReport {
+            start: start.clone(),
+            traces: 0,
+            expanded: 0,
+            exceeded: vec![],
+            elapsed_ms: 0.0,
+        };
+
+        let mut worklist = Worklist::new(self.options.strategy);
+        worklist.extend(vec![(
+            self.automata.cost(&start, &stack),
+            Item {
+                steps: vec![start.clone()],
+                guards: vec![],
+                configurations: vec![(LocationKey::from(&start), stack.clone())],
+                stack,
+            },
+        )]);
+
+        'explore: loop {
+            let mut batch = self.pop_batch(&mut worklist);
+            if batch.is_empty() {
+                break;
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                report.exceed(Budget::Time);
+                break;
+            }
+
+            let mut ends = vec![];
+            let mut requests = vec![];
+            for item in &mut batch {
+                let location = item.steps.last().expect("items are never empty");
+                let (key, _) = item.configurations.last().expect("items are never empty");
+
+                let end =
+                    if let Some(sink) = stop_at.iter().find(|s| location_contains(s, location)) {
+                        Some(TraceEnd::Sink(sink.clone()))
+                    } else if item.is_cycle(key) {
+                        Some(TraceEnd::Cycle)
+                    } else if self
+                        .options
+                        .max_depth
+                        .is_some_and(|max_depth| item.steps.len() >= max_depth)
+                    {
+                        report.exceed(Budget::Depth);
+                        Some(TraceEnd::Truncated)
+                    } else {
+                        requests.push((location.clone(), item.stack.pop().unwrap()));
+                        None
+                    };
+
+                ends.push(end);
+            }
+
+            report.expanded += requests.len();
+            let Some(transitions) = self.transitions_of(requests, deadline).await? else {
+                report.exceed(Budget::Time);
+                break;
+            };
+            let mut transitions = transitions.into_iter();
+
+            let mut successors = vec![];
+            let mut batch = batch.into_iter().zip(ends);
+            while let Some((mut item, mut end)) = batch.next() {
+                let next_steps = match end {
+                    Some(_) => vec![],
+                    None => {
+                        let expansion = transitions.next().expect("every request has transitions");
+                        match (self.options.guards, expansion.guard) {
+                            (GuardMode::Prune, Some(guard)) => {
+                                end = Some(TraceEnd::Sanitized(guard));
+                            }
+                            (_, Some(guard)) if !item.guards.contains(&guard) => {
+                                item.guards.push(guard);
+                            }
+                            _ => {}
+                        }
+
+                        expansion.next_steps
+                    }
+                };
+
+                if next_steps.is_empty() {
+                    report.traces += 1;
+                    on_trace(Trace {
+                        steps: item.steps,
+                        end: end.unwrap_or(TraceEnd::DeadEnd),
+                        guards: item.guards,
+                    });
+
+                    if self
+                        .options
+                        .max_paths
+                        .is_some_and(|max_paths| report.traces >= max_paths)
+                    {
+                        if !worklist.is_empty() || !successors.is_empty() || batch.len() > 0 {
+                            report.exceed(Budget::Paths);
+                        }
+                        break 'explore;
+                    }
+
+                    continue;
+                }
+
+                successors.push(
+                    next_steps
+                        .into_iter()
+                        .map(|(next_location, mut pushed_items)| {
+                            let mut stack = item.stack.clone();
+                            stack.append(&mut pushed_items);
+
+                            let mut steps = item.steps.clone();
+                            steps.push(next_location.clone());
+
+                            let mut configurations = item.configurations.clone();
+                            configurations.push((LocationKey::from(&next_location), stack.clone()));
+
+                            (
+                                self.automata.cost(&next_location, &stack),
+                                Item {
+                                    steps,
+                                    guards: item.guards.clone(),
+                                    configurations,
+                                    stack,
+                                },
+                            )
+                        })
+                        .collect(),
+                );
+            }
+
+            worklist.extend_batches(successors);
+        }
+
+        report.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(report)
+    }
+
+    /// Explore all traces from each start location, with the automaton's
+    /// initial stack.
+    pub async fn explore_all(
+        &mut self,
+        starts: Vec<Location>,
+        stop_at: &[Location],
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<(Vec<Trace>, Vec<SourceReport>)> {
+        let mut traces = vec![];
+        let mut reports = vec![];
+        for start in starts {
+            let stack = self.automata.initial_state();
+            let report = self
+                .explore(start, stack, stop_at, &mut |trace| {
+                    on_trace(&trace);
+                    traces.push(trace);
+                })
+                .await?;
+
+            reports.push(report);
+        }
+
+        Ok((traces, reports))
+    }
+
+    /// Build the dataflow graph of all start locations, with the automaton's
+    /// initial stack.
+    pub async fn explore_graph_all(
+        &mut self,
+        starts: Vec<Location>,
+        stop_at: &[Location],
+    ) -> Result<(DataflowGraph<P::Stack>, Vec<SourceReport>)> {
+        let mut graph = DataflowGraph::new();
+        let mut reports = vec![];
+        for start in starts {
+            let stack = self.automata.initial_state();
+            reports.push(
+                self.explore_graph(&mut graph, start, stack, stop_at)
+                    .await?,
+            );
+        }
+
+        Ok((graph, reports))
+    }
+
+    /// Add the configurations reachable from `start` to `graph`, expanding
+    /// each configuration once, even across start locations.
+    ///
+    /// The path budget doesn't apply to graphs. Configurations left
+    /// unexpanded by the other budgets are marked [`TraceEnd::Truncated`].
+    ///
+    /// A configuration revisiting one it was reached from, like in
+    /// [`Engine::explore`], becomes an edge back to it instead of a new
+    /// node, and the revisited node is marked [`TraceEnd::Cycle`].
+    pub async fn explore_graph(
+        &mut self,
+        graph: &mut DataflowGraph<P::Stack>,
+        start: Location,
+        stack: Vec<P::Stack>,
+        stop_at: &[Location],
+    ) -> Result<SourceReport> {
+        let started = Instant::now();
+        let deadline = self.options.timeout.map(|timeout| started + timeout);
+
+        let mut report = SourceReport {
+            start: start.clone(),
+            traces: 0,
+            expanded: 0,
+            exceeded: vec![],
+            elapsed_ms: 0.0,
+        };
+
+        let cost = self.automata.cost(&start, &stack);
+        let (id, new) = graph.insert(start, stack);
+        graph.add_start(id);
+
+        let mut worklist = Worklist::new(self.options.strategy);
+        if new {
+            worklist.extend(vec![(cost, (1, id))]);
+        }
+
+        // node each new node was first reached from, for cutting cycles
+        let mut parents = HashMap::new();
+
+        loop {
+            let batch = self.pop_batch(&mut worklist);
+            if batch.is_empty() {
+                break;
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                report.exceed(Budget::Time);
+                for (_, id) in batch {
+                    graph.nodes[id].end = Some(TraceEnd::Truncated);
+                }
+                break;
+            }
+
+            let mut expanded = vec![];
+            let mut requests = vec![];
+            for (depth, id) in batch {
+                let node = &mut graph.nodes[id];
+
+                if let Some(sink) = stop_at
+                    .iter()
+                    .find(|s| location_contains(s, &node.location))
+                {
+                    node.end = Some(TraceEnd::Sink(sink.clone()));
+                } else if self
+                    .options
+                    .max_depth
+                    .is_some_and(|max_depth| depth >= max_depth)
+                {
+                    report.exceed(Budget::Depth);
+                    node.end = Some(TraceEnd::Truncated);
+                } else {
+                    let mut stack = node.stack.clone();
+                    requests.push((node.location.clone(), stack.pop().unwrap()));
+                    expanded.push((depth, id, stack));
+                }
+            }
+
+            report.expanded += requests.len();
+            let Some(transitions) = self.transitions_of(requests, deadline).await? else {
+                report.exceed(Budget::Time);
+                for (_, id, _) in expanded {
+                    graph.nodes[id].end = Some(TraceEnd::Truncated);
+                }
+                break;
+            };
+
+            let mut successors = vec![];
+            for ((depth, id, stack), expansion) in expanded.into_iter().zip(transitions) {
+                match (self.options.guards, expansion.guard) {
+                    (GuardMode::Prune, Some(guard)) => {
+                        graph.nodes[id].end = Some(TraceEnd::Sanitized(guard));
+                    }
+                    (_, guard) => graph.nodes[id].guard = guard,
+                }
+
+                let mut item_successors = vec![];
+                for (next_location, mut pushed_items) in expansion.next_steps {
+                    let mut next_stack = stack.clone();
+                    next_stack.append(&mut pushed_items);
+
+                    let key = LocationKey::from(&next_location);
+                    if let Some(earlier) = revisited_node(graph, &parents, id, &key, &next_stack) {
+                        graph.add_edge(id, earlier);
+                        graph.nodes[earlier].end.get_or_insert(TraceEnd::Cycle);
+                        continue;
+                    }
+
+                    let cost = self.automata.cost(&next_location, &next_stack);
+                    let (next_id, new) = graph.insert(next_location, next_stack);
+                    graph.add_edge(id, next_id);
+
+                    if new {
+                        parents.insert(next_id, id);
+                        item_successors.push((cost, (depth + 1, next_id)));
+                    }
+                }
+
+                successors.push(item_successors);
+            }
+
+            worklist.extend_batches(successors);
+        }
+
+        for (_, id) in worklist.into_items() {
+            graph.nodes[id].end = Some(TraceEnd::Truncated);
+        }
+
+        report.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        Ok(report)
+    }
+
+    /// Transitions of each (location, stack head) request, looking up
+    /// uncached ones concurrently. Returns `None` if the deadline passed first.
+    async fn transitions_of(
+        &mut self,
+        requests: Vec<(Location, P::Stack)>,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Vec<Expansion<P::Stack>>>> {
+        let keys = requests
+            .iter()
+            .map(|(location, head)| (LocationKey::from(location), head.clone()))
+            .collect::<Vec<_>>();
+
+        let mut missing: Vec<usize> = vec![];
+        for (i, key) in keys.iter().enumerate() {
+            if !self.transitions.contains_key(key) && !missing.iter().any(|j| &keys[*j] == key) {
+                missing.push(i);
+            }
+        }
+
+        let (automata, lsp_provider, sources) = (self.automata, self.lsp_provider, &self.sources);
+        let guards = self.options.guards;
+        let lookups = join_all(missing.iter().map(|i| {
+            let (location, head) = requests[*i].clone();
+            lookup(automata, lsp_provider, sources, guards, location, head)
+        }));
+
+        let results = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, lookups).await {
+                Ok(results) => results,
+                Err(_) => return Ok(None),
+            },
+            None => lookups.await,
+        };
+
+        for (i, result) in missing.into_iter().zip(results) {
+            self.transitions.insert(keys[i].clone(), result?);
+        }
+
+        Ok(Some(
+            keys.iter()
+                .map(|key| self.transitions[key].clone())
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{display_traces, line_location as location, Graph, NoLsp};
+
+    #[test]
+    fn test_is_cycle() {
+        let item = |configurations: &[(u32, &[u8])]| Item {
+            steps: vec![],
+            guards: vec![],
+            configurations: configurations
+                .iter()
+                .map(|(line, stack)| (LocationKey::from(&location(*line)), stack.to_vec()))
+                .collect(),
+            stack: configurations.last().unwrap().1.to_vec(),
+        };
+        let is_cycle = |configurations: &[(u32, &[u8])]| {
+            let (line, _) = configurations.last().unwrap();
+            item(configurations).is_cycle(&LocationKey::from(&location(*line)))
+        };
+
+        assert!(is_cycle(&[(1, &[0]), (2, &[1]), (1, &[0])]));
+        assert!(is_cycle(&[(1, &[0]), (2, &[0, 1]), (1, &[0, 1, 0])]));
+        // resolving the start location itself
+        assert!(!is_cycle(&[(1, &[0]), (1, &[0, 1])]));
+        assert!(!is_cycle(&[(1, &[0]), (2, &[0]), (1, &[1])]));
+    }
+
+    async fn explore(graph: &Graph, options: EngineOptions) -> (Vec<String>, SourceReport) {
+        let lsp = NoLsp::default();
+        let mut engine = Engine::new(graph, &lsp, options);
+        let mut traces = vec![];
+        let report = engine
+            .explore(location(1), graph.initial_state(), &[], &mut |t| {
+                traces.push(t)
+            })
+            .await
+            .unwrap();
+
+        (display_traces(&traces), report)
+    }
+
+    #[tokio::test]
+    async fn test_strategies() {
+        let edges: &[(u32, &[u32])] = &[(1, &[2, 3]), (2, &[4, 5]), (3, &[6])];
+
+        let (dfs, _) = explore(&Graph::new(edges), EngineOptions::default()).await;
+        assert_eq!(
+            dfs,
+            vec![
+                "1 -> 2 -> 4 DeadEnd",
+                "1 -> 2 -> 5 DeadEnd",
+                "1 -> 3 -> 6 DeadEnd"
+            ]
+        );
+
+        let bfs_graph = Graph::new(edges);
+        let (bfs, _) = explore(
+            &bfs_graph,
+            EngineOptions {
+                strategy: Strategy::BreadthFirst,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(bfs.len(), 3);
+        assert_eq!(
+            *bfs_graph.transitions.lock().unwrap(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+
+        let (best, _) = explore(
+            &Graph::new(edges),
+            EngineOptions {
+                strategy: Strategy::BestFirst,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(best.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_budgets() {
+        let edges: &[(u32, &[u32])] = &[(1, &[2, 3]), (2, &[4]), (4, &[5])];
+
+        let (traces, report) = explore(
+            &Graph::new(edges),
+            EngineOptions {
+                max_depth: Some(3),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(traces, vec!["1 -> 2 -> 4 Truncated", "1 -> 3 DeadEnd"]);
+        assert_eq!(report.exceeded, vec![Budget::Depth]);
+
+        let (traces, report) = explore(
+            &Graph::new(edges),
+            EngineOptions {
+                max_paths: Some(1),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(traces, vec!["1 -> 2 -> 4 -> 5 DeadEnd"]);
+        assert_eq!(report.exceeded, vec![Budget::Paths]);
+
+        let (traces, report) = explore(
+            &Graph::new(edges),
+            EngineOptions {
+                timeout: Some(Duration::ZERO),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(traces.is_empty());
+        assert_eq!(report.exceeded, vec![Budget::Time]);
+    }
+
+    #[tokio::test]
+    async fn test_guards() {
+        let mut graph = Graph::new(&[(1, &[2, 3]), (2, &[4]), (3, &[4])]);
+        graph.guards = vec![2];
+
+        let (traces, _) = explore(&graph, EngineOptions::default()).await;
+        assert_eq!(
+            traces,
+            vec!["1 -> 2 -> 4 DeadEnd guards [2]", "1 -> 3 -> 4 DeadEnd"]
+        );
+
+        let (traces, _) = explore(
+            &graph,
+            EngineOptions {
+                guards: GuardMode::Prune,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(
+            traces,
+            vec![
+                format!("1 -> 2 Sanitized({:?})", location(2)),
+                "1 -> 3 -> 4 DeadEnd".to_string(),
+            ]
+        );
+
+        let (traces, _) = explore(
+            &graph,
+            EngineOptions {
+                guards: GuardMode::Ignore,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(traces, vec!["1 -> 2 -> 4 DeadEnd", "1 -> 3 -> 4 DeadEnd"]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency() {
+        let edges: &[(u32, &[u32])] =
+            &[(1, &[2, 3, 4]), (2, &[5]), (3, &[5]), (4, &[6]), (5, &[7])];
+
+        for strategy in [Strategy::DepthFirst, Strategy::BreadthFirst] {
+            let (mut sequential, _) = explore(
+                &Graph::new(edges),
+                EngineOptions {
+                    strategy,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            let graph = Graph::new(edges);
+            let (mut concurrent, report) = explore(
+                &graph,
+                EngineOptions {
+                    strategy,
+                    concurrency: 3,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            sequential.sort();
+            concurrent.sort();
+            assert_eq!(sequential, concurrent);
+            assert_eq!(report.expanded, 9);
+
+            // the shared (5, ()) configuration is only transitioned once
+            let mut transitions = graph.transitions.lock().unwrap().clone();
+            transitions.sort();
+            assert_eq!(transitions, vec![1, 2, 3, 4, 5, 6, 7]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_required_queries() {
+        let mut graph = Graph::new(&[(1, &[2])]);
+        graph.queries.references = false;
+        graph.queries.implementations = false;
+
+        let lsp = NoLsp::default();
+        let mut engine = Engine::new(&graph, &lsp, EngineOptions::default());
+        engine
+            .explore(location(1), graph.initial_state(), &[], &mut |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(*lsp.definitions.lock().unwrap(), vec![1, 2]);
+        assert!(lsp.references.lock().unwrap().is_empty());
+        assert!(lsp.implementations.lock().unwrap().is_empty());
+    }
+}
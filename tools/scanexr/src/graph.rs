@@ -0,0 +1,417 @@
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use lsp_types::Location;
+use serde_json::{json, Value};
+
+use crate::{
+    language_provider::{Trace, TraceEnd},
+    utils::{location_json, LocationKey},
+};
+
+/// A location with the automaton stack it was reached with.
+#[derive(Debug, Clone)]
+pub struct GraphNode<S> {
+    pub location: Location,
+    pub stack: Vec<S>,
+    /// Why exploration stopped at this node, if it did before expanding it,
+    /// or [`TraceEnd::Cycle`] if a later configuration loops back to it.
+    pub end: Option<TraceEnd>,
+    /// Guard protecting this node, see [`GuardMode::Mark`].
+    ///
+    /// [`GuardMode::Mark`]: crate::engine::GuardMode::Mark
+    pub guard: Option<Location>,
+    /// Nodes this node transitions to.
+    pub next: Vec<usize>,
+}
+
+/// Dataflow of a scan, each node is a configuration the automaton
+/// reached, and each edge is a transition between two configurations.
+///
+/// Unlike enumerated paths, shared prefixes and suffixes are stored once,
+/// and paths can be enumerated from the graph on demand.
+#[derive(Debug, Clone)]
+pub struct DataflowGraph<S> {
+    pub nodes: Vec<GraphNode<S>>,
+    /// Nodes of the start locations.
+    pub starts: Vec<usize>,
+    index: HashMap<(LocationKey, Vec<S>), usize>,
+}
+
+impl<S: Clone + Hash + Eq> Default for DataflowGraph<S> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![],
+            starts: vec![],
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<S: Clone + Hash + Eq> DataflowGraph<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the node of a configuration, adding it if it is new.
+    pub fn insert(&mut self, location: Location, stack: Vec<S>) -> (usize, bool) {
+        let key = (LocationKey::from(&location), stack);
+        if let Some(id) = self.index.get(&key) {
+            return (*id, false);
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(GraphNode {
+            location,
+            stack: key.1.clone(),
+            end: None,
+            guard: None,
+            next: vec![],
+        });
+        self.index.insert(key, id);
+
+        (id, true)
+    }
+
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        let next = &mut self.nodes[from].next;
+        if !next.contains(&to) {
+            next.push(to);
+        }
+    }
+
+    pub fn add_start(&mut self, id: usize) {
+        if !self.starts.contains(&id) {
+            self.starts.push(id);
+        }
+    }
+
+    /// Convert the stack items of all nodes, e.g. to their names.
+    pub fn map<T: Clone + Hash + Eq>(self, f: impl Fn(&S) -> T) -> DataflowGraph<T> {
+        let mut graph = DataflowGraph {
+            nodes: vec![],
+            starts: self.starts,
+            index: HashMap::new(),
+        };
+
+        for (id, node) in self.nodes.into_iter().enumerate() {
+            let stack = node.stack.iter().map(&f).collect::<Vec<_>>();
+            graph
+                .index
+                .insert((LocationKey::from(&node.location), stack.clone()), id);
+            graph.nodes.push(GraphNode {
+                location: node.location,
+                stack,
+                end: node.end,
+                guard: node.guard,
+                next: node.next,
+            });
+        }
+
+        graph
+    }
+}
+
+impl<S> DataflowGraph<S> {
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(from, node)| node.next.iter().map(move |to| (from, *to)))
+    }
+
+    /// Enumerate all paths from a node, cutting paths that revisit a node.
+    pub fn paths(&self, start: usize) -> Vec<Trace> {
+        let mut traces = vec![];
+        let mut path = vec![start];
+        self.visit(&mut path, &mut traces);
+
+        traces
+    }
+
+    fn visit(&self, path: &mut Vec<usize>, traces: &mut Vec<Trace>) {
+        let id = *path.last().expect("path is never empty");
+        let node = &self.nodes[id];
+
+        let end = if path[..path.len() - 1].contains(&id) {
+            Some(TraceEnd::Cycle)
+        } else if node.next.is_empty() {
+            Some(node.end.clone().unwrap_or(TraceEnd::DeadEnd))
+        } else {
+            None
+        };
+
+        if let Some(end) = end {
+            let mut guards: Vec<Location> = vec![];
+            for guard in path.iter().filter_map(|id| self.nodes[*id].guard.as_ref()) {
+                if !guards.contains(guard) {
+                    guards.push(guard.clone());
+                }
+            }
+
+            traces.push(Trace {
+                steps: path
+                    .iter()
+                    .map(|id| self.nodes[*id].location.clone())
+                    .collect(),
+                end,
+                guards,
+            });
+            return;
+        }
+
+        for next in &node.next {
+            path.push(*next);
+            self.visit(path, traces);
+            path.pop();
+        }
+    }
+}
+
+impl<S: Debug> DataflowGraph<S> {
+    /// `file:line:column` of a node followed by the head of its stack.
+    fn label(&self, id: usize) -> (String, String) {
+        let node = &self.nodes[id];
+        let filename = node
+            .location
+            .uri
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .unwrap_or_default()
+            .to_string();
+        let start = node.location.range.start;
+
+        (
+            format!("{}:{}:{}", filename, start.line + 1, start.character + 1),
+            node.stack
+                .last()
+                .map(|head| format!("{:?}", head))
+                .unwrap_or_default(),
+        )
+    }
+
+    pub fn to_json(&self) -> Value {
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| {
+                json!({
+                    "id": id,
+                    "location": location_json(&node.location),
+                    "meta": self.label(id).1,
+                    "start": self.starts.contains(&id),
+                    "sink": matches!(node.end, Some(TraceEnd::Sink(_))),
+                    "truncated": node.end == Some(TraceEnd::Truncated),
+                    "sanitized": matches!(node.end, Some(TraceEnd::Sanitized(_))),
+                    "cycle": node.end == Some(TraceEnd::Cycle),
+                    "guard": node.guard.as_ref().map(location_json),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let edges = self
+            .edges()
+            .map(|(from, to)| json!({ "from": from, "to": to }))
+            .collect::<Vec<_>>();
+
+        json!({ "nodes": nodes, "edges": edges })
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph dataflow {".to_string(),
+            "    node [shape=box];".to_string(),
+        ];
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            let (location, meta) = self.label(id);
+            let mut attributes = vec![format!(
+                "label=\"{}\\n{}\"",
+                location,
+                meta.replace('\\', "\\\\").replace('"', "\\\"")
+            )];
+
+            if self.starts.contains(&id) {
+                attributes.push("style=bold".to_string());
+            }
+            match node.end {
+                Some(TraceEnd::Sink(_)) => attributes.push("color=red".to_string()),
+                Some(TraceEnd::Truncated) => attributes.push("style=dashed".to_string()),
+                Some(TraceEnd::Sanitized(_)) => attributes.push("color=green".to_string()),
+                Some(TraceEnd::Cycle) => attributes.push("color=blue".to_string()),
+                _ => {}
+            }
+
+            lines.push(format!("    n{} [{}];", id, attributes.join(", ")));
+        }
+
+        for (from, to) in self.edges() {
+            lines.push(format!("    n{} -> n{};", from, to));
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut lines = vec!["flowchart TD".to_string()];
+
+        for id in 0..self.nodes.len() {
+            let (location, meta) = self.label(id);
+            lines.push(format!(
+                "    n{}[\"{}<br/>{}\"]",
+                id,
+                location,
+                meta.replace('"', "#quot;")
+            ));
+        }
+
+        for (from, to) in self.edges() {
+            lines.push(format!("    n{} --> n{}", from, to));
+        }
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            match node.end {
+                Some(TraceEnd::Sink(_)) => lines.push(format!("    class n{} sink", id)),
+                Some(TraceEnd::Truncated) => lines.push(format!("    class n{} truncated", id)),
+                Some(TraceEnd::Sanitized(_)) => lines.push(format!("    class n{} sanitized", id)),
+                Some(TraceEnd::Cycle) => lines.push(format!("    class n{} cycle", id)),
+                _ => {}
+            }
+        }
+
+        lines.push("    classDef sink stroke:#f00".to_string());
+        lines.push("    classDef truncated stroke-dasharray:4".to_string());
+        lines.push("    classDef sanitized stroke:#0a0".to_string());
+        lines.push("    classDef cycle stroke:#00f".to_string());
+        lines.join("\n")
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::{Engine, EngineOptions, GuardMode},
+        language_provider::LanguageAutomata,
+        test_utils::{display_traces, line_location as location, Graph, NoLsp},
+    };
+
+    async fn build(
+        graph: &Graph,
+        stop_at: &[Location],
+        options: EngineOptions,
+    ) -> DataflowGraph<()> {
+        let mut dataflow = DataflowGraph::new();
+        let lsp = NoLsp::default();
+        let mut engine = Engine::new(graph, &lsp, options);
+        engine
+            .explore_graph(&mut dataflow, location(1), graph.initial_state(), stop_at)
+            .await
+            .unwrap();
+
+        dataflow
+    }
+
+    #[tokio::test]
+    async fn test_graph() {
+        // a diamond, followed by a cycle
+        let graph = Graph::new(&[(1, &[2, 3]), (2, &[4]), (3, &[4]), (4, &[5, 1])]);
+        let dataflow = build(&graph, &[location(5)], EngineOptions::default()).await;
+
+        assert_eq!(dataflow.nodes.len(), 5);
+        assert_eq!(dataflow.edges().count(), 6);
+        assert_eq!(*graph.transitions.lock().unwrap(), vec![1, 2, 4, 3]);
+
+        let paths = display_traces(&dataflow.paths(dataflow.starts[0]));
+        assert_eq!(
+            paths,
+            vec![
+                format!("1 -> 2 -> 4 -> 5 Sink({:?})", location(5)),
+                "1 -> 2 -> 4 -> 1 Cycle".to_string(),
+                format!("1 -> 3 -> 4 -> 5 Sink({:?})", location(5)),
+                "1 -> 3 -> 4 -> 1 Cycle".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graph_recursion() {
+        // 2 calls itself through 3, growing the stack each round
+        let mut graph = Graph::new(&[(1, &[2]), (2, &[3, 4]), (3, &[2])]);
+        graph.pushes = vec![3];
+        let dataflow = build(&graph, &[location(4)], EngineOptions::default()).await;
+
+        assert_eq!(dataflow.nodes.len(), 4);
+        assert_eq!(dataflow.nodes[1].end, Some(TraceEnd::Cycle));
+
+        let paths = display_traces(&dataflow.paths(dataflow.starts[0]));
+        assert_eq!(
+            paths,
+            vec![
+                "1 -> 2 -> 3 -> 2 Cycle".to_string(),
+                format!("1 -> 2 -> 4 Sink({:?})", location(4)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graph_formats() {
+        // 2 loops back to the start, 3 is guarded
+        let mut graph = Graph::new(&[(1, &[2, 3, 4]), (2, &[1])]);
+        graph.guards = vec![3];
+        let options = EngineOptions {
+            guards: GuardMode::Prune,
+            ..Default::default()
+        };
+        let dataflow = build(&graph, &[location(4)], options).await;
+
+        insta::assert_snapshot!(dataflow.to_dot(), @r###"
+        digraph dataflow {
+            node [shape=box];
+            n0 [label="contract.sol:2:1\n()", style=bold, color=blue];
+            n1 [label="contract.sol:3:1\n()"];
+            n2 [label="contract.sol:4:1\n()", color=green];
+            n3 [label="contract.sol:5:1\n()", color=red];
+            n0 -> n1;
+            n0 -> n2;
+            n0 -> n3;
+            n1 -> n0;
+        }
+        "###);
+
+        insta::assert_snapshot!(dataflow.to_mermaid(), @r###"
+        flowchart TD
+            n0["contract.sol:2:1<br/>()"]
+            n1["contract.sol:3:1<br/>()"]
+            n2["contract.sol:4:1<br/>()"]
+            n3["contract.sol:5:1<br/>()"]
+            n0 --> n1
+            n0 --> n2
+            n0 --> n3
+            n1 --> n0
+            class n0 cycle
+            class n2 sanitized
+            class n3 sink
+            classDef sink stroke:#f00
+            classDef truncated stroke-dasharray:4
+            classDef sanitized stroke:#0a0
+            classDef cycle stroke:#00f
+        "###);
+
+        let json = dataflow.to_json();
+        let flags = |flag: &str| {
+            json["nodes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|node| node[flag] == true)
+                .map(|node| node["id"].as_u64().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(flags("sink"), vec![3]);
+        assert_eq!(flags("sanitized"), vec![2]);
+        assert_eq!(flags("cycle"), vec![0]);
+        assert_eq!(json["edges"].as_array().unwrap().len(), 4);
+    }
+}
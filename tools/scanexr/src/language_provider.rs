@@ -1,27 +1,220 @@
-use std::path::{Path, PathBuf};
+use std::{
+    hash::Hash,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
-use async_recursion::async_recursion;
+use anyhow::Result;
 use async_trait::async_trait;
+use lsp_client::cache::CachedMetrics;
 use lsp_types::Location;
-use tree_sitter::{Language, Tree};
+use tree_sitter::Language;
+
+use crate::{
+    engine::{Engine, EngineOptions, SourceReport},
+    graph::DataflowGraph,
+    rules::{match_rules, starts_and_sinks, Rule, RuleMatches},
+    sources::SourceCache,
+};
+
+/// Why a trace ended at its last step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEnd {
+    /// The automaton has no transitions from the last step.
+    DeadEnd,
+    /// The last step is inside this sink.
+    Sink(Location),
+    /// The last step revisits an earlier configuration of the trace.
+    Cycle,
+    /// The trace reached the maximum depth of the exploration.
+    Truncated,
+    /// The last step is protected by this guard, see [`GuardMode::Prune`].
+    ///
+    /// [`GuardMode::Prune`]: crate::engine::GuardMode::Prune
+    Sanitized(Location),
+}
+
+/// A path from a start location to where exploration stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub steps: Vec<Location>,
+    pub end: TraceEnd,
+    /// Guards protecting steps of the trace, see [`GuardMode::Mark`].
+    ///
+    /// [`GuardMode::Mark`]: crate::engine::GuardMode::Mark
+    pub guards: Vec<Location>,
+}
+
+impl Trace {
+    pub fn sink(&self) -> Option<&Location> {
+        match &self.end {
+            TraceEnd::Sink(sink) => Some(sink),
+            _ => None,
+        }
+    }
+}
+
+/// Which traces of a scan to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Every trace, whether it reached a sink or not.
+    All,
+    /// Only traces that reached a sink.
+    Sinks,
+    /// All traces of start locations from which no sink was reached.
+    Unreached,
+}
+
+pub struct ScanOutput {
+    pub paths: Vec<Trace>,
+    /// Exploration summary of each start location.
+    pub sources: Vec<SourceReport>,
+    /// Language server traffic statistics of the scan.
+    pub metrics: CachedMetrics,
+}
+
+pub struct GraphOutput {
+    /// Dataflow graph of all start locations, with stack items by name.
+    pub graph: DataflowGraph<String>,
+    /// Exploration summary of each start location.
+    pub sources: Vec<SourceReport>,
+    /// Language server traffic statistics of the scan.
+    pub metrics: CachedMetrics,
+}
+
+impl ScanOutput {
+    pub fn select(&self, mode: ScanMode) -> Vec<&Trace> {
+        match mode {
+            ScanMode::All => self.paths.iter().collect(),
+            ScanMode::Sinks => self.paths.iter().filter(|t| t.sink().is_some()).collect(),
+            ScanMode::Unreached => self
+                .paths
+                .iter()
+                .filter(|trace| {
+                    !self
+                        .paths
+                        .iter()
+                        .any(|t| t.sink().is_some() && t.steps.first() == trace.steps.first())
+                })
+                .collect(),
+        }
+    }
+}
 
 #[async_trait]
 pub trait SupportedLanguage {
-    fn get_start_end(&self, project_files: &[PathBuf]) -> Result<(Vec<Location>, Vec<Location>)>;
+    /// Name rules refer to the language by.
+    fn name(&self) -> &'static str;
+    /// Other names the language can be selected by.
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Extensions of the language's files, without the dot.
+    fn extensions(&self) -> &'static [&'static str];
+    /// Language identifier of `path` a language server is told when
+    /// the file is opened.
+    fn language_id(&self, _path: &Path) -> &'static str {
+        self.name()
+    }
+    /// Grammar rule queries are matched with.
+    fn tree_sitter_language(&self) -> Language;
+    /// Language servers a scan tries to start, first installed wins.
+    fn servers(&self) -> &'static [&'static str];
+    /// Rules scanned for when no rules are given.
+    fn default_rules(&self) -> Result<Vec<Rule>>;
+    /// Query of the language's scopes, see [`ScopeResolver`], for
+    /// resolving names without a language server.
+    ///
+    /// [`ScopeResolver`]: crate::languages::scopes::ScopeResolver
+    fn locals_query(&self) -> Option<&'static str> {
+        None
+    }
+    /// Path of the module an `@local.import` capture refers to, relative
+    /// to the importing file if it starts with `.`.
+    fn import_path(&self, import: &str) -> String {
+        import
+            .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+            .to_string()
+    }
+
+    /// Whether `path` is scanned, by default if it has one of the
+    /// language's extensions.
+    fn is_project_file(&self, path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|e| self.extensions().iter().any(|extension| e == *extension))
+    }
+    /// Files of the project at `root_dir` that are scanned by default, e.g.
+    /// without tests or dependencies.
+    fn project_files(&self, _root_dir: &Path, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        Ok(files
+            .iter()
+            .filter(|f| self.is_project_file(f))
+            .cloned()
+            .collect())
+    }
+    fn match_rules(&self, rules: &[Rule], project_files: &[PathBuf]) -> Result<Vec<RuleMatches>> {
+        let project_files = project_files
+            .iter()
+            .filter(|f| self.is_project_file(f))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        match_rules(self.tree_sitter_language(), rules, &project_files)
+    }
+
+    /// Start and end locations of the default rules.
+    fn get_start_end(&self, project_files: &[PathBuf]) -> Result<(Vec<Location>, Vec<Location>)> {
+        let matches = self.match_rules(&self.default_rules()?, project_files)?;
+
+        Ok(starts_and_sinks(&matches))
+    }
     async fn find_paths(
         &self,
         root_dir: &Path,
         project_files: Vec<PathBuf>,
         start_locations: Vec<Location>,
         stop_at: &[Location],
-    ) -> Result<Vec<Vec<Location>>>;
+        options: &EngineOptions,
+        // explicit lifetime, so async_trait doesn't bind it to the method's
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<ScanOutput>;
+    async fn find_graph(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+    ) -> Result<GraphOutput>;
 }
 
 #[async_trait]
 pub trait LspProvider {
     async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>>;
     async fn find_references(&self, location: &Location) -> Result<Vec<Location>>;
+
+    /// Implementations of the interface method at `location`, for servers
+    /// that support them.
+    async fn find_implementations(&self, _location: &Location) -> Result<Vec<Location>> {
+        Ok(vec![])
+    }
+}
+
+/// Language server queries a transition needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Queries {
+    pub definitions: bool,
+    pub references: bool,
+    /// Implementations are passed to the transition with the definitions,
+    /// e.g. the methods an interface method call may run.
+    pub implementations: bool,
+}
+
+impl Queries {
+    pub const ALL: Queries = Queries {
+        definitions: true,
+        references: true,
+        implementations: true,
+    };
 }
 
 /// Push down automaton that receives an input
@@ -37,74 +230,146 @@ pub trait LanguageAutomata {
     fn get_language(&self) -> Language;
     fn initial_state(&self) -> Vec<Self::Stack>;
 
+    /// Queries the transition of `input` with `stack` on top needs,
+    /// queries that aren't needed are passed to it as errors.
+    fn required_queries(&self, _input: &Location, _stack: &Self::Stack) -> Queries {
+        Queries::ALL
+    }
+
     /// Get the next (inputs, items to push to the stack) values.
+    ///
+    /// Files and queries should be taken from `sources`, which is shared
+    /// by all transitions of a scan.
     fn transition(
         &self,
         input: Location,
         stack: Self::Stack,
         definitions: Result<Vec<Location>>,
         references: Result<Vec<Location>>,
+        sources: &SourceCache,
     ) -> Result<Vec<(Location, Vec<Self::Stack>)>>;
 
-    fn get_tree(&self, location: &Location) -> Result<Tree> {
-        let mut parser = tree_sitter::Parser::new();
-        parser
-            .set_language(self.get_language())
-            .context("failed to set language")?;
-
-        let content =
-            String::from_utf8(std::fs::read(location.uri.to_file_path().unwrap()).unwrap())
-                .unwrap();
+    /// Priority of a configuration for best-first exploration, lowest first.
+    ///
+    /// Defaults to the stack size, preferring configurations with the
+    /// least pending work.
+    fn cost(&self, _location: &Location, stack: &[Self::Stack]) -> usize {
+        stack.len()
+    }
 
-        parser.parse(content, None).context("failed to parse text")
+    /// Location of a check protecting the value at `location`, e.g.
+    /// a `require` on it, or a modifier restricting its callers.
+    fn find_guard(&self, _location: &Location, _sources: &SourceCache) -> Result<Option<Location>> {
+        Ok(None)
     }
 }
 
-#[async_recursion]
+/// Find all paths from `location`, stopping at any location contained in
+/// one of the `stop_at` sinks.
+///
+/// Paths revisiting a configuration are cut and end with [`TraceEnd::Cycle`].
+/// See [`Engine`] for other search strategies and budgets.
 pub async fn find_paths<P>(
     language_provider: &P,
     lsp_provider: &P::LspProvider,
     location: Location,
-    mut stack: Vec<P::Stack>,
+    stack: Vec<P::Stack>,
     stop_at: &[Location],
-) -> Result<Vec<Vec<Location>>>
+) -> Result<Vec<Trace>>
 where
     P: LanguageAutomata + Sync + Send,
-    P::Stack: Sync + Send + Clone,
+    P::Stack: Sync + Send + Clone + Hash + Eq,
     P::LspProvider: Sync,
 {
-    if stop_at.contains(&location) {
-        return Ok(vec![vec![location.clone()]]);
-    }
-
-    let definitions = lsp_provider.find_definitions(&location).await;
-    let references = lsp_provider.find_references(&location).await;
-    let stack_head = stack.pop().unwrap();
-    let next_steps =
-        language_provider.transition(location.clone(), stack_head, definitions, references)?;
-
-    if next_steps.is_empty() {
-        return Ok(vec![vec![location.clone()]]);
-    }
-
-    let mut paths = vec![];
-    for (next_location, mut pushed_items) in next_steps {
-        let mut next_stack = stack.clone();
-        next_stack.append(&mut pushed_items);
-        let next_paths = find_paths::<P>(
-            language_provider,
-            lsp_provider,
-            next_location,
-            next_stack,
-            stop_at,
-        )
+    let mut engine = Engine::new(language_provider, lsp_provider, EngineOptions::default());
+
+    let mut traces = vec![];
+    engine
+        .explore(location, stack, stop_at, &mut |trace| traces.push(trace))
         .await?;
 
-        for mut next_path in next_paths {
-            next_path.insert(0, location.clone());
-            paths.push(next_path);
+    Ok(traces)
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{display_traces, line_location as location, Graph, NoLsp};
+
+    fn trace(start: u32, end: u32, reached: bool) -> Trace {
+        Trace {
+            steps: vec![location(start), location(end)],
+            end: if reached {
+                TraceEnd::Sink(location(end))
+            } else {
+                TraceEnd::DeadEnd
+            },
+            guards: vec![],
         }
     }
 
-    Ok(paths)
+    #[test]
+    fn test_select() {
+        let output = ScanOutput {
+            paths: vec![trace(1, 2, true), trace(1, 3, false), trace(4, 5, false)],
+            sources: vec![],
+            metrics: CachedMetrics::default(),
+        };
+
+        let ends = |mode| {
+            output
+                .select(mode)
+                .iter()
+                .map(|t| t.steps.last().unwrap().range.start.line)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ends(ScanMode::All), vec![2, 3, 5]);
+        assert_eq!(ends(ScanMode::Sinks), vec![2]);
+        assert_eq!(ends(ScanMode::Unreached), vec![5]);
+    }
+
+    #[tokio::test]
+    async fn test_find_paths_cycle() {
+        let graph = Graph::new(&[(1, &[2]), (2, &[3, 1]), (3, &[4])]);
+
+        let traces = find_paths(
+            &graph,
+            &NoLsp::default(),
+            location(1),
+            graph.initial_state(),
+            &[location(4)],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            display_traces(&traces),
+            vec![
+                format!("1 -> 2 -> 3 -> 4 Sink({:?})", location(4)),
+                "1 -> 2 -> 1 Cycle".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_paths_memoized() {
+        let graph = Graph::new(&[(1, &[2, 3]), (2, &[4]), (3, &[4]), (4, &[5])]);
+
+        let traces = find_paths(
+            &graph,
+            &NoLsp::default(),
+            location(1),
+            graph.initial_state(),
+            &[],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            display_traces(&traces),
+            vec!["1 -> 2 -> 4 -> 5 DeadEnd", "1 -> 3 -> 4 -> 5 DeadEnd"]
+        );
+        assert_eq!(*graph.transitions.lock().unwrap(), vec![1, 2, 4, 5, 3]);
+    }
 }
@@ -0,0 +1,840 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lsp_types::{Location, Url};
+use tree_sitter::Node;
+
+use crate::{
+    engine::EngineOptions,
+    language_provider::{
+        GraphOutput, LanguageAutomata, Queries, ScanOutput, SupportedLanguage, Trace,
+    },
+    languages::{find_project_graph, find_project_paths, LibraryPaths, ProjectServer, StepMeta},
+    rules::{parse_rules, Rule},
+    sources::SourceCache,
+    utils::{get_breadcrumbs, get_node_location, get_query_results},
+};
+
+/// Language servers tried in order, the first one installed is used.
+const SERVERS: &[&str] = &["gopls"];
+
+const DEFAULT_RULES: &str = include_str!("../../rules/go.toml");
+
+const LOCALS: &str = include_str!("../../queries/go/locals.scm");
+
+/// Expressions whose value is derived from their operands.
+const COMPOSITE_EXPRESSIONS: &[&str] = &[
+    "binary_expression",
+    "unary_expression",
+    "parenthesized_expression",
+    "type_conversion_expression",
+    "composite_literal",
+    "literal_value",
+    "keyed_element",
+    "literal_element",
+    "variadic_argument",
+    "expression_list",
+];
+
+/// Expressions whose value is derived from their operand, e.g. `x.y`.
+const OPERAND_EXPRESSIONS: &[&str] = &[
+    "selector_expression",
+    "index_expression",
+    "slice_expression",
+    "type_assertion_expression",
+];
+
+pub struct Go;
+
+/// The module cache, vendored modules and the standard library.
+const LIBRARIES: LibraryPaths = LibraryPaths {
+    dirs: &["pkg/mod", "vendor", "go/src", "libexec/src"],
+    suffixes: &[],
+};
+
+fn is_comment(node: &Node) -> bool {
+    node.kind() == "comment"
+}
+
+fn named_children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    let children = node
+        .named_children(&mut cursor)
+        .filter(|n| !is_comment(n))
+        .collect();
+    children
+}
+
+/// The node whose definition a call's function is resolved by,
+/// e.g. `Method` in `obj.Method()`.
+fn function_name(call: Node) -> Option<Node> {
+    let function = call.child_by_field_name("function")?;
+    match function.kind() {
+        "selector_expression" => function.child_by_field_name("field"),
+        "identifier" => Some(function),
+        _ => None,
+    }
+}
+
+/// The call whose function is resolved by `name`.
+fn call_of(name: Node) -> Option<Node> {
+    let mut node = name;
+    for _ in 0..2 {
+        node = node.parent()?;
+        if node.kind() == "call_expression" {
+            return (function_name(node) == Some(name)).then_some(node);
+        }
+    }
+
+    None
+}
+
+/// Operand of a call's selector, the receiver of a method call, or
+/// the package of a function.
+fn receiver(call: Node) -> Option<Node> {
+    call.child_by_field_name("function")
+        .filter(|f| f.kind() == "selector_expression")
+        .and_then(|f| f.child_by_field_name("operand"))
+}
+
+fn call_arguments(call: Node) -> Vec<Node> {
+    call.child_by_field_name("arguments")
+        .map(named_children)
+        .unwrap_or_default()
+}
+
+/// Value assigned to one of `names`, and the item of a multiple value it
+/// is bound to when there are fewer values than names.
+fn value_of<'a>(
+    names: &[Node<'a>],
+    name: Node<'a>,
+    values: Option<Node<'a>>,
+) -> Option<(Node<'a>, Option<usize>)> {
+    let position = names.iter().position(|n| *n == name)?;
+    let values = named_children(values?);
+
+    match values.as_slice() {
+        [value] if names.len() > 1 => Some((*value, Some(position))),
+        _ => values.get(position).map(|value| (*value, None)),
+    }
+}
+
+/// Value a variable is declared or assigned with, e.g. `f()` for `x`
+/// in `x, err := f()`, and the item of it the variable is bound to.
+fn assigned_value(identifier: Node) -> Option<(Node, Option<usize>)> {
+    let parent = identifier.parent()?;
+    match parent.kind() {
+        "var_spec" | "const_spec" => {
+            let mut cursor = parent.walk();
+            let names = parent
+                .children_by_field_name("name", &mut cursor)
+                .filter(|name| name.is_named())
+                .collect::<Vec<_>>();
+            value_of(&names, identifier, parent.child_by_field_name("value"))
+        }
+        "expression_list" => {
+            let statement = parent.parent()?;
+            if statement.child_by_field_name("left") != Some(parent) {
+                return None;
+            }
+
+            match statement.kind() {
+                "short_var_declaration" | "assignment_statement" => value_of(
+                    &named_children(parent),
+                    identifier,
+                    statement.child_by_field_name("right"),
+                ),
+                "range_clause" | "receive_statement" => {
+                    Some((statement.child_by_field_name("right")?, None))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+impl Go {
+    /// Values a library call's result is derived from: its arguments,
+    /// and the receiver of a method call.
+    fn library_inputs(&self, uri: &Url, call: Node) -> Vec<Location> {
+        receiver(call)
+            .into_iter()
+            .chain(call_arguments(call))
+            .map(|input| get_node_location(uri.clone(), &input))
+            .collect()
+    }
+
+    /// Resolve a parameter or receiver from the call it was reached from,
+    /// or from all calls to its function.
+    fn resolve_parameter(
+        &self,
+        uri: &Url,
+        anchor: Option<Location>,
+        declaration: Node,
+        name: Node,
+    ) -> Vec<(Location, Vec<StepMeta>)> {
+        let Some(parameters) = declaration.parent() else {
+            return vec![];
+        };
+        let Some(function) = parameters.parent() else {
+            return vec![];
+        };
+
+        let goto = if function.child_by_field_name("receiver") == Some(parameters) {
+            StepMeta::GotoReceiver
+        } else {
+            let mut cursor = parameters.walk();
+            let names = parameters
+                .named_children(&mut cursor)
+                .flat_map(|declaration| {
+                    let mut cursor = declaration.walk();
+                    let names = declaration
+                        .children_by_field_name("name", &mut cursor)
+                        .filter(|name| name.is_named())
+                        .collect::<Vec<_>>();
+                    names
+                })
+                .collect::<Vec<_>>();
+
+            let Some(index) = names.iter().position(|n| *n == name) else {
+                return vec![];
+            };
+            StepMeta::GotoArgument(index)
+        };
+
+        let resolve = StepMeta::Resolve {
+            anchor: None,
+            index: None,
+        };
+
+        match (anchor, function.child_by_field_name("name")) {
+            (Some(anchor), _) => vec![(anchor, vec![resolve, goto])],
+            (None, Some(function_name)) => vec![(
+                get_node_location(uri.clone(), &function_name),
+                vec![resolve, goto, StepMeta::GotoReference],
+            )],
+            (None, None) => vec![],
+        }
+    }
+
+    /// Values a function returns, as the expression lists of its returns.
+    fn returns<'a>(
+        &self,
+        text: &str,
+        function: Node<'a>,
+        sources: &SourceCache,
+    ) -> Result<Vec<Node<'a>>> {
+        let Some(body) = function.child_by_field_name("body") else {
+            return Ok(vec![]);
+        };
+
+        Ok(get_query_results(
+            text,
+            body,
+            &*sources.query("(return_statement (expression_list) @return)")?,
+            0,
+        ))
+    }
+}
+
+impl LanguageAutomata for Go {
+    type Stack = StepMeta;
+    type LspProvider = ProjectServer;
+
+    fn get_language(&self) -> tree_sitter::Language {
+        tree_sitter_go::language()
+    }
+
+    fn initial_state(&self) -> Vec<Self::Stack> {
+        vec![StepMeta::Start]
+    }
+
+    fn required_queries(&self, _location: &Location, state: &Self::Stack) -> Queries {
+        state.queries()
+    }
+
+    fn transition(
+        &self,
+        location: Location,
+        state: Self::Stack,
+        definitions: Result<Vec<Location>>,
+        references: Result<Vec<Location>>,
+        sources: &SourceCache,
+    ) -> Result<Vec<(Location, Vec<Self::Stack>)>> {
+        let file = sources.get(&location.uri)?;
+        let root = file.tree.root_node();
+
+        let Some(breadcrumbs) = get_breadcrumbs(root, &location) else {
+            return Ok(vec![]);
+        };
+
+        let breadcrumbs = breadcrumbs
+            .into_iter()
+            .map(|n| (n.kind(), n))
+            .collect::<Vec<_>>();
+
+        let uri = location.uri.clone();
+        let is_field = |parent: &Node, field: &str, node: &Node| {
+            parent.child_by_field_name(field).as_ref() == Some(node)
+        };
+
+        Ok(
+            match (state, breadcrumbs.as_slice(), definitions, references) {
+                (StepMeta::Start, _, _, _) => {
+                    vec![(
+                        location,
+                        vec![
+                            StepMeta::Start,
+                            StepMeta::Resolve {
+                                anchor: None,
+                                index: None,
+                            },
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::GotoDefinition,
+                    [("identifier" | "field_identifier", name), ..],
+                    Ok(definitions),
+                    _,
+                ) if call_of(*name).is_some()
+                    && definitions.iter().all(|d| LIBRARIES.contains(d)) =>
+                {
+                    call_of(*name)
+                        .map(|call| self.library_inputs(&uri, call))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|input| (input, vec![]))
+                        .collect()
+                }
+                (
+                    StepMeta::GotoDefinition | StepMeta::GotoImplementation,
+                    [("identifier" | "field_identifier", _), ..],
+                    Ok(definitions),
+                    _,
+                ) => definitions
+                    .into_iter()
+                    .filter(|d| !LIBRARIES.contains(d))
+                    .map(|d| (d, vec![]))
+                    .collect(),
+                (
+                    StepMeta::GotoReference,
+                    [("identifier" | "field_identifier", _), ("function_declaration" | "method_declaration", _), ..],
+                    _,
+                    Ok(references),
+                ) => references.into_iter().map(|r| (r, vec![])).collect(),
+                (StepMeta::GotoArgument(index), [(_, name), ..], _, _) => call_of(*name)
+                    .and_then(|call| call_arguments(call).into_iter().nth(index))
+                    .map(|argument| (get_node_location(uri, &argument), vec![]))
+                    .into_iter()
+                    .collect(),
+                (StepMeta::GotoReceiver, [(_, name), ..], _, _) => call_of(*name)
+                    .and_then(receiver)
+                    .map(|receiver| (get_node_location(uri, &receiver), vec![]))
+                    .into_iter()
+                    .collect(),
+                (StepMeta::Resolve { anchor, index }, [("identifier", identifier), ..], _, _)
+                    if assigned_value(*identifier).is_some() =>
+                {
+                    assigned_value(*identifier)
+                        .map(|(value, item)| {
+                            (
+                                get_node_location(uri, &value),
+                                vec![StepMeta::Resolve {
+                                    anchor,
+                                    index: item.or(index),
+                                }],
+                            )
+                        })
+                        .into_iter()
+                        .collect()
+                }
+                (
+                    StepMeta::Resolve { anchor, .. },
+                    [("identifier", name), ("parameter_declaration" | "variadic_parameter_declaration", declaration), ("parameter_list", _), ..],
+                    _,
+                    _,
+                ) => self.resolve_parameter(&uri, anchor, *declaration, *name),
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("identifier" | "field_identifier", name), ("function_declaration" | "method_declaration", function), ..],
+                    _,
+                    _,
+                ) if is_field(function, "name", name) => self
+                    .returns(&file.text, *function, sources)?
+                    .iter()
+                    .map(|node| (get_node_location(uri.clone(), node), vec![state.clone()]))
+                    .collect(),
+                (state @ StepMeta::Resolve { .. }, [("func_literal", function), ..], _, _) => self
+                    .returns(&file.text, *function, sources)?
+                    .iter()
+                    .map(|node| (get_node_location(uri.clone(), node), vec![state.clone()]))
+                    .collect(),
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("field_identifier", name), ("method_spec", method), ..],
+                    _,
+                    _,
+                ) if is_field(method, "name", name) => {
+                    vec![(location, vec![state, StepMeta::GotoImplementation])]
+                }
+                (StepMeta::Resolve { anchor, index }, [("call_expression", call), ..], _, _) => {
+                    let Some(function) = function_name(*call) else {
+                        return Ok(vec![]);
+                    };
+
+                    let function = get_node_location(uri, &function);
+                    vec![(
+                        function.clone(),
+                        vec![
+                            StepMeta::Resolve { anchor, index },
+                            StepMeta::Resolve {
+                                anchor: Some(function),
+                                index,
+                            },
+                            StepMeta::GotoDefinition,
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::Resolve {
+                        anchor,
+                        index: Some(index),
+                    },
+                    [("expression_list", list), ..],
+                    _,
+                    _,
+                ) if list.named_child_count() > 1 => named_children(*list)
+                    .get(index)
+                    .map(|value| {
+                        (
+                            get_node_location(uri, value),
+                            vec![StepMeta::Resolve {
+                                anchor,
+                                index: None,
+                            }],
+                        )
+                    })
+                    .into_iter()
+                    .collect(),
+                (state @ StepMeta::Resolve { .. }, [(kind, node), ..], _, _)
+                    if OPERAND_EXPRESSIONS.contains(kind) =>
+                {
+                    node.child_by_field_name("operand")
+                        .map(|operand| (get_node_location(uri, &operand), vec![state]))
+                        .into_iter()
+                        .collect()
+                }
+                (state @ StepMeta::Resolve { .. }, [(kind, node), ..], _, _)
+                    if COMPOSITE_EXPRESSIONS.contains(kind) =>
+                {
+                    let operands = match *kind {
+                        "type_conversion_expression" => {
+                            node.child_by_field_name("operand").into_iter().collect()
+                        }
+                        "composite_literal" => {
+                            node.child_by_field_name("body").into_iter().collect()
+                        }
+                        "keyed_element" => named_children(*node)
+                            .into_iter()
+                            .last()
+                            .into_iter()
+                            .collect(),
+                        _ => named_children(*node),
+                    };
+
+                    operands
+                        .iter()
+                        .map(|n| (get_node_location(uri.clone(), n), vec![state.clone()]))
+                        .collect()
+                }
+                (state @ StepMeta::Resolve { .. }, [("identifier", _), ..], _, _) => {
+                    vec![(location, vec![state, StepMeta::GotoDefinition])]
+                }
+                _ => vec![],
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl SupportedLanguage for Go {
+    fn name(&self) -> &'static str {
+        "go"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["go"]
+    }
+
+    fn tree_sitter_language(&self) -> tree_sitter::Language {
+        tree_sitter_go::language()
+    }
+
+    fn servers(&self) -> &'static [&'static str] {
+        SERVERS
+    }
+
+    fn default_rules(&self) -> Result<Vec<Rule>> {
+        parse_rules(DEFAULT_RULES).context("invalid default go rules")
+    }
+
+    fn locals_query(&self) -> Option<&'static str> {
+        Some(LOCALS)
+    }
+
+    fn is_project_file(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|e| e == "go")
+            && !path.components().any(|c| c.as_os_str() == "vendor")
+    }
+
+    async fn find_paths(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<ScanOutput> {
+        find_project_paths(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+            on_trace,
+        )
+        .await
+    }
+
+    async fn find_graph(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+    ) -> Result<GraphOutput> {
+        find_project_graph(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+        )
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+    use crate::test_utils::RESOLVE;
+
+    snapshot!(
+        Go,
+        test_start,
+        StepMeta::Start,
+        r#"
+main.go
+#@#
+func run(db *sql.DB, name string) {
+    db.Query(query)
+    //       ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_identifier,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func run(db *sql.DB, name string) {
+    db.Query(query)
+    //       ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        goto_definition,
+        StepMeta::GotoDefinition,
+        r#"
+main.go
+#@#
+func run(db *sql.DB, name string) {
+    query := "SELECT * FROM users WHERE name = '" + name + "'"
+//  ^^^^^ definition
+    db.Query(query)
+    //       ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_short_var_declaration,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func run(name string) {
+    query := "SELECT * FROM users WHERE name = '" + name + "'"
+//  ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_multiple_values,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func run() {
+    prefix, query := "", load()
+//          ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_multiple_return_values,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func run() {
+    query, err := load()
+//  ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_var_spec,
+        RESOLVE,
+        r#"
+main.go
+#@#
+var query, limit = load()
+//         ^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_returns,
+        StepMeta::Resolve {
+            anchor: None,
+            index: Some(0),
+        },
+        r#"
+main.go
+#@#
+func load(r *http.Request) (string, error) {
+//   ^^^^ start
+    if r == nil {
+        return "", errors.New("no request")
+    }
+    return r.FormValue("q"), nil
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_return_item,
+        StepMeta::Resolve {
+            anchor: None,
+            index: Some(0),
+        },
+        r#"
+main.go
+#@#
+func load(r *http.Request) (string, error) {
+    return r.FormValue("q"), nil
+    //     ^^^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        library_call,
+        StepMeta::GotoDefinition,
+        r#"
+main.go
+#@#
+func run(name string) {
+    query := fmt.Sprintf("SELECT * FROM users WHERE name = '%s'", name)
+    //           ^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_interface_method,
+        RESOLVE,
+        r#"
+main.go
+#@#
+type Source interface {
+    Query() string
+//  ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        goto_implementation,
+        StepMeta::GotoImplementation,
+        r#"
+main.go
+#@#
+type Source interface {
+    Query() string
+//  ^^^^^ start
+}
+
+func (s *RequestSource) Query() string {
+//                      ^^^^^ definition
+    return s.request.FormValue("q")
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_selector,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func (s *RequestSource) Query() string {
+    return s.request.URL.RawQuery
+    //     ^^^^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_receiver,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func (s *RequestSource) Query() string {
+//    ^ start
+    return s.request.URL.RawQuery
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_parameter,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func run(db *sql.DB, prefix, name string) {
+//                           ^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        goto_argument,
+        StepMeta::GotoArgument(2),
+        r#"
+main.go
+#@#
+func main() {
+    run(db, "", os.Args[1])
+//  ^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        goto_receiver,
+        StepMeta::GotoReceiver,
+        r#"
+main.go
+#@#
+func main() {
+    source.Query()
+    //     ^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Go,
+        resolve_composite_literal,
+        RESOLVE,
+        r#"
+main.go
+#@#
+func build(name string) Filter {
+    return Filter{Name: name, Limit: 10}
+    //     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    #[test]
+    fn test_default_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.go");
+        std::fs::write(
+            &path,
+            r#"
+package main
+
+func handle(db *sql.DB, w http.ResponseWriter, r *http.Request) {
+    db.QueryContext(r.Context(), "SELECT "+r.FormValue("q"))
+    exec.Command("sh", "-c", os.Args[1]).Run()
+}
+"#,
+        )
+        .unwrap();
+
+        let vendored = dir.path().join("vendor/lib/lib.go");
+        std::fs::create_dir_all(vendored.parent().unwrap()).unwrap();
+        std::fs::write(&vendored, "package lib\n\nvar _ = exec.Command(os.Args[1])").unwrap();
+
+        let go = Go;
+        let rules = go.default_rules().unwrap();
+        let matches = go.match_rules(&rules, &[path, vendored]).unwrap();
+
+        let lines = |locations: &[Location]| {
+            locations
+                .iter()
+                .map(|l| l.range.start.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&matches[0].sources), vec![4]);
+        assert_eq!(lines(&matches[0].sinks), vec![3, 5]);
+        assert_eq!(lines(&matches[1].sources), vec![5, 5, 5]);
+    }
+}
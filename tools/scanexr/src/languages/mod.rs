@@ -1 +1,537 @@
+use std::{
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use lsp_client::{
+    cache::{CachedMetrics, RequestCache},
+    client::Client,
+    presets::ServerPreset,
+};
+use lsp_types::{
+    notification::{DidOpenTextDocument, Initialized},
+    request::{
+        GotoDefinition, GotoImplementation, GotoImplementationParams, Initialize, References,
+    },
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, InitializeParams,
+    InitializedParams, Location, PartialResultParams, ReferenceContext, ReferenceParams,
+    TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Url,
+    WorkDoneProgressParams,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    engine::{Backend, Engine, EngineOptions},
+    language_provider::{
+        GraphOutput, LanguageAutomata, LspProvider, Queries, ScanOutput, SupportedLanguage, Trace,
+    },
+    utils::LocationKey,
+};
+
+#[cfg(feature = "go")]
+pub mod go;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "rust")]
+pub mod rust;
+pub mod scopes;
+#[cfg(feature = "solidity")]
+pub mod solc;
+#[cfg(feature = "solidity")]
+pub mod solc_versions;
+#[cfg(feature = "solidity")]
 pub mod solidity;
+#[cfg(feature = "solidity")]
+pub mod solidity_project;
+#[cfg(feature = "typescript")]
+pub mod typescript;
+
+fn position_params(location: &Location) -> TextDocumentPositionParams {
+    TextDocumentPositionParams {
+        text_document: TextDocumentIdentifier {
+            uri: location.uri.clone(),
+        },
+        position: location.range.start,
+    }
+}
+
+fn goto_locations(response: Option<GotoDefinitionResponse>) -> Vec<Location> {
+    match response {
+        Some(GotoDefinitionResponse::Scalar(location)) => vec![location],
+        Some(GotoDefinitionResponse::Array(locations)) => locations,
+        Some(GotoDefinitionResponse::Link(links)) => links
+            .into_iter()
+            .map(|link| Location::new(link.target_uri, link.target_selection_range))
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Definitions of the symbol at `location`, as locations.
+pub(crate) async fn request_definitions(
+    client: &Client,
+    location: &Location,
+) -> Result<Vec<Location>> {
+    let definitions = client
+        .request::<GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: position_params(location),
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+            partial_result_params: PartialResultParams {
+                partial_result_token: None,
+            },
+        })
+        .await
+        .context("awaiting goto definition response")?
+        .context("getting goto definition result")?;
+
+    Ok(goto_locations(definitions))
+}
+
+/// Implementations of the interface or method at `location`, as locations.
+pub(crate) async fn request_implementations(
+    client: &Client,
+    location: &Location,
+) -> Result<Vec<Location>> {
+    let implementations = client
+        .request::<GotoImplementation>(GotoImplementationParams {
+            text_document_position_params: position_params(location),
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+            partial_result_params: PartialResultParams {
+                partial_result_token: None,
+            },
+        })
+        .await
+        .context("awaiting goto implementation response")?
+        .context("getting goto implementation result")?;
+
+    Ok(goto_locations(implementations))
+}
+
+/// References to the symbol at `location`, without its declaration.
+pub(crate) async fn request_references(
+    client: &Client,
+    location: &Location,
+) -> Result<Vec<Location>> {
+    let references = client
+        .request::<References>(ReferenceParams {
+            text_document_position: position_params(location),
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+            partial_result_params: PartialResultParams {
+                partial_result_token: None,
+            },
+            context: ReferenceContext {
+                include_declaration: false,
+            },
+        })
+        .await
+        .context("awaiting references response")?
+        .context("getting references result")?;
+
+    Ok(references.unwrap_or_default())
+}
+
+/// Language server started from the first of a list of
+/// [`ServerPreset`]s that is installed.
+pub struct PresetServer {
+    client: Client,
+    cache: Arc<RequestCache>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl PresetServer {
+    pub async fn start(
+        language: &(dyn SupportedLanguage + Sync),
+        root_dir: &Path,
+        project_files: &[PathBuf],
+    ) -> Result<Self> {
+        let presets = language.servers();
+        let Some(child) = presets
+            .iter()
+            .filter_map(|name| ServerPreset::find(name))
+            .find_map(|preset| preset.spawn().ok())
+        else {
+            bail!("failed to start any of {:?}", presets);
+        };
+
+        let (client, handles) = lsp_client::clients::child_client(child);
+        Self::with_client(client, handles, language, root_dir, project_files).await
+    }
+
+    /// Initialize the server behind `client` in `root_dir`, and open the
+    /// project's files in it, since some servers only index open files.
+    pub async fn with_client(
+        client: Client,
+        handles: Vec<JoinHandle<()>>,
+        language: &(dyn SupportedLanguage + Sync),
+        root_dir: &Path,
+        project_files: &[PathBuf],
+    ) -> Result<Self> {
+        let cache = Arc::new(RequestCache::new());
+        let client = client.with_middleware(cache.clone());
+        client
+            .request::<Initialize>(InitializeParams {
+                root_uri: Some(
+                    Url::from_file_path(root_dir)
+                        .map_err(|_| anyhow!("failed to convert root dir to url"))?,
+                ),
+                ..Default::default()
+            })
+            .await??;
+
+        client.notify::<Initialized>(InitializedParams {})?;
+
+        for file in project_files {
+            let Ok(text) = std::fs::read_to_string(file) else {
+                continue;
+            };
+
+            client.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: Url::from_file_path(file)
+                        .map_err(|_| anyhow!("failed to convert {} to url", file.display()))?,
+                    language_id: language.language_id(file).to_string(),
+                    version: 0,
+                    text,
+                },
+            })?;
+        }
+
+        Ok(Self {
+            client,
+            cache,
+            handles,
+        })
+    }
+
+    pub fn metrics(&self) -> CachedMetrics {
+        CachedMetrics::new(&self.client, &self.cache)
+    }
+}
+
+impl Drop for PresetServer {
+    fn drop(&mut self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl LspProvider for PresetServer {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        request_definitions(&self.client, location).await
+    }
+
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        request_references(&self.client, location).await
+    }
+
+    async fn find_implementations(&self, location: &Location) -> Result<Vec<Location>> {
+        request_implementations(&self.client, location).await
+    }
+}
+
+/// Definitions and references of a scan's project, from a language server
+/// or from tree-sitter scopes, see [`Backend`].
+pub enum ProjectServer {
+    Preset(PresetServer),
+    Scopes(scopes::ScopeResolver),
+}
+
+impl ProjectServer {
+    pub async fn start(
+        language: &(dyn SupportedLanguage + Sync),
+        root_dir: &Path,
+        project_files: &[PathBuf],
+        backend: Backend,
+    ) -> Result<Self> {
+        let scopes = || scopes::ScopeResolver::new(language, project_files).map(Self::Scopes);
+
+        match backend {
+            Backend::Compiler => bail!("{} has no compiler backend", language.name()),
+            Backend::TreeSitter => scopes(),
+            Backend::LanguageServer => Ok(Self::Preset(
+                PresetServer::start(language, root_dir, project_files).await?,
+            )),
+            Backend::Auto => match PresetServer::start(language, root_dir, project_files).await {
+                Ok(server) => Ok(Self::Preset(server)),
+                Err(e) if language.locals_query().is_some() => {
+                    eprintln!(
+                        "{:#}, resolving {} names with tree-sitter scopes",
+                        e,
+                        language.name()
+                    );
+                    scopes()
+                }
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    pub fn metrics(&self) -> CachedMetrics {
+        match self {
+            Self::Preset(server) => server.metrics(),
+            Self::Scopes(_) => CachedMetrics::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl LspProvider for ProjectServer {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        match self {
+            Self::Preset(server) => server.find_definitions(location).await,
+            Self::Scopes(scopes) => scopes.find_definitions(location).await,
+        }
+    }
+
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        match self {
+            Self::Preset(server) => server.find_references(location).await,
+            Self::Scopes(scopes) => scopes.find_references(location).await,
+        }
+    }
+
+    async fn find_implementations(&self, location: &Location) -> Result<Vec<Location>> {
+        match self {
+            Self::Preset(server) => server.find_implementations(location).await,
+            Self::Scopes(scopes) => scopes.find_implementations(location).await,
+        }
+    }
+}
+
+/// Stack of the languages scanned with a [`ProjectServer`], each step
+/// saying which query the next transition follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepMeta {
+    Start,
+    GotoDefinition,
+    GotoImplementation,
+    GotoArgument(usize),
+    GotoReceiver,
+    GotoReference,
+    Resolve {
+        /// Call expression function a return value was resolved from,
+        /// so if it resolves to a parameter, we return to the same call.
+        anchor: Option<Location>,
+        /// Item of a tuple, array or multiple value being resolved, e.g.
+        /// of `f()` in `x, err := f()`.
+        index: Option<usize>,
+    },
+}
+
+impl StepMeta {
+    /// Queries a transition with this step on top of the stack needs.
+    pub fn queries(&self) -> Queries {
+        Queries {
+            definitions: matches!(self, StepMeta::GotoDefinition),
+            references: matches!(self, StepMeta::GotoReference),
+            implementations: matches!(self, StepMeta::GotoImplementation),
+        }
+    }
+}
+
+impl Hash for StepMeta {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            StepMeta::GotoArgument(index) => index.hash(state),
+            StepMeta::Resolve { anchor, index } => {
+                anchor.as_ref().map(LocationKey::from).hash(state);
+                index.hash(state);
+            }
+            StepMeta::Start
+            | StepMeta::GotoDefinition
+            | StepMeta::GotoImplementation
+            | StepMeta::GotoReceiver
+            | StepMeta::GotoReference => {}
+        }
+    }
+}
+
+/// Where a language's dependencies live, definitions there are library
+/// calls instead of code to follow.
+pub struct LibraryPaths {
+    /// Directories matched anywhere in a path, e.g. `node_modules`.
+    pub dirs: &'static [&'static str],
+    /// Endings of files only declaring types, e.g. `.d.ts`.
+    pub suffixes: &'static [&'static str],
+}
+
+impl LibraryPaths {
+    /// Whether a definition is outside the project's sources.
+    pub fn contains(&self, location: &Location) -> bool {
+        let path = location.uri.path();
+        self.suffixes.iter().any(|suffix| path.ends_with(suffix))
+            || self
+                .dirs
+                .iter()
+                .any(|dir| path.contains(&format!("/{}/", dir)))
+    }
+}
+
+/// [`SupportedLanguage::find_paths`] of a language scanned with
+/// a [`ProjectServer`].
+pub async fn find_project_paths<L>(
+    language: &L,
+    root_dir: &Path,
+    project_files: Vec<PathBuf>,
+    start_locations: Vec<Location>,
+    stop_at: &[Location],
+    options: &EngineOptions,
+    on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+) -> Result<ScanOutput>
+where
+    L: LanguageAutomata<Stack = StepMeta, LspProvider = ProjectServer>
+        + SupportedLanguage
+        + Sync
+        + Send,
+{
+    let lsp = ProjectServer::start(language, root_dir, &project_files, options.backend)
+        .await
+        .with_context(|| format!("failed to start {} language server", language.name()))?;
+
+    let mut engine = Engine::new(language, &lsp, options.clone());
+    let (paths, sources) = engine
+        .explore_all(start_locations, stop_at, on_trace)
+        .await?;
+
+    Ok(ScanOutput {
+        paths,
+        sources,
+        metrics: lsp.metrics(),
+    })
+}
+
+/// [`SupportedLanguage::find_graph`] of a language scanned with
+/// a [`ProjectServer`].
+pub async fn find_project_graph<L>(
+    language: &L,
+    root_dir: &Path,
+    project_files: Vec<PathBuf>,
+    start_locations: Vec<Location>,
+    stop_at: &[Location],
+    options: &EngineOptions,
+) -> Result<GraphOutput>
+where
+    L: LanguageAutomata<Stack = StepMeta, LspProvider = ProjectServer>
+        + SupportedLanguage
+        + Sync
+        + Send,
+{
+    let lsp = ProjectServer::start(language, root_dir, &project_files, options.backend)
+        .await
+        .with_context(|| format!("failed to start {} language server", language.name()))?;
+
+    let mut engine = Engine::new(language, &lsp, options.clone());
+    let (graph, sources) = engine.explore_graph_all(start_locations, stop_at).await?;
+
+    Ok(GraphOutput {
+        graph: graph.map(|meta| format!("{:?}", meta)),
+        sources,
+        metrics: lsp.metrics(),
+    })
+}
+
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+    use super::{python::Python, *};
+
+    async fn fake_lsp_server(
+        mut client_rx: UnboundedReceiver<String>,
+        server_tx: UnboundedSender<String>,
+        notifications_tx: UnboundedSender<Value>,
+    ) {
+        while let Some(msg) = client_rx.recv().await {
+            let (_header, content) = msg
+                .split_once("\r\n\r\n")
+                .expect("got message without header");
+            let msg: Value = serde_json::from_str(content).expect("got invalid json");
+
+            match msg.get("id") {
+                Some(id) => server_tx
+                    .send(
+                        json!({"jsonrpc": "2.0", "result": {"capabilities": {}}, "id": id})
+                            .to_string(),
+                    )
+                    .expect("failed to send response"),
+                None => notifications_tx
+                    .send(msg)
+                    .expect("failed to record notification"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_library_paths() {
+        let libraries = LibraryPaths {
+            dirs: &["node_modules", ".cargo/registry"],
+            suffixes: &[".d.ts"],
+        };
+        let location = |path: &str| Location {
+            uri: Url::parse(&format!("file://{}", path)).unwrap(),
+            range: Default::default(),
+        };
+
+        assert!(libraries.contains(&location("/app/node_modules/lodash/index.js")));
+        assert!(libraries.contains(&location("/home/u/.cargo/registry/src/serde/lib.rs")));
+        assert!(libraries.contains(&location("/app/src/types.d.ts")));
+        assert!(!libraries.contains(&location("/app/src/node_modules.ts")));
+        assert!(!libraries.contains(&location("/app/src/index.ts")));
+    }
+
+    #[tokio::test]
+    async fn test_preset_server_opens_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![dir.path().join("main.py"), dir.path().join("utils.py")];
+        for (file, text) in files.iter().zip(["import utils\n", "x = 1\n"]) {
+            std::fs::write(file, text).unwrap();
+        }
+
+        let (client_tx, client_rx) = unbounded_channel();
+        let (server_tx, server_rx) = unbounded_channel();
+        let (notifications_tx, mut notifications_rx) = unbounded_channel();
+        let server_handle = tokio::spawn(fake_lsp_server(client_rx, server_tx, notifications_tx));
+
+        let server = PresetServer::with_client(
+            Client::new(client_tx, server_rx),
+            vec![server_handle],
+            &Python,
+            dir.path(),
+            &files,
+        )
+        .await
+        .unwrap();
+
+        let initialized = notifications_rx.recv().await.unwrap();
+        assert_eq!(initialized["method"], "initialized");
+
+        for (file, text) in files.iter().zip(["import utils\n", "x = 1\n"]) {
+            let opened = notifications_rx.recv().await.unwrap();
+            assert_eq!(opened["method"], "textDocument/didOpen");
+            assert_eq!(
+                opened["params"]["textDocument"],
+                json!({
+                    "uri": Url::from_file_path(file).unwrap(),
+                    "languageId": "python",
+                    "version": 0,
+                    "text": text,
+                })
+            );
+        }
+
+        drop(server);
+    }
+}
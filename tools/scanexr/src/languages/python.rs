@@ -0,0 +1,726 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lsp_types::Location;
+use tree_sitter::Node;
+
+use crate::{
+    engine::EngineOptions,
+    language_provider::{
+        GraphOutput, LanguageAutomata, Queries, ScanOutput, SupportedLanguage, Trace,
+    },
+    languages::{find_project_graph, find_project_paths, LibraryPaths, ProjectServer, StepMeta},
+    rules::{parse_rules, Rule},
+    sources::SourceCache,
+    utils::{get_breadcrumbs, get_node_location, get_query_results},
+};
+
+/// Language servers tried in order, the first one installed is used.
+const SERVERS: &[&str] = &["pylsp", "pyls", "jedi-language-server"];
+
+const DEFAULT_RULES: &str = include_str!("../../rules/python.toml");
+
+const LOCALS: &str = include_str!("../../queries/python/locals.scm");
+
+/// Expressions whose value is derived from all of their operands.
+const COMPOSITE_EXPRESSIONS: &[&str] = &[
+    "binary_operator",
+    "boolean_operator",
+    "comparison_operator",
+    "unary_operator",
+    "not_operator",
+    "conditional_expression",
+    "parenthesized_expression",
+    "await",
+    "concatenated_string",
+    "string",
+    "interpolation",
+    "list",
+    "set",
+    "dictionary",
+    "pair",
+    "list_splat",
+    "dictionary_splat",
+];
+
+pub struct Python;
+
+/// Installed packages and type stubs.
+const LIBRARIES: LibraryPaths = LibraryPaths {
+    dirs: &["site-packages", "dist-packages", "typeshed"],
+    suffixes: &[".pyi"],
+};
+
+/// Position of a parameter among the arguments of a call, `self` and
+/// `cls` are passed by the call's receiver.
+fn parameter_index(parameters: Node, parameter: Node, text: &str) -> Option<usize> {
+    let mut cursor = parameters.walk();
+    let mut index = parameters
+        .named_children(&mut cursor)
+        .position(|p| p == parameter)?;
+
+    let first = parameters.named_child(0)?;
+    if matches!(first.utf8_text(text.as_bytes()), Ok("self" | "cls")) {
+        index = index.checked_sub(1)?;
+    }
+
+    Some(index)
+}
+
+/// Positional argument of a call.
+fn positional_argument(call: Node, index: usize) -> Option<Node> {
+    let arguments = call.child_by_field_name("arguments")?;
+    let mut cursor = arguments.walk();
+    let argument = arguments
+        .named_children(&mut cursor)
+        .filter(|a| a.kind() != "keyword_argument" && a.kind() != "comment")
+        .nth(index);
+    argument
+}
+
+/// The node whose definition a call's function is resolved by,
+/// e.g. `method` in `obj.method()`.
+fn function_name(call: Node) -> Option<Node> {
+    let function = call.child_by_field_name("function")?;
+    match function.kind() {
+        "attribute" => function.child_by_field_name("attribute"),
+        _ => Some(function),
+    }
+}
+
+impl Python {
+    /// Values a library call's result is derived from: its arguments,
+    /// and the receiver of a method call.
+    fn library_inputs(&self, uri: &lsp_types::Url, call: Node) -> Vec<Location> {
+        let mut inputs = vec![];
+
+        if let Some(function) = call.child_by_field_name("function") {
+            if function.kind() == "attribute" {
+                if let Some(object) = function.child_by_field_name("object") {
+                    inputs.push(get_node_location(uri.clone(), &object));
+                }
+            }
+        }
+
+        if let Some(arguments) = call.child_by_field_name("arguments") {
+            let mut cursor = arguments.walk();
+            for argument in arguments.named_children(&mut cursor) {
+                let argument = match argument.kind() {
+                    "keyword_argument" => argument.child_by_field_name("value"),
+                    "comment" => None,
+                    _ => Some(argument),
+                };
+
+                if let Some(argument) = argument {
+                    inputs.push(get_node_location(uri.clone(), &argument));
+                }
+            }
+        }
+
+        inputs
+    }
+
+    /// Resolve a parameter from the arguments of the call it was reached
+    /// from, or of all calls to its function.
+    fn resolve_parameter(
+        &self,
+        uri: &lsp_types::Url,
+        text: &str,
+        anchor: Option<Location>,
+        function_definition: Node,
+        parameters: Node,
+        parameter: Node,
+    ) -> Vec<(Location, Vec<StepMeta>)> {
+        let Some(index) = parameter_index(parameters, parameter, text) else {
+            return vec![];
+        };
+
+        let resolve = StepMeta::Resolve {
+            anchor: None,
+            index: None,
+        };
+
+        match anchor {
+            Some(anchor) => vec![(anchor, vec![resolve, StepMeta::GotoArgument(index)])],
+            None => vec![(
+                get_node_location(
+                    uri.clone(),
+                    &function_definition.child_by_field_name("name").unwrap(),
+                ),
+                vec![
+                    resolve,
+                    StepMeta::GotoArgument(index),
+                    StepMeta::GotoReference,
+                ],
+            )],
+        }
+    }
+}
+
+impl LanguageAutomata for Python {
+    type Stack = StepMeta;
+    type LspProvider = ProjectServer;
+
+    fn get_language(&self) -> tree_sitter::Language {
+        tree_sitter_python::language()
+    }
+
+    fn initial_state(&self) -> Vec<Self::Stack> {
+        vec![StepMeta::Start]
+    }
+
+    fn required_queries(&self, _location: &Location, state: &Self::Stack) -> Queries {
+        state.queries()
+    }
+
+    fn transition(
+        &self,
+        location: Location,
+        state: Self::Stack,
+        definitions: Result<Vec<Location>>,
+        references: Result<Vec<Location>>,
+        sources: &SourceCache,
+    ) -> Result<Vec<(Location, Vec<Self::Stack>)>> {
+        let file = sources.get(&location.uri)?;
+        let root = file.tree.root_node();
+
+        let Some(breadcrumbs) = get_breadcrumbs(root, &location) else {
+            return Ok(vec![]);
+        };
+
+        let breadcrumbs = breadcrumbs
+            .into_iter()
+            .map(|n| (n.kind(), n))
+            .collect::<Vec<_>>();
+
+        let uri = location.uri.clone();
+        let is_field = |parent: &Node, field: &str, node: &Node| {
+            parent.child_by_field_name(field).as_ref() == Some(node)
+        };
+
+        Ok(
+            match (state, breadcrumbs.as_slice(), definitions, references) {
+                (StepMeta::Start, _, _, _) => {
+                    vec![(
+                        location,
+                        vec![
+                            StepMeta::Start,
+                            StepMeta::Resolve {
+                                anchor: None,
+                                index: None,
+                            },
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::GotoDefinition,
+                    [("identifier", identifier), ("call", call), ..]
+                    | [("identifier", identifier), ("attribute", _), ("call", call), ..],
+                    Ok(definitions),
+                    _,
+                ) if function_name(*call) == Some(*identifier)
+                    && definitions.iter().all(|d| LIBRARIES.contains(d)) =>
+                {
+                    self.library_inputs(&uri, *call)
+                        .into_iter()
+                        .map(|input| (input, vec![]))
+                        .collect()
+                }
+                (StepMeta::GotoDefinition, [("identifier", _), ..], Ok(definitions), _) => {
+                    definitions
+                        .into_iter()
+                        .filter(|d| !LIBRARIES.contains(d))
+                        .map(|d| (d, vec![]))
+                        .collect()
+                }
+                (
+                    StepMeta::GotoReference,
+                    [("identifier", _), ("function_definition", _), ..],
+                    _,
+                    Ok(references),
+                ) => references.into_iter().map(|r| (r, vec![])).collect(),
+                (
+                    StepMeta::GotoArgument(index),
+                    [("identifier", _), ("call", call), ..]
+                    | [("identifier", _), ("attribute", _), ("call", call), ..]
+                    | [("call", call), ..],
+                    _,
+                    _,
+                ) => positional_argument(*call, index)
+                    .map(|argument| (get_node_location(uri, &argument), vec![]))
+                    .into_iter()
+                    .collect(),
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("identifier", identifier), ("assignment" | "augmented_assignment" | "for_statement", assignment), ..],
+                    _,
+                    _,
+                ) if is_field(assignment, "left", identifier) => assignment
+                    .child_by_field_name("right")
+                    .map(|right| (get_node_location(uri, &right), vec![state]))
+                    .into_iter()
+                    .collect(),
+                (
+                    StepMeta::Resolve { anchor, .. },
+                    [("identifier", identifier), ("pattern_list" | "tuple_pattern", pattern), ("assignment" | "for_statement", assignment), ..],
+                    _,
+                    _,
+                ) if is_field(assignment, "left", pattern) => {
+                    let mut cursor = pattern.walk();
+                    let index = pattern
+                        .named_children(&mut cursor)
+                        .position(|p| &p == identifier);
+
+                    assignment
+                        .child_by_field_name("right")
+                        .map(|right| {
+                            (
+                                get_node_location(uri, &right),
+                                vec![StepMeta::Resolve { anchor, index }],
+                            )
+                        })
+                        .into_iter()
+                        .collect()
+                }
+                (
+                    StepMeta::Resolve { anchor, .. },
+                    [("identifier", parameter), ("parameters", parameters), ("function_definition", function_definition), ..],
+                    _,
+                    _,
+                ) => self.resolve_parameter(
+                    &uri,
+                    &file.text,
+                    anchor,
+                    *function_definition,
+                    *parameters,
+                    *parameter,
+                ),
+                (
+                    StepMeta::Resolve { anchor, .. },
+                    [("identifier", identifier), (
+                        "default_parameter" | "typed_parameter" | "typed_default_parameter",
+                        parameter,
+                    ), ("parameters", parameters), ("function_definition", function_definition), ..],
+                    _,
+                    _,
+                ) if parameter.kind() == "typed_parameter"
+                    || is_field(parameter, "name", identifier) =>
+                {
+                    self.resolve_parameter(
+                        &uri,
+                        &file.text,
+                        anchor,
+                        *function_definition,
+                        *parameters,
+                        *parameter,
+                    )
+                }
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("identifier", identifier), ("function_definition", function_definition), ..],
+                    _,
+                    _,
+                ) if is_field(function_definition, "name", identifier) => get_query_results(
+                    &file.text,
+                    *function_definition,
+                    &*sources.query("(return_statement (_) @return)")?,
+                    0,
+                )
+                .iter()
+                .map(|node| (get_node_location(uri.clone(), node), vec![state.clone()]))
+                .collect(),
+                (StepMeta::Resolve { anchor, index }, [("call", call), ..], _, _) => {
+                    let Some(function) = function_name(*call) else {
+                        return Ok(vec![]);
+                    };
+
+                    let function = get_node_location(uri, &function);
+                    vec![(
+                        function.clone(),
+                        vec![
+                            StepMeta::Resolve { anchor, index },
+                            StepMeta::Resolve {
+                                anchor: Some(function),
+                                index,
+                            },
+                            StepMeta::GotoDefinition,
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::Resolve {
+                        anchor,
+                        index: Some(index),
+                    },
+                    [("tuple" | "expression_list" | "list", tuple), ..],
+                    _,
+                    _,
+                ) => {
+                    let mut cursor = tuple.walk();
+                    let item = tuple.named_children(&mut cursor).nth(index);
+
+                    item.map(|item| {
+                        (
+                            get_node_location(uri, &item),
+                            vec![StepMeta::Resolve {
+                                anchor,
+                                index: None,
+                            }],
+                        )
+                    })
+                    .into_iter()
+                    .collect()
+                }
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("attribute" | "subscript", node), ..],
+                    _,
+                    _,
+                ) => {
+                    let object = node
+                        .child_by_field_name("object")
+                        .or_else(|| node.child_by_field_name("value"));
+
+                    object
+                        .map(|object| (get_node_location(uri, &object), vec![state]))
+                        .into_iter()
+                        .collect()
+                }
+                (state @ StepMeta::Resolve { .. }, [("keyword_argument", node), ..], _, _) => node
+                    .child_by_field_name("value")
+                    .map(|value| (get_node_location(uri, &value), vec![state]))
+                    .into_iter()
+                    .collect(),
+                (state @ StepMeta::Resolve { .. }, [(kind, node), ..], _, _)
+                    if COMPOSITE_EXPRESSIONS.contains(kind)
+                        || matches!(*kind, "tuple" | "expression_list") =>
+                {
+                    let mut cursor = node.walk();
+                    let operands = node
+                        .named_children(&mut cursor)
+                        .filter(|n| {
+                            !matches!(
+                                n.kind(),
+                                "string_start"
+                                    | "string_content"
+                                    | "string_end"
+                                    | "escape_sequence"
+                                    | "type_conversion"
+                                    | "format_specifier"
+                                    | "comment"
+                            )
+                        })
+                        .map(|n| (get_node_location(uri.clone(), &n), vec![state.clone()]))
+                        .collect::<Vec<_>>();
+                    operands
+                }
+                (state @ StepMeta::Resolve { .. }, [("identifier", _), ..], _, _) => {
+                    vec![(location, vec![state, StepMeta::GotoDefinition])]
+                }
+                _ => vec![],
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl SupportedLanguage for Python {
+    fn name(&self) -> &'static str {
+        "python"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["py"]
+    }
+
+    fn tree_sitter_language(&self) -> tree_sitter::Language {
+        tree_sitter_python::language()
+    }
+
+    fn servers(&self) -> &'static [&'static str] {
+        SERVERS
+    }
+
+    fn default_rules(&self) -> Result<Vec<Rule>> {
+        parse_rules(DEFAULT_RULES).context("invalid default python rules")
+    }
+
+    fn locals_query(&self) -> Option<&'static str> {
+        Some(LOCALS)
+    }
+
+    fn import_path(&self, import: &str) -> String {
+        let module = import.trim_start_matches('.');
+        let path = module.replace('.', "/");
+        match import.len() - module.len() {
+            0 => path,
+            1 => format!("./{}", path),
+            dots => format!("{}{}", "../".repeat(dots - 1), path),
+        }
+    }
+
+    async fn find_paths(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<ScanOutput> {
+        find_project_paths(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+            on_trace,
+        )
+        .await
+    }
+
+    async fn find_graph(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+    ) -> Result<GraphOutput> {
+        find_project_graph(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+        )
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+    use crate::test_utils::RESOLVE;
+
+    snapshot!(
+        Python,
+        test_start,
+        StepMeta::Start,
+        r#"
+main.py
+#@#
+import os
+
+def run(name):
+    command = "ls " + name
+    os.system(command)
+    #         ^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_identifier,
+        RESOLVE,
+        r#"
+main.py
+#@#
+import os
+
+def run(name):
+    command = "ls " + name
+    os.system(command)
+    #         ^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        goto_definition,
+        StepMeta::GotoDefinition,
+        r#"
+main.py
+#@#
+import os
+
+def run(name):
+    command = "ls " + name
+#   ^^^^^^^ definition
+    os.system(command)
+    #         ^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_assignment,
+        RESOLVE,
+        r#"
+main.py
+#@#
+def run(name):
+    command = "ls " + name
+#   ^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_binary_operator,
+        RESOLVE,
+        r#"
+main.py
+#@#
+def run(name):
+    command = "ls " + name
+    #         ^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_f_string,
+        RESOLVE,
+        r#"
+main.py
+#@#
+def run(name):
+    command = f"ls {name}"
+    #         ^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_tuple_unpacking,
+        RESOLVE,
+        r#"
+main.py
+#@#
+def run():
+    first, second = load()
+    #      ^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_tuple_item,
+        StepMeta::Resolve {
+            anchor: None,
+            index: Some(1),
+        },
+        r#"
+main.py
+#@#
+def load(name):
+    return "ls", name
+    #      ^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_returns,
+        RESOLVE,
+        r#"
+main.py
+#@#
+def build(name):
+#   ^^^^^ start
+    if name:
+        return "ls " + name
+    return "ls"
+        "#
+    );
+
+    snapshot!(
+        Python,
+        library_call,
+        StepMeta::GotoDefinition,
+        r#"
+main.py
+#@#
+def run(name):
+    command = name.strip(" ")
+    #              ^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_attribute,
+        RESOLVE,
+        r#"
+main.py
+#@#
+def run(request):
+    command = request.args["command"]
+    #         ^^^^^^^^^^^^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        Python,
+        resolve_parameter,
+        RESOLVE,
+        r#"
+main.py
+#@#
+class Runner:
+    def run(self, name, flags=None):
+        #               ^^^^^ start
+        pass
+        "#
+    );
+
+    snapshot!(
+        Python,
+        goto_argument,
+        StepMeta::GotoArgument(1),
+        r#"
+main.py
+#@#
+runner.run("ls", user, flags=1)
+#      ^^^ start
+        "#
+    );
+
+    #[test]
+    fn test_default_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.py");
+        std::fs::write(
+            &path,
+            r#"
+import os
+from flask import request
+
+def run():
+    os.system("ls " + request.args["path"])
+    eval(input())
+    print(input())
+"#,
+        )
+        .unwrap();
+
+        let python = Python;
+        let rules = python.default_rules().unwrap();
+        let matches = python.match_rules(&rules, &[path]).unwrap();
+
+        let lines = |locations: &[Location]| {
+            locations
+                .iter()
+                .map(|l| l.range.start.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&matches[0].sources), vec![5]);
+        assert_eq!(lines(&matches[0].sinks), vec![5, 6, 7]);
+        assert_eq!(lines(&matches[1].sources), vec![6]);
+    }
+}
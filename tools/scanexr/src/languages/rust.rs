@@ -0,0 +1,893 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lsp_types::{Location, Url};
+use tree_sitter::Node;
+
+use crate::{
+    engine::EngineOptions,
+    language_provider::{
+        GraphOutput, LanguageAutomata, Queries, ScanOutput, SupportedLanguage, Trace,
+    },
+    languages::{find_project_graph, find_project_paths, LibraryPaths, ProjectServer, StepMeta},
+    rules::{parse_rules, Rule},
+    sources::SourceCache,
+    utils::{get_breadcrumbs, get_node_location, get_query_results},
+};
+
+/// Language servers tried in order, the first one installed is used.
+const SERVERS: &[&str] = &["rust-analyzer"];
+
+const DEFAULT_RULES: &str = include_str!("../../rules/rust.toml");
+
+const LOCALS: &str = include_str!("../../queries/rust/locals.scm");
+
+/// Expressions whose value is derived from their operands.
+const COMPOSITE_EXPRESSIONS: &[&str] = &[
+    "binary_expression",
+    "unary_expression",
+    "reference_expression",
+    "parenthesized_expression",
+    "type_cast_expression",
+    "try_expression",
+    "await_expression",
+    "index_expression",
+    "range_expression",
+    "tuple_expression",
+    "array_expression",
+    "struct_expression",
+    "block",
+    "unsafe_block",
+    "async_block",
+    "if_expression",
+    "else_clause",
+    "match_expression",
+    "return_expression",
+];
+
+/// Patterns that only wrap the pattern they bind, e.g. `mut x` or `&x`.
+const WRAPPER_PATTERNS: &[&str] = &[
+    "mut_pattern",
+    "ref_pattern",
+    "reference_pattern",
+    "captured_pattern",
+];
+
+/// Patterns that destructure their value, e.g. `Foo { x, .. }`.
+const DESTRUCTURING_PATTERNS: &[&str] = &[
+    "tuple_pattern",
+    "struct_pattern",
+    "field_pattern",
+    "tuple_struct_pattern",
+    "slice_pattern",
+    "or_pattern",
+];
+
+pub struct Rust;
+
+/// The standard library and dependencies.
+const LIBRARIES: LibraryPaths = LibraryPaths {
+    dirs: &["rustlib/src", ".cargo/registry", ".cargo/git"],
+    suffixes: &[],
+};
+
+fn is_comment(node: &Node) -> bool {
+    matches!(
+        node.kind(),
+        "line_comment" | "block_comment" | "attribute_item"
+    )
+}
+
+/// The node whose definition a call's function is resolved by,
+/// e.g. `method` in `obj.method()` or `new` in `Command::new()`.
+fn function_name(call: Node) -> Option<Node> {
+    let mut function = call.child_by_field_name("function")?;
+    if function.kind() == "generic_function" {
+        function = function.child_by_field_name("function")?;
+    }
+
+    match function.kind() {
+        "field_expression" => function.child_by_field_name("field"),
+        "scoped_identifier" => function.child_by_field_name("name"),
+        _ => Some(function),
+    }
+}
+
+/// The call whose function is resolved by `name`.
+fn call_of(name: Node) -> Option<Node> {
+    let mut node = name;
+    for _ in 0..3 {
+        node = node.parent()?;
+        if node.kind() == "call_expression" {
+            return (function_name(node) == Some(name)).then_some(node);
+        }
+    }
+
+    None
+}
+
+/// Receiver of a method call, e.g. `obj` in `obj.method()`.
+fn receiver(call: Node) -> Option<Node> {
+    let mut function = call.child_by_field_name("function")?;
+    if function.kind() == "generic_function" {
+        function = function.child_by_field_name("function")?;
+    }
+
+    match function.kind() {
+        "field_expression" => function.child_by_field_name("value"),
+        _ => None,
+    }
+}
+
+/// Arguments of a call, starting with the receiver of a method call,
+/// which is passed as its `self` parameter.
+fn call_arguments(call: Node) -> Vec<Node> {
+    let mut arguments = receiver(call).into_iter().collect::<Vec<_>>();
+
+    if let Some(list) = call.child_by_field_name("arguments") {
+        let mut cursor = list.walk();
+        arguments.extend(list.named_children(&mut cursor).filter(|a| !is_comment(a)));
+    }
+
+    arguments
+}
+
+/// Position of a parameter among the parameters of a function,
+/// including `self`.
+fn parameter_index(parameters: Node, parameter: Node) -> Option<usize> {
+    let mut cursor = parameters.walk();
+    let index = parameters
+        .named_children(&mut cursor)
+        .filter(|p| matches!(p.kind(), "parameter" | "self_parameter"))
+        .position(|p| p == parameter);
+
+    index
+}
+
+/// Declaration binding an identifier in its pattern, e.g. a `let` or a
+/// parameter, and the item of a destructured tuple it is bound to.
+fn binding_of(identifier: Node) -> Option<(Node, Option<usize>)> {
+    let mut node = identifier;
+    let mut index = None;
+
+    loop {
+        let parent = node.parent()?;
+        match parent.kind() {
+            "tuple_pattern" => {
+                let mut cursor = parent.walk();
+                index = parent.named_children(&mut cursor).position(|p| p == node);
+            }
+            kind if WRAPPER_PATTERNS.contains(&kind) => {}
+            kind if DESTRUCTURING_PATTERNS.contains(&kind) => index = None,
+            "match_pattern" => {
+                let match_expression = parent.parent()?.parent()?.parent()?;
+                return Some((match_expression, index));
+            }
+            "let_declaration" | "let_condition" | "for_expression" | "parameter" => {
+                return (parent.child_by_field_name("pattern") == Some(node))
+                    .then_some((parent, index));
+            }
+            _ => return None,
+        }
+        node = parent;
+    }
+}
+
+/// Final expression of a block, which is its value.
+fn tail_expression(block: Node) -> Option<Node> {
+    let mut cursor = block.walk();
+    let last = block
+        .named_children(&mut cursor)
+        .filter(|n| !is_comment(n))
+        .last()?;
+
+    match last.kind() {
+        "expression_statement" => {
+            let ends_with_semicolon = last
+                .child(last.child_count().checked_sub(1)?)
+                .is_some_and(|c| c.kind() == ";");
+            (!ends_with_semicolon)
+                .then(|| last.named_child(0))
+                .flatten()
+        }
+        "let_declaration" | "use_declaration" | "empty_statement" => None,
+        kind if kind.ends_with("_item") => None,
+        _ => Some(last),
+    }
+}
+
+/// Sub-expressions the value of a composite expression is derived from.
+fn operands(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    match node.kind() {
+        "type_cast_expression" => node.child_by_field_name("value").into_iter().collect(),
+        "index_expression" => node.named_child(0).into_iter().collect(),
+        "block" => tail_expression(node).into_iter().collect(),
+        "if_expression" => node
+            .child_by_field_name("consequence")
+            .into_iter()
+            .chain(node.child_by_field_name("alternative"))
+            .collect(),
+        "match_expression" => node
+            .child_by_field_name("body")
+            .map(|body| {
+                body.named_children(&mut cursor)
+                    .filter_map(|arm| arm.child_by_field_name("value"))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "struct_expression" => node
+            .child_by_field_name("body")
+            .map(|body| {
+                body.named_children(&mut cursor)
+                    .filter_map(|field| match field.kind() {
+                        "field_initializer" => field.child_by_field_name("value"),
+                        "shorthand_field_initializer" | "base_field_initializer" => field
+                            .named_children(&mut field.walk())
+                            .find(|n| !is_comment(n)),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => node
+            .named_children(&mut cursor)
+            .filter(|n| !is_comment(n) && n.kind() != "mutable_specifier")
+            .collect(),
+    }
+}
+
+impl Rust {
+    /// Values a library call's result is derived from: its arguments,
+    /// and the receiver of a method call.
+    fn library_inputs(&self, uri: &Url, call: Node) -> Vec<Location> {
+        call_arguments(call)
+            .iter()
+            .map(|argument| get_node_location(uri.clone(), argument))
+            .collect()
+    }
+
+    /// Resolve a parameter from the arguments of the call it was reached
+    /// from, or of all calls to its function.
+    fn resolve_parameter(
+        &self,
+        uri: &Url,
+        anchor: Option<Location>,
+        parameter: Node,
+    ) -> Vec<(Location, Vec<StepMeta>)> {
+        let Some(parameters) = parameter.parent() else {
+            return vec![];
+        };
+        let Some(function_item) = parameters.parent().filter(|f| f.kind() == "function_item")
+        else {
+            return vec![];
+        };
+        let Some(index) = parameter_index(parameters, parameter) else {
+            return vec![];
+        };
+
+        let resolve = StepMeta::Resolve {
+            anchor: None,
+            index: None,
+        };
+
+        match anchor {
+            Some(anchor) => vec![(anchor, vec![resolve, StepMeta::GotoArgument(index)])],
+            None => vec![(
+                get_node_location(
+                    uri.clone(),
+                    &function_item.child_by_field_name("name").unwrap(),
+                ),
+                vec![
+                    resolve,
+                    StepMeta::GotoArgument(index),
+                    StepMeta::GotoReference,
+                ],
+            )],
+        }
+    }
+}
+
+impl LanguageAutomata for Rust {
+    type Stack = StepMeta;
+    type LspProvider = ProjectServer;
+
+    fn get_language(&self) -> tree_sitter::Language {
+        tree_sitter_rust::language()
+    }
+
+    fn initial_state(&self) -> Vec<Self::Stack> {
+        vec![StepMeta::Start]
+    }
+
+    fn required_queries(&self, _location: &Location, state: &Self::Stack) -> Queries {
+        state.queries()
+    }
+
+    fn transition(
+        &self,
+        location: Location,
+        state: Self::Stack,
+        definitions: Result<Vec<Location>>,
+        references: Result<Vec<Location>>,
+        sources: &SourceCache,
+    ) -> Result<Vec<(Location, Vec<Self::Stack>)>> {
+        let file = sources.get(&location.uri)?;
+        let root = file.tree.root_node();
+
+        let Some(breadcrumbs) = get_breadcrumbs(root, &location) else {
+            return Ok(vec![]);
+        };
+
+        let breadcrumbs = breadcrumbs
+            .into_iter()
+            .map(|n| (n.kind(), n))
+            .collect::<Vec<_>>();
+
+        let uri = location.uri.clone();
+        let is_field = |parent: &Node, field: &str, node: &Node| {
+            parent.child_by_field_name(field).as_ref() == Some(node)
+        };
+
+        Ok(
+            match (state, breadcrumbs.as_slice(), definitions, references) {
+                (StepMeta::Start, _, _, _) => {
+                    vec![(
+                        location,
+                        vec![
+                            StepMeta::Start,
+                            StepMeta::Resolve {
+                                anchor: None,
+                                index: None,
+                            },
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::GotoDefinition,
+                    [("identifier" | "field_identifier", name), ..],
+                    Ok(definitions),
+                    _,
+                ) if call_of(*name).is_some()
+                    && definitions.iter().all(|d| LIBRARIES.contains(d)) =>
+                {
+                    call_of(*name)
+                        .map(|call| self.library_inputs(&uri, call))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|input| (input, vec![]))
+                        .collect()
+                }
+                (
+                    StepMeta::GotoDefinition,
+                    [("identifier" | "field_identifier" | "self", _), ..],
+                    Ok(definitions),
+                    _,
+                ) => definitions
+                    .into_iter()
+                    .filter(|d| !LIBRARIES.contains(d))
+                    .map(|d| (d, vec![]))
+                    .collect(),
+                (
+                    StepMeta::GotoReference,
+                    [("identifier", _), ("function_item", _), ..],
+                    _,
+                    Ok(references),
+                ) => references.into_iter().map(|r| (r, vec![])).collect(),
+                (StepMeta::GotoArgument(index), [(_, name), ..], _, _) => call_of(*name)
+                    .and_then(|call| call_arguments(call).into_iter().nth(index))
+                    .map(|argument| (get_node_location(uri, &argument), vec![]))
+                    .into_iter()
+                    .collect(),
+                (
+                    StepMeta::Resolve { anchor, .. },
+                    [("self", _), ("self_parameter", parameter), ..],
+                    _,
+                    _,
+                ) => self.resolve_parameter(&uri, anchor, *parameter),
+                (
+                    StepMeta::Resolve { anchor, index },
+                    [("identifier" | "shorthand_field_identifier", identifier), ..],
+                    _,
+                    _,
+                ) if binding_of(*identifier).is_some() => {
+                    let Some((binding, item)) = binding_of(*identifier) else {
+                        return Ok(vec![]);
+                    };
+
+                    let value = match binding.kind() {
+                        "parameter" => return Ok(self.resolve_parameter(&uri, anchor, binding)),
+                        _ => binding.child_by_field_name("value"),
+                    };
+
+                    value
+                        .map(|value| {
+                            (
+                                get_node_location(uri, &value),
+                                vec![StepMeta::Resolve {
+                                    anchor,
+                                    index: item.or(index),
+                                }],
+                            )
+                        })
+                        .into_iter()
+                        .collect()
+                }
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("identifier", identifier), ("function_item", function_item), ..],
+                    _,
+                    _,
+                ) if is_field(function_item, "name", identifier) => {
+                    let mut returns = get_query_results(
+                        &file.text,
+                        *function_item,
+                        &*sources.query("(return_expression (_) @return)")?,
+                        0,
+                    );
+                    returns.extend(
+                        function_item
+                            .child_by_field_name("body")
+                            .and_then(tail_expression),
+                    );
+
+                    returns
+                        .iter()
+                        .map(|node| (get_node_location(uri.clone(), node), vec![state.clone()]))
+                        .collect()
+                }
+                (StepMeta::Resolve { anchor, index }, [("call_expression", call), ..], _, _) => {
+                    let Some(function) = function_name(*call) else {
+                        return Ok(vec![]);
+                    };
+
+                    let function = get_node_location(uri, &function);
+                    vec![(
+                        function.clone(),
+                        vec![
+                            StepMeta::Resolve { anchor, index },
+                            StepMeta::Resolve {
+                                anchor: Some(function),
+                                index,
+                            },
+                            StepMeta::GotoDefinition,
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::Resolve {
+                        anchor,
+                        index: Some(index),
+                    },
+                    [("tuple_expression" | "array_expression", tuple), ..],
+                    _,
+                    _,
+                ) => {
+                    let mut cursor = tuple.walk();
+                    let item = tuple
+                        .named_children(&mut cursor)
+                        .filter(|n| !is_comment(n))
+                        .nth(index);
+
+                    item.map(|item| {
+                        (
+                            get_node_location(uri, &item),
+                            vec![StepMeta::Resolve {
+                                anchor,
+                                index: None,
+                            }],
+                        )
+                    })
+                    .into_iter()
+                    .collect()
+                }
+                (state @ StepMeta::Resolve { .. }, [("field_expression", node), ..], _, _) => node
+                    .child_by_field_name("value")
+                    .map(|value| (get_node_location(uri, &value), vec![state]))
+                    .into_iter()
+                    .collect(),
+                (state @ StepMeta::Resolve { .. }, [("macro_invocation", node), ..], _, _) => {
+                    get_query_results(
+                        &file.text,
+                        *node,
+                        &*sources.query("(token_tree [(identifier) (self)] @argument)")?,
+                        0,
+                    )
+                    .iter()
+                    .map(|n| (get_node_location(uri.clone(), n), vec![state.clone()]))
+                    .collect()
+                }
+                (state @ StepMeta::Resolve { .. }, [(kind, node), ..], _, _)
+                    if COMPOSITE_EXPRESSIONS.contains(kind) =>
+                {
+                    operands(*node)
+                        .iter()
+                        .map(|n| (get_node_location(uri.clone(), n), vec![state.clone()]))
+                        .collect()
+                }
+                (state @ StepMeta::Resolve { .. }, [("identifier" | "self", _), ..], _, _) => {
+                    vec![(location, vec![state, StepMeta::GotoDefinition])]
+                }
+                _ => vec![],
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl SupportedLanguage for Rust {
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rs"]
+    }
+
+    fn tree_sitter_language(&self) -> tree_sitter::Language {
+        tree_sitter_rust::language()
+    }
+
+    fn servers(&self) -> &'static [&'static str] {
+        SERVERS
+    }
+
+    fn default_rules(&self) -> Result<Vec<Rule>> {
+        parse_rules(DEFAULT_RULES).context("invalid default rust rules")
+    }
+
+    fn locals_query(&self) -> Option<&'static str> {
+        Some(LOCALS)
+    }
+
+    fn import_path(&self, import: &str) -> String {
+        match import.contains("::") {
+            true => import
+                .split("::")
+                .filter(|segment| !matches!(*segment, "crate" | "self" | "super"))
+                .collect::<Vec<_>>()
+                .join("/"),
+            // a `mod` declaration, of a module next to the declaring file
+            false => format!("./{}", import),
+        }
+    }
+
+    async fn find_paths(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<ScanOutput> {
+        find_project_paths(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+            on_trace,
+        )
+        .await
+    }
+
+    async fn find_graph(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+    ) -> Result<GraphOutput> {
+        find_project_graph(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+        )
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+    use crate::test_utils::RESOLVE;
+
+    snapshot!(
+        Rust,
+        test_start,
+        StepMeta::Start,
+        r#"
+main.rs
+#@#
+fn run(name: &str) {
+    Command::new("sh").arg(name);
+    //                     ^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_identifier,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(name: &str) {
+    Command::new("sh").arg(name);
+    //                     ^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        goto_definition,
+        StepMeta::GotoDefinition,
+        r#"
+main.rs
+#@#
+fn run(name: &str) {
+    let command = format!("ls {}", name);
+    //  ^^^^^^^ definition
+    Command::new("sh").arg(command);
+    //                     ^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_let,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(name: &str) {
+    let mut command = format!("ls {}", name);
+    //      ^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_macro,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(name: &str) {
+    let command = format!("ls {} {}", name, self.flags);
+    //            ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_tuple_destructuring,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run() {
+    let (first, mut second) = load();
+    //              ^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_struct_destructuring,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(request: Request) {
+    let Request { path, .. } = request;
+    //            ^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_match_arm,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(input: Option<String>) {
+    match input {
+        Some(path) => open(path),
+        //   ^^^^ start
+        None => {}
+    }
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_tuple_item,
+        StepMeta::Resolve {
+            anchor: None,
+            index: Some(1),
+        },
+        r#"
+main.rs
+#@#
+fn load(name: String) -> (String, String) {
+    ("ls".to_string(), name)
+//  ^^^^^^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_returns,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn build(name: &str) -> String {
+// ^^^^^ start
+    if name.is_empty() {
+        return "ls".to_string();
+    }
+    match name {
+        "-" => String::new(),
+        _ => format!("ls {}", name),
+    }
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        library_call,
+        StepMeta::GotoDefinition,
+        r#"
+main.rs
+#@#
+fn run(name: &str) {
+    let command = name.replace("a", "b");
+    //                 ^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_field,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(config: &Config) {
+    let command = &config.command;
+    //             ^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_struct_expression,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+fn run(path: String, flags: String) -> Config {
+    Config { command: path, flags, ..Default::default() }
+//  ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_parameter,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+impl Runner {
+    fn run(&self, name: &str, flags: u32) {
+        //                    ^^^^^ start
+    }
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        resolve_self,
+        RESOLVE,
+        r#"
+main.rs
+#@#
+impl Runner {
+    fn run(&self) {
+        //  ^^^^ start
+    }
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        goto_argument,
+        StepMeta::GotoArgument(2),
+        r#"
+main.rs
+#@#
+fn main() {
+    runner.run("ls", user, 1);
+    //     ^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        Rust,
+        goto_argument_path_call,
+        StepMeta::GotoArgument(1),
+        r#"
+main.rs
+#@#
+fn main() {
+    Runner::run(&runner, user);
+    //      ^^^ start
+}
+        "#
+    );
+
+    #[test]
+    fn test_default_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.rs");
+        std::fs::write(
+            &path,
+            r#"
+use std::process::Command;
+
+fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    Command::new("sh").arg(&args[1]).output().unwrap();
+}
+
+pub unsafe extern "C" fn copy(data: *const u8, len: usize) -> Vec<u8> {
+    std::slice::from_raw_parts(data, len).to_vec()
+}
+"#,
+        )
+        .unwrap();
+
+        let rust = Rust;
+        let rules = rust.default_rules().unwrap();
+        let matches = rust.match_rules(&rules, &[path]).unwrap();
+
+        let lines = |locations: &[Location]| {
+            locations
+                .iter()
+                .map(|l| l.range.start.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&matches[0].sources), vec![5, 5]);
+        assert_eq!(lines(&matches[0].sinks), vec![4]);
+        assert_eq!(lines(&matches[1].sources), vec![9]);
+        assert_eq!(lines(&matches[1].sinks), vec![8]);
+    }
+}
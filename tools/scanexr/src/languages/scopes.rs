@@ -0,0 +1,555 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use lsp_types::{Location, Position, Range, Url};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::{
+    converter::{Convert, Converter},
+    language_provider::{LspProvider, SupportedLanguage},
+    utils::parse_file,
+};
+
+struct Scope {
+    range: Range,
+    parent: Option<usize>,
+}
+
+struct Symbol {
+    name: String,
+    range: Range,
+    scope: usize,
+}
+
+/// Scopes of a file, the first being the whole file.
+struct FileScopes {
+    scopes: Vec<Scope>,
+    definitions: Vec<Symbol>,
+    references: Vec<Symbol>,
+    imports: Vec<Url>,
+}
+
+fn range_contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position < range.end
+}
+
+/// Sort locations of all files by path and position, so results don't
+/// depend on the order files are indexed in.
+fn sort_locations(locations: &mut [Location]) {
+    locations.sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+}
+
+fn node_range(node: &Node) -> Range {
+    Range::new(
+        Converter::convert(node.start_position()),
+        Converter::convert(node.end_position()),
+    )
+}
+
+/// `path` with `.` and `..` components applied.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Project files of the module at `import`, relative to the directory
+/// of `file` if it starts with `.`, otherwise by the longest suffix of
+/// it matching a file or directory of the project.
+fn resolve_import(file: &Path, import: &str, project_files: &[PathBuf]) -> Vec<PathBuf> {
+    let module_files = |module: &Path| {
+        project_files
+            .iter()
+            .filter(|f| {
+                f.with_extension("").ends_with(module)
+                    || f.parent().is_some_and(|dir| dir.ends_with(module))
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    if import.starts_with('.') {
+        let Some(dir) = file.parent() else {
+            return vec![];
+        };
+        let module = normalize(&dir.join(import));
+
+        return project_files
+            .iter()
+            .filter(|f| f.with_extension("") == module || f.parent() == Some(&module))
+            .cloned()
+            .collect();
+    }
+
+    let components = Path::new(import).components().collect::<Vec<_>>();
+    (0..components.len())
+        .map(|skip| module_files(&components[skip..].iter().collect::<PathBuf>()))
+        .find(|files| !files.is_empty())
+        .unwrap_or_default()
+}
+
+impl FileScopes {
+    fn new(
+        language: &(dyn SupportedLanguage + Sync),
+        query: &Query,
+        path: &Path,
+        project_files: &[PathBuf],
+    ) -> Result<Self> {
+        let (text, tree) = parse_file(path, language.tree_sitter_language())?;
+        let root = tree.root_node();
+
+        let mut scopes = vec![Scope {
+            range: node_range(&root),
+            parent: None,
+        }];
+        let mut scope_nodes = vec![root];
+        let mut definitions = vec![];
+        let mut references = vec![];
+        let mut imports = vec![];
+
+        let mut captures = QueryCursor::new()
+            .matches(query, root, text.as_bytes())
+            .flat_map(|m| m.captures.to_vec())
+            .map(|capture| {
+                (
+                    query.capture_names()[capture.index as usize].as_str(),
+                    capture.node,
+                )
+            })
+            .collect::<Vec<_>>();
+        // outer scopes first, so each scope's parent is already known
+        captures.sort_by_key(|(_, node)| (node.start_byte(), std::cmp::Reverse(node.end_byte())));
+
+        for (name, node) in &captures {
+            if *name == "local.scope" && !scope_nodes.contains(node) {
+                let parent = (0..scopes.len())
+                    .rev()
+                    .find(|&i| scope_nodes[i].byte_range().contains(&node.start_byte()));
+                scopes.push(Scope {
+                    range: node_range(node),
+                    parent,
+                });
+                scope_nodes.push(*node);
+            }
+        }
+
+        // innermost scope around `node`, or around the scope it names
+        let scope_of = |node: &Node| {
+            let scope = (0..scope_nodes.len())
+                .rev()
+                .find(|&i| {
+                    let range = scope_nodes[i].byte_range();
+                    range.start <= node.start_byte() && node.end_byte() <= range.end
+                })
+                .unwrap_or(0);
+            match scope_nodes[scope].child_by_field_name("name") == Some(*node) {
+                true => scopes[scope].parent.unwrap_or(0),
+                false => scope,
+            }
+        };
+
+        for (name, node) in &captures {
+            let symbol = || -> Result<Symbol> {
+                Ok(Symbol {
+                    name: node.utf8_text(text.as_bytes())?.to_string(),
+                    range: node_range(node),
+                    scope: scope_of(node),
+                })
+            };
+
+            match *name {
+                "local.definition" => definitions.push(symbol()?),
+                "local.reference" => references.push(symbol()?),
+                "local.import" => {
+                    let import = language.import_path(node.utf8_text(text.as_bytes())?);
+                    imports.extend(
+                        resolve_import(path, &import, project_files)
+                            .into_iter()
+                            .filter(|f| f != path)
+                            .filter_map(|f| Url::from_file_path(f).ok()),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        // patterns may capture the same name more than once
+        definitions.dedup_by(|a, b| a.range == b.range);
+        references.dedup_by(|a, b| a.range == b.range);
+        // a name is either defined or referenced
+        references.retain(|reference| {
+            !definitions
+                .iter()
+                .any(|definition| definition.range == reference.range)
+        });
+
+        Ok(Self {
+            scopes,
+            definitions,
+            references,
+            imports,
+        })
+    }
+
+    /// Innermost scope around `position`.
+    fn scope_at(&self, position: Position) -> usize {
+        (0..self.scopes.len())
+            .rev()
+            .find(|&i| range_contains(&self.scopes[i].range, position))
+            .unwrap_or(0)
+    }
+
+    /// Definitions of `name` in the scopes around `position`, the last one
+    /// before it in the innermost scope defining it, or all of them if
+    /// they all come after it.
+    fn local_definitions(&self, name: &str, position: Position) -> Vec<Range> {
+        let mut scope = Some(self.scope_at(position));
+        while let Some(current) = scope {
+            let definitions = self
+                .definitions
+                .iter()
+                .filter(|d| d.scope == current && d.name == name)
+                .collect::<Vec<_>>();
+
+            if let Some(last) = definitions.iter().rev().find(|d| d.range.start <= position) {
+                return vec![last.range];
+            }
+            if !definitions.is_empty() {
+                return definitions.iter().map(|d| d.range).collect();
+            }
+
+            scope = self.scopes[current].parent;
+        }
+
+        vec![]
+    }
+
+    fn top_level_definitions(&self, name: &str) -> impl Iterator<Item = Range> + '_ {
+        let name = name.to_string();
+        self.definitions
+            .iter()
+            .filter(move |d| d.scope == 0 && d.name == name)
+            .map(|d| d.range)
+    }
+}
+
+/// Definitions and references resolved from tree-sitter scopes, for
+/// projects no language server can be started for, or can't compile.
+///
+/// Files are indexed with the captures of the language's
+/// [`SupportedLanguage::locals_query`]:
+/// - `@local.scope`: a node whose definitions are only visible inside it.
+/// - `@local.definition`: a name defined in the innermost scope around
+///   it, or around that scope if it is the scope's `name`, e.g. of a
+///   function.
+/// - `@local.reference`: a name resolved through the scopes around it.
+/// - `@local.import`: a module, see [`SupportedLanguage::import_path`],
+///   whose files' top level definitions are visible in the importing file.
+///
+/// Names not defined in their file or its imports resolve to the top
+/// level definitions of all project files.
+pub struct ScopeResolver {
+    files: HashMap<Url, FileScopes>,
+}
+
+impl ScopeResolver {
+    pub fn new(
+        language: &(dyn SupportedLanguage + Sync),
+        project_files: &[PathBuf],
+    ) -> Result<Self> {
+        let locals = language
+            .locals_query()
+            .ok_or_else(|| anyhow!("{} has no locals query", language.name()))?;
+        let query = Query::new(language.tree_sitter_language(), locals)
+            .with_context(|| format!("invalid {} locals query", language.name()))?;
+
+        let mut files = HashMap::new();
+        for path in project_files.iter().filter(|f| language.is_project_file(f)) {
+            let Ok(uri) = Url::from_file_path(path) else {
+                continue;
+            };
+            // files that can't be read, e.g. not utf-8, have no symbols
+            if let Ok(scopes) = FileScopes::new(language, &query, path, project_files) {
+                files.insert(uri, scopes);
+            }
+        }
+
+        Ok(Self { files })
+    }
+
+    fn resolve(&self, uri: &Url, name: &str, position: Position) -> Vec<Location> {
+        let Some(file) = self.files.get(uri) else {
+            return vec![];
+        };
+
+        let locations = |uri: &Url, ranges: Vec<Range>| {
+            ranges
+                .into_iter()
+                .map(|range| Location::new(uri.clone(), range))
+                .collect::<Vec<_>>()
+        };
+
+        let local = file.local_definitions(name, position);
+        if !local.is_empty() {
+            return locations(uri, local);
+        }
+
+        let imported = file
+            .imports
+            .iter()
+            .filter_map(|import| Some((import, self.files.get(import)?)))
+            .flat_map(|(import, file)| {
+                locations(import, file.top_level_definitions(name).collect())
+            })
+            .collect::<Vec<_>>();
+        if !imported.is_empty() {
+            return imported;
+        }
+
+        let mut global = self
+            .files
+            .iter()
+            .flat_map(|(uri, file)| locations(uri, file.top_level_definitions(name).collect()))
+            .collect::<Vec<_>>();
+        sort_locations(&mut global);
+        global
+    }
+
+    /// Name at `location` and its definitions, itself if it is one.
+    fn definitions_at(&self, location: &Location) -> Option<(&str, Vec<Location>)> {
+        let position = location.range.start;
+        let file = self.files.get(&location.uri)?;
+
+        if let Some(definition) = file
+            .definitions
+            .iter()
+            .find(|d| range_contains(&d.range, position))
+        {
+            let definition_location = Location::new(location.uri.clone(), definition.range);
+            return Some((&definition.name, vec![definition_location]));
+        }
+
+        let reference = file
+            .references
+            .iter()
+            .find(|r| range_contains(&r.range, position))?;
+
+        Some((
+            &reference.name,
+            self.resolve(&location.uri, &reference.name, position),
+        ))
+    }
+}
+
+#[async_trait]
+impl LspProvider for ScopeResolver {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        Ok(self
+            .definitions_at(location)
+            .map(|(_, definitions)| definitions)
+            .unwrap_or_default())
+    }
+
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        let Some((name, definitions)) = self.definitions_at(location) else {
+            return Ok(vec![]);
+        };
+
+        let mut references = vec![];
+        for (uri, file) in &self.files {
+            for reference in file.references.iter().filter(|r| r.name == name) {
+                let resolved = self.resolve(uri, name, reference.range.start);
+                if resolved.iter().any(|d| definitions.contains(d)) {
+                    references.push(Location::new(uri.clone(), reference.range));
+                }
+            }
+        }
+        sort_locations(&mut references);
+
+        Ok(references)
+    }
+}
+
+#[cfg(all(test, feature = "python", feature = "go"))]
+mod tests {
+    use super::*;
+    use crate::languages::{go::Go, python::Python};
+
+    fn setup(files: &[(&str, &str)]) -> (tempfile::TempDir, Vec<PathBuf>) {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = files
+            .iter()
+            .map(|(name, text)| {
+                let path = dir.path().join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, text).unwrap();
+                path
+            })
+            .collect();
+
+        (dir, paths)
+    }
+
+    fn location(path: &Path, line: u32, character: u32) -> Location {
+        let position = Position::new(line, character);
+        Location::new(
+            Url::from_file_path(path).unwrap(),
+            Range::new(position, position),
+        )
+    }
+
+    fn display(locations: Vec<Location>) -> Vec<String> {
+        locations
+            .iter()
+            .map(|l| {
+                let path = l.uri.to_file_path().unwrap();
+                format!(
+                    "{}:{}:{}",
+                    path.file_name().unwrap().to_string_lossy(),
+                    l.range.start.line,
+                    l.range.start.character
+                )
+            })
+            .collect()
+    }
+
+    const DB: &str = r#"
+def run_query(q):
+    return q
+"#;
+
+    const APP: &str = r#"
+from .db import run_query
+
+def handle(request):
+    name = request.args
+    name = name.strip()
+    return run_query(name)
+
+def other(name):
+    return name
+"#;
+
+    #[tokio::test]
+    async fn test_definitions() {
+        let (_dir, files) = setup(&[("pkg/db.py", DB), ("pkg/app.py", APP)]);
+        let resolver = ScopeResolver::new(&Python, &files).unwrap();
+        let app = &files[1];
+
+        let definitions = |line, character| {
+            let resolver = &resolver;
+            async move {
+                display(
+                    resolver
+                        .find_definitions(&location(app, line, character))
+                        .await
+                        .unwrap(),
+                )
+            }
+        };
+
+        // imported function
+        assert_eq!(definitions(6, 11).await, vec!["db.py:1:4"]);
+        // last assignment before the reference
+        assert_eq!(definitions(6, 21).await, vec!["app.py:5:4"]);
+        // parameter of the enclosing function only
+        assert_eq!(definitions(9, 11).await, vec!["app.py:8:10"]);
+        // a definition is its own definition
+        assert_eq!(definitions(3, 4).await, vec!["app.py:3:4"]);
+        // not a name
+        assert!(definitions(6, 4).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_references() {
+        let (_dir, files) = setup(&[("pkg/db.py", DB), ("pkg/app.py", APP)]);
+        let resolver = ScopeResolver::new(&Python, &files).unwrap();
+
+        let references = resolver
+            .find_references(&location(&files[0], 1, 4))
+            .await
+            .unwrap();
+        assert_eq!(display(references), vec!["app.py:1:16", "app.py:6:11"]);
+
+        let references = resolver
+            .find_references(&location(&files[1], 3, 11))
+            .await
+            .unwrap();
+        assert_eq!(display(references), vec!["app.py:4:11"]);
+    }
+
+    #[tokio::test]
+    async fn test_package_across_files() {
+        let (_dir, files) = setup(&[
+            (
+                "main.go",
+                "package main\n\nfunc main() {\n\ts := &Source{}\n\thelper(s.Query())\n}\n",
+            ),
+            (
+                "util.go",
+                "package main\n\nfunc helper(q string) {}\n\nfunc (s *Source) Query() string {\n\treturn \"\"\n}\n",
+            ),
+        ]);
+        let resolver = ScopeResolver::new(&Go, &files).unwrap();
+
+        let definitions = resolver
+            .find_definitions(&location(&files[0], 4, 1))
+            .await
+            .unwrap();
+        assert_eq!(display(definitions), vec!["util.go:2:5"]);
+
+        let definitions = resolver
+            .find_definitions(&location(&files[0], 4, 10))
+            .await
+            .unwrap();
+        assert_eq!(display(definitions), vec!["util.go:4:17"]);
+
+        let definitions = resolver
+            .find_definitions(&location(&files[0], 4, 8))
+            .await
+            .unwrap();
+        assert_eq!(display(definitions), vec!["main.go:3:1"]);
+    }
+
+    #[test]
+    fn test_resolve_import() {
+        let files = [
+            "/p/app/main.py",
+            "/p/app/db.py",
+            "/p/lib/util/mod.rs",
+            "/p/store/a.go",
+        ]
+        .map(PathBuf::from);
+
+        let resolve = |file: &str, import: &str| {
+            resolve_import(Path::new(file), import, &files)
+                .iter()
+                .map(|f| f.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(resolve("/p/app/main.py", "./db"), vec!["/p/app/db.py"]);
+        assert_eq!(
+            resolve("/p/app/main.py", "../lib/util"),
+            vec!["/p/lib/util/mod.rs"]
+        );
+        assert_eq!(
+            resolve("/p/app/main.py", "example.com/p/store"),
+            vec!["/p/store/a.go"]
+        );
+        assert!(resolve("/p/app/main.py", "./missing").is_empty());
+    }
+}
@@ -0,0 +1,706 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use lsp_types::{Location, Position, Range, Url};
+use serde_json::Value;
+use tokio::process::Command;
+
+use crate::{
+    converter::{Convert, Converter},
+    language_provider::LspProvider,
+    languages::solidity_project::SolidityProject,
+    utils::LocationKey,
+};
+
+/// A named declaration of the compiled project, e.g. a contract, function
+/// or variable.
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub id: i64,
+    pub name: String,
+    /// AST node type, e.g. `FunctionDefinition` or `VariableDeclaration`.
+    pub kind: String,
+    /// Location of the declaration's name.
+    pub location: Location,
+    /// Type of a variable, e.g. `address payable` or
+    /// `mapping(address => uint256)`.
+    pub type_string: Option<String>,
+    pub visibility: Option<String>,
+    /// Contracts a contract inherits from, itself first and the most
+    /// basic contract last.
+    pub linearized_base_contracts: Vec<i64>,
+    /// Functions and modifiers a function or modifier overrides.
+    pub base_functions: Vec<i64>,
+}
+
+struct Reference {
+    location: Location,
+    declaration: i64,
+    /// Whether the reference is called, e.g. `f` in `f()`, a modifier
+    /// invocation, or a contract created with `new`.
+    call: bool,
+}
+
+/// Text of a source unit, to convert `src` byte offsets to positions.
+struct SourceText {
+    uri: Url,
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceText {
+    fn new(uri: Url, text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            uri,
+            text,
+            line_starts,
+        }
+    }
+
+    /// Start of the first whole word `name` in `length` bytes from `start`.
+    fn find_name(&self, start: usize, length: usize, name: &str) -> Option<usize> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+        let text = self.text.get(start..start + length)?;
+
+        text.match_indices(name)
+            .map(|(i, _)| i)
+            .find(|i| {
+                !text[..*i].ends_with(is_word) && !text[i + name.len()..].starts_with(is_word)
+            })
+            .map(|i| start + i)
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        Position::new(line as u32, (offset - self.line_starts[line]) as u32)
+    }
+
+    fn location(&self, start: usize, length: usize) -> Location {
+        Location::new(
+            self.uri.clone(),
+            Range::new(self.position(start), self.position(start + length)),
+        )
+    }
+}
+
+/// Start and length of a `start:length:source` location, `None` for the
+/// `-1:-1:-1` of nodes without one.
+fn parse_src(src: &str) -> Option<(usize, usize)> {
+    let mut parts = src.split(':');
+    let start = parts.next()?.parse().ok()?;
+    let length = parts.next()?.parse().ok()?;
+
+    Some((start, length))
+}
+
+fn ids(value: &Value) -> Vec<i64> {
+    value
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_i64).collect())
+        .unwrap_or_default()
+}
+
+/// Whether the `key` child of a `node_type` node is called, if the node
+/// itself is called for the types that only wrap the callee.
+fn is_callee(node_type: &str, key: &str, called: bool) -> bool {
+    matches!(
+        (node_type, key),
+        ("FunctionCall", "expression")
+            | ("FunctionCallOptions", "expression")
+            | ("ModifierInvocation", "modifierName")
+            | ("NewExpression", "typeName")
+    ) || (called && node_type == "UserDefinedTypeName" && key == "pathNode")
+}
+
+/// Declarations and references of a Solidity project, from the compact
+/// JSON AST of `solc --combined-json ast`.
+///
+/// The project is compiled once, so unlike [`SolidityLs`] no requests are
+/// made while scanning, and names are resolved by `referencedDeclaration`
+/// ids instead of positions. It also has the types, visibility and
+/// inheritance of declarations, see [`SolcAst::declaration_at`].
+///
+/// Projects that don't compile have no AST, use [`SolidityLs`] for them.
+///
+/// [`SolidityLs`]: super::solidity::SolidityLs
+#[derive(Default)]
+pub struct SolcAst {
+    declarations: HashMap<i64, Declaration>,
+    references: Vec<Reference>,
+}
+
+impl SolcAst {
+    /// Compile `project_files` with the `solc` on the path, see
+    /// [`SolcAst::with_solc`].
+    pub async fn new(root_dir: &Path, project_files: &[PathBuf]) -> Result<Self> {
+        Self::with_solc(Path::new("solc"), root_dir, project_files).await
+    }
+
+    /// Compile `project_files` with the `solc` binary, resolving imports by
+    /// the [`SolidityProject`] at `root_dir`.
+    pub async fn with_solc(
+        solc: &Path,
+        root_dir: &Path,
+        project_files: &[PathBuf],
+    ) -> Result<Self> {
+        let project = SolidityProject::detect(root_dir)?;
+        let output = Command::new(solc)
+            .current_dir(root_dir)
+            .args(["--combined-json", "ast"])
+            .args(project.solc_args())
+            .args(
+                project_files
+                    .iter()
+                    .map(|f| f.strip_prefix(root_dir).unwrap_or(f)),
+            )
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", solc.display()))?;
+
+        if !output.status.success() {
+            bail!(
+                "failed to compile project with solc: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let output =
+            serde_json::from_slice(&output.stdout).context("failed to parse solc output")?;
+
+        Self::from_combined_json(&project, &output)
+    }
+
+    /// Index the ASTs of `solc --combined-json ast` output of `project`.
+    pub fn from_combined_json(project: &SolidityProject, output: &Value) -> Result<Self> {
+        let sources = output
+            .get("sources")
+            .and_then(Value::as_object)
+            .context("solc output has no sources")?;
+
+        let mut ast = Self::default();
+        for (name, source) in sources {
+            let unit = source
+                .get("AST")
+                .with_context(|| format!("solc output has no AST for {}", name))?;
+
+            let path = project.resolve(
+                unit.get("absolutePath")
+                    .and_then(Value::as_str)
+                    .unwrap_or(name),
+            );
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read source {}", path.display()))?;
+
+            let source = SourceText::new(Converter::convert(path.as_path()), text);
+            ast.visit(&source, unit, false);
+        }
+
+        ast.references.sort_by(|a, b| {
+            (a.location.uri.as_str(), a.location.range.start)
+                .cmp(&(b.location.uri.as_str(), b.location.range.start))
+        });
+
+        Ok(ast)
+    }
+
+    fn visit(&mut self, source: &SourceText, node: &Value, called: bool) {
+        match node {
+            Value::Array(nodes) => {
+                for node in nodes {
+                    self.visit(source, node, false);
+                }
+            }
+            Value::Object(fields) => {
+                let node_type = fields
+                    .get("nodeType")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+
+                self.add_declaration(source, node_type, node);
+                self.add_reference(source, node_type, node, called);
+
+                for (key, child) in fields {
+                    self.visit(source, child, is_callee(node_type, key, called));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_declaration(&mut self, source: &SourceText, node_type: &str, node: &Value) {
+        if !(node_type.ends_with("Definition") || node_type == "VariableDeclaration") {
+            return;
+        }
+
+        let (Some(id), Some(name)) = (
+            node.get("id").and_then(Value::as_i64),
+            node.get("name").and_then(Value::as_str),
+        ) else {
+            return;
+        };
+
+        // constructors, fallback functions and unnamed parameters
+        if name.is_empty() {
+            return;
+        }
+
+        // compilers before 0.8.2 have no `nameLocation`, only the `src` of
+        // the whole declaration
+        let name_location = node
+            .get("nameLocation")
+            .and_then(Value::as_str)
+            .and_then(parse_src);
+        let Some((start, length)) = name_location.or_else(|| {
+            let (start, length) = parse_src(node.get("src")?.as_str()?)?;
+            Some((source.find_name(start, length, name)?, name.len()))
+        }) else {
+            return;
+        };
+
+        let text = |key: &str| node.get(key).and_then(Value::as_str).map(str::to_string);
+        self.declarations.insert(
+            id,
+            Declaration {
+                id,
+                name: name.to_string(),
+                kind: node_type.to_string(),
+                location: source.location(start, length),
+                type_string: node
+                    .pointer("/typeDescriptions/typeString")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                visibility: text("visibility"),
+                linearized_base_contracts: ids(&node["linearizedBaseContracts"]),
+                base_functions: [&node["baseFunctions"], &node["baseModifiers"]]
+                    .into_iter()
+                    .flat_map(ids)
+                    .collect(),
+            },
+        );
+    }
+
+    fn add_reference(&mut self, source: &SourceText, node_type: &str, node: &Value, call: bool) {
+        // builtins, e.g. `require`, reference negative ids
+        let Some(declaration) = node
+            .get("referencedDeclaration")
+            .and_then(Value::as_i64)
+            .filter(|id| *id >= 0)
+        else {
+            return;
+        };
+
+        // referenced again by its `pathNode`
+        if node_type == "UserDefinedTypeName" && node.get("pathNode").is_some() {
+            return;
+        }
+
+        let Some((start, length)) = node.get("src").and_then(Value::as_str).and_then(parse_src)
+        else {
+            return;
+        };
+
+        // only the last name of `a.f` and `A.B`, which the ast has no
+        // location of in older compilers
+        let name = ["memberName", "name"]
+            .iter()
+            .find_map(|key| node.get(*key).and_then(Value::as_str))
+            .and_then(|name| name.rsplit('.').next())
+            .unwrap_or_default();
+
+        let (start, length) = match node.get("memberLocation").and_then(Value::as_str) {
+            Some(location) => parse_src(location).unwrap_or((start, length)),
+            None if !name.is_empty() && name.len() <= length => {
+                (start + length - name.len(), name.len())
+            }
+            None => (start, length),
+        };
+
+        self.references.push(Reference {
+            location: source.location(start, length),
+            declaration,
+            call,
+        });
+    }
+
+    pub fn declaration(&self, id: i64) -> Option<&Declaration> {
+        self.declarations.get(&id)
+    }
+
+    /// Declaration named or referenced at `location`.
+    pub fn declaration_at(&self, location: &Location) -> Option<&Declaration> {
+        let contains = |outer: &Location| {
+            outer.uri == location.uri
+                && outer.range.start <= location.range.start
+                && location.range.start < outer.range.end
+        };
+
+        self.declarations
+            .values()
+            .find(|d| contains(&d.location))
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .filter(|r| contains(&r.location))
+                    .max_by_key(|r| r.location.range.start)
+                    .and_then(|r| self.declaration(r.declaration))
+            })
+    }
+
+    /// All references to the declaration `id`, sorted by path and position.
+    pub fn references(&self, id: i64) -> impl Iterator<Item = &Location> {
+        self.references
+            .iter()
+            .filter(move |r| r.declaration == id)
+            .map(|r| &r.location)
+    }
+
+    /// Contracts `contract` inherits from, most derived first.
+    pub fn base_contracts(&self, contract: &Declaration) -> Vec<&Declaration> {
+        contract
+            .linearized_base_contracts
+            .iter()
+            .filter(|id| **id != contract.id)
+            .filter_map(|id| self.declaration(*id))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LspProvider for SolcAst {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        Ok(self
+            .declaration_at(location)
+            .map(|d| vec![d.location.clone()])
+            .unwrap_or_default())
+    }
+
+    /// Call sites of the declaration at `location`, like the references of
+    /// [`SolidityLs`](super::solidity::SolidityLs).
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        let Some(declaration) = self.declaration_at(location) else {
+            return Ok(vec![]);
+        };
+
+        let key = LocationKey::from(&declaration.location);
+        Ok(self
+            .references
+            .iter()
+            .filter(|r| r.call && r.declaration == declaration.id)
+            .filter(|r| LocationKey::from(&r.location) != key)
+            .map(|r| r.location.clone())
+            .collect())
+    }
+
+    /// Functions overriding the function at `location`.
+    async fn find_implementations(&self, location: &Location) -> Result<Vec<Location>> {
+        let Some(declaration) = self.declaration_at(location) else {
+            return Ok(vec![]);
+        };
+
+        let mut implementations = self
+            .declarations
+            .values()
+            .filter(|d| d.base_functions.contains(&declaration.id))
+            .map(|d| d.location.clone())
+            .collect::<Vec<_>>();
+        implementations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+
+        Ok(implementations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const VAULT: &str = r#"contract Base {
+    function withdraw(address payable to) public virtual {
+        to.transfer(1);
+    }
+}
+
+contract Vault is Base {
+    address payable owner;
+
+    function withdraw(address payable to) public override {
+        super.withdraw(to);
+        Base.withdraw(owner);
+    }
+}
+"#;
+
+    /// `src` of the `nth` occurrence of `needle` in [`VAULT`].
+    fn src(needle: &str, nth: usize) -> String {
+        let (start, _) = VAULT.match_indices(needle).nth(nth).unwrap();
+        format!("{}:{}:0", start, needle.len())
+    }
+
+    fn identifier(name: &str, nth: usize, declaration: i64) -> Value {
+        json!({
+            "nodeType": "Identifier",
+            "name": name,
+            "src": src(name, nth),
+            "referencedDeclaration": declaration,
+        })
+    }
+
+    fn call(callee: &str, nth: usize, expression: Value, arguments: Vec<Value>) -> Value {
+        json!({
+            "nodeType": "ExpressionStatement",
+            "src": src(callee, nth),
+            "expression": {
+                "nodeType": "FunctionCall",
+                "src": src(callee, nth),
+                "expression": expression,
+                "arguments": arguments,
+            },
+        })
+    }
+
+    fn parameter(id: i64, nth: usize, name_location: Option<String>) -> Value {
+        let mut parameter = json!({
+            "nodeType": "VariableDeclaration",
+            "id": id,
+            "name": "to",
+            "src": src("address payable to", nth),
+            "typeDescriptions": { "typeString": "address payable" },
+            "visibility": "internal",
+        });
+        if let Some(name_location) = name_location {
+            parameter["nameLocation"] = name_location.into();
+        }
+
+        parameter
+    }
+
+    /// Output of `solc --combined-json ast Vault.sol`, with only the nodes
+    /// the index reads. The parameter of `Vault.withdraw` has no
+    /// `nameLocation`, like in compilers before 0.8.2.
+    fn combined_json() -> Value {
+        let base_withdraw = json!({
+            "nodeType": "FunctionDefinition",
+            "id": 3,
+            "name": "withdraw",
+            "nameLocation": src("withdraw", 0),
+            "src": src("function withdraw", 0),
+            "visibility": "public",
+            "parameters": { "nodeType": "ParameterList", "parameters": [parameter(4, 0, Some(src("to", 0)))] },
+            "body": {
+                "nodeType": "Block",
+                "statements": [call(
+                    "to.transfer(1)",
+                    0,
+                    json!({
+                        "nodeType": "MemberAccess",
+                        "memberName": "transfer",
+                        "src": src("to.transfer", 0),
+                        "expression": identifier("to", 1, 4),
+                    }),
+                    vec![],
+                )],
+            },
+        });
+
+        let vault_withdraw = json!({
+            "nodeType": "FunctionDefinition",
+            "id": 7,
+            "name": "withdraw",
+            "nameLocation": src("withdraw", 1),
+            "src": src("function withdraw", 1),
+            "visibility": "public",
+            "baseFunctions": [3],
+            "parameters": { "nodeType": "ParameterList", "parameters": [parameter(8, 1, None)] },
+            "body": {
+                "nodeType": "Block",
+                "statements": [
+                    call(
+                        "super.withdraw(to)",
+                        0,
+                        json!({
+                            "nodeType": "MemberAccess",
+                            "memberName": "withdraw",
+                            "src": src("super.withdraw", 0),
+                            "referencedDeclaration": 3,
+                            "expression": identifier("super", 0, -25),
+                        }),
+                        vec![identifier("to", 3, 8)],
+                    ),
+                    call(
+                        "Base.withdraw(owner)",
+                        0,
+                        json!({
+                            "nodeType": "MemberAccess",
+                            "memberName": "withdraw",
+                            "memberLocation": src("withdraw", 3),
+                            "src": src("Base.withdraw", 0),
+                            "referencedDeclaration": 3,
+                            "expression": identifier("Base", 2, 2),
+                        }),
+                        vec![identifier("owner", 1, 6)],
+                    ),
+                ],
+            },
+        });
+
+        json!({
+            "sources": {
+                "Vault.sol": {
+                    "AST": {
+                        "nodeType": "SourceUnit",
+                        "id": 1,
+                        "absolutePath": "Vault.sol",
+                        "src": format!("0:{}:0", VAULT.len()),
+                        "nodes": [
+                            {
+                                "nodeType": "ContractDefinition",
+                                "id": 2,
+                                "name": "Base",
+                                "nameLocation": src("Base", 0),
+                                "src": src("contract Base", 0),
+                                "linearizedBaseContracts": [2],
+                                "nodes": [base_withdraw],
+                            },
+                            {
+                                "nodeType": "ContractDefinition",
+                                "id": 5,
+                                "name": "Vault",
+                                "nameLocation": src("Vault", 0),
+                                "src": src("contract Vault", 0),
+                                "linearizedBaseContracts": [5, 2],
+                                "baseContracts": [{
+                                    "nodeType": "InheritanceSpecifier",
+                                    "baseName": {
+                                        "nodeType": "IdentifierPath",
+                                        "name": "Base",
+                                        "src": src("Base", 1),
+                                        "referencedDeclaration": 2,
+                                    },
+                                }],
+                                "nodes": [
+                                    {
+                                        "nodeType": "VariableDeclaration",
+                                        "id": 6,
+                                        "name": "owner",
+                                        "nameLocation": src("owner", 0),
+                                        "src": src("address payable owner", 0),
+                                        "typeDescriptions": { "typeString": "address payable" },
+                                        "visibility": "internal",
+                                    },
+                                    vault_withdraw,
+                                ],
+                            },
+                        ],
+                    },
+                },
+            },
+        })
+    }
+
+    fn setup() -> (tempfile::TempDir, PathBuf, SolcAst) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Vault.sol");
+        std::fs::write(&path, VAULT).unwrap();
+
+        let project = SolidityProject::detect(dir.path()).unwrap();
+        let ast = SolcAst::from_combined_json(&project, &combined_json()).unwrap();
+
+        (dir, path, ast)
+    }
+
+    fn location(path: &Path, line: u32, character: u32) -> Location {
+        let position = Position::new(line, character);
+        Location::new(
+            Url::from_file_path(path).unwrap(),
+            Range::new(position, position),
+        )
+    }
+
+    fn display(locations: Vec<Location>) -> Vec<String> {
+        locations
+            .iter()
+            .map(|l| {
+                format!(
+                    "{}:{}-{}:{}",
+                    l.range.start.line,
+                    l.range.start.character,
+                    l.range.end.line,
+                    l.range.end.character
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_definitions() {
+        let (_dir, path, ast) = setup();
+
+        let definitions = |line, character| {
+            let ast = &ast;
+            let location = location(&path, line, character);
+            async move { display(ast.find_definitions(&location).await.unwrap()) }
+        };
+
+        // `super.withdraw`
+        assert_eq!(definitions(10, 14).await, vec!["1:13-1:21"]);
+        // `Base.withdraw`, by its member location
+        assert_eq!(definitions(11, 13).await, vec!["1:13-1:21"]);
+        // `Base` of `Base.withdraw`
+        assert_eq!(definitions(11, 8).await, vec!["0:9-0:13"]);
+        // parameter without a name location
+        assert_eq!(definitions(10, 23).await, vec!["9:38-9:40"]);
+        // a declaration is its own definition
+        assert_eq!(definitions(7, 20).await, vec!["7:20-7:25"]);
+        // builtin
+        assert!(definitions(10, 8).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_references() {
+        let (_dir, path, ast) = setup();
+
+        // call sites only, not the inheritance specifier or `Base.withdraw`
+        let references = ast.find_references(&location(&path, 1, 13)).await.unwrap();
+        assert_eq!(display(references), vec!["10:14-10:22", "11:13-11:21"]);
+        let references = ast.find_references(&location(&path, 0, 9)).await.unwrap();
+        assert!(references.is_empty());
+
+        let references = ast.references(2).cloned().collect();
+        assert_eq!(display(references), vec!["6:18-6:22", "11:8-11:12"]);
+
+        let implementations = ast
+            .find_implementations(&location(&path, 1, 13))
+            .await
+            .unwrap();
+        assert_eq!(display(implementations), vec!["9:13-9:21"]);
+    }
+
+    #[test]
+    fn test_declarations() {
+        let (_dir, path, ast) = setup();
+
+        let owner = ast.declaration_at(&location(&path, 11, 22)).unwrap();
+        assert_eq!(owner.name, "owner");
+        assert_eq!(owner.kind, "VariableDeclaration");
+        assert_eq!(owner.type_string.as_deref(), Some("address payable"));
+        assert_eq!(owner.visibility.as_deref(), Some("internal"));
+
+        let vault = ast.declaration_at(&location(&path, 6, 9)).unwrap();
+        let bases = ast
+            .base_contracts(vault)
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(bases, vec!["Base"]);
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SolcVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl SolcVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for SolcVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for SolcVersion {
+    type Err = anyhow::Error;
+
+    /// `0.8.19`, also with a `v` prefix or a `+commit...` suffix.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let s = s.split_once('+').map_or(s, |(version, _)| version);
+
+        let parts = s
+            .split('.')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("invalid solc version: {}", s))?;
+        let [major, minor, patch] = parts[..] else {
+            bail!("invalid solc version: {}", s);
+        };
+
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Versions a `pragma solidity` allows, any of a set of ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    ranges: Vec<Vec<(Op, SolcVersion)>>,
+}
+
+/// Components of a possibly partial version, e.g. `0.8` or `0.8.x`.
+fn parse_partial(s: &str) -> Result<Vec<u64>> {
+    s.split('.')
+        .take_while(|part| !matches!(*part, "x" | "X" | "*"))
+        .map(|part| {
+            part.parse()
+                .with_context(|| format!("invalid version in pragma: {}", s))
+        })
+        .collect()
+}
+
+/// The version `parts` starts, and the first version after the ones it
+/// matches, e.g. `0.8` matches 0.8.0 up to 0.9.0.
+fn partial_bounds(parts: &[u64]) -> (SolcVersion, Option<SolcVersion>) {
+    let part = |i: usize| parts.get(i).copied().unwrap_or(0);
+    let lower = SolcVersion::new(part(0), part(1), part(2));
+    let upper = match parts.len() {
+        0 => None,
+        1 => Some(SolcVersion::new(part(0) + 1, 0, 0)),
+        2 => Some(SolcVersion::new(part(0), part(1) + 1, 0)),
+        _ => Some(SolcVersion::new(part(0), part(1), part(2) + 1)),
+    };
+
+    (lower, upper)
+}
+
+fn parse_comparator(comparator: &str, range: &mut Vec<(Op, SolcVersion)>) -> Result<()> {
+    let (op, version) = match comparator {
+        c if c.starts_with(">=") => (">=", &c[2..]),
+        c if c.starts_with("<=") => ("<=", &c[2..]),
+        c if c.starts_with('>') => (">", &c[1..]),
+        c if c.starts_with('<') => ("<", &c[1..]),
+        c if c.starts_with('=') => ("=", &c[1..]),
+        c if c.starts_with('^') => ("^", &c[1..]),
+        c if c.starts_with('~') => ("~", &c[1..]),
+        c => ("=", c),
+    };
+
+    let parts = parse_partial(version.trim())?;
+    let (lower, upper) = partial_bounds(&parts);
+    match op {
+        ">=" => range.push((Op::Ge, lower)),
+        ">" => match upper {
+            Some(upper) => range.push((Op::Ge, upper)),
+            None => range.push((Op::Gt, lower)),
+        },
+        "<" => range.push((Op::Lt, lower)),
+        "<=" => match upper {
+            Some(upper) => range.push((Op::Lt, upper)),
+            None => range.push((Op::Le, lower)),
+        },
+        "=" if parts.len() == 3 => range.push((Op::Eq, lower)),
+        "=" => {
+            range.push((Op::Ge, lower));
+            range.extend(upper.map(|upper| (Op::Lt, upper)));
+        }
+        "^" => {
+            // the first nonzero component can't change
+            let upper = match lower {
+                SolcVersion {
+                    major: 0, minor: 0, ..
+                } if parts.len() == 3 => SolcVersion::new(0, 0, lower.patch + 1),
+                SolcVersion { major: 0, .. } if parts.len() >= 2 => {
+                    SolcVersion::new(0, lower.minor + 1, 0)
+                }
+                _ => SolcVersion::new(lower.major + 1, 0, 0),
+            };
+            range.push((Op::Ge, lower));
+            range.push((Op::Lt, upper));
+        }
+        "~" => {
+            let upper = match parts.len() {
+                0 | 1 => SolcVersion::new(lower.major + 1, 0, 0),
+                _ => SolcVersion::new(lower.major, lower.minor + 1, 0),
+            };
+            range.push((Op::Ge, lower));
+            range.push((Op::Lt, upper));
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+impl FromStr for VersionReq {
+    type Err = anyhow::Error;
+
+    /// Constraints of a pragma, e.g. `^0.8.0`, `>=0.6.0 <0.9.0` or
+    /// `0.4.24 || ^0.5.0`.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ranges = vec![];
+        for alternative in s.split("||") {
+            let mut range = vec![];
+            let tokens = alternative
+                .replace(">= ", ">=")
+                .replace("<= ", "<=")
+                .replace("> ", ">")
+                .replace("< ", "<")
+                .replace("^ ", "^")
+                .replace("~ ", "~")
+                .replace("= ", "=");
+            let tokens = tokens.split_whitespace().collect::<Vec<_>>();
+
+            match tokens[..] {
+                // `0.4.0 - 0.5.0`, both inclusive
+                [from, "-", to] => {
+                    parse_comparator(&format!(">={}", from), &mut range)?;
+                    parse_comparator(&format!("<={}", to), &mut range)?;
+                }
+                _ => {
+                    for comparator in tokens {
+                        parse_comparator(comparator, &mut range)?;
+                    }
+                }
+            }
+
+            ranges.push(range);
+        }
+
+        Ok(Self { ranges })
+    }
+}
+
+impl VersionReq {
+    pub fn matches(&self, version: SolcVersion) -> bool {
+        self.ranges.iter().any(|range| {
+            range.iter().all(|(op, bound)| match op {
+                Op::Eq => version == *bound,
+                Op::Lt => version < *bound,
+                Op::Le => version <= *bound,
+                Op::Gt => version > *bound,
+                Op::Ge => version >= *bound,
+            })
+        })
+    }
+}
+
+/// Requirement of the `pragma solidity` directives of a source file, all
+/// of which have to match.
+pub fn pragma_requirement(text: &str) -> Result<Option<Vec<VersionReq>>> {
+    let mut requirements = vec![];
+    for line in text.lines() {
+        let line = line.split_once("//").map_or(line, |(code, _)| code).trim();
+        let Some(pragma) = line.strip_prefix("pragma solidity") else {
+            continue;
+        };
+
+        let pragma = pragma.split_once(';').map_or(pragma, |(pragma, _)| pragma);
+        requirements.push(pragma.trim().parse()?);
+    }
+
+    Ok(match requirements.is_empty() {
+        true => None,
+        false => Some(requirements),
+    })
+}
+
+/// A solc binary of a version manager's directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolcInstall {
+    pub version: SolcVersion,
+    pub path: PathBuf,
+}
+
+/// Directories svm (`~/.svm/0.8.19/solc-0.8.19`) and solc-select
+/// (`~/.solc-select/artifacts/solc-0.8.19/solc-0.8.19`) install solc to.
+pub fn default_solc_dirs() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+        return vec![];
+    };
+
+    vec![
+        home.join(".svm"),
+        home.join(".local/share/svm"),
+        home.join(".solc-select/artifacts"),
+    ]
+}
+
+/// The binaries named `solc-<version>` in `dirs` or their subdirectories,
+/// newest first.
+pub fn find_installs(dirs: &[PathBuf]) -> Vec<SolcInstall> {
+    fn visit(dir: &Path, depth: usize, installs: &mut Vec<SolcInstall>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth > 0 {
+                    visit(&path, depth - 1, installs);
+                }
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let version = name
+                .strip_prefix("solc-")
+                .and_then(|version| version.strip_suffix(".exe").or(Some(version)))
+                .and_then(|version| version.parse().ok());
+            if let Some(version) = version {
+                installs.push(SolcInstall { version, path });
+            }
+        }
+    }
+
+    let mut installs = vec![];
+    for dir in dirs {
+        visit(dir, 1, &mut installs);
+    }
+
+    installs.sort_by(|a, b| b.version.cmp(&a.version).then(a.path.cmp(&b.path)));
+    installs.dedup_by_key(|install| install.version);
+
+    installs
+}
+
+/// `project_files` grouped by the newest of `installs` their pragmas
+/// allow, files without a pragma or an installed version matching it by
+/// the `solc` on the path.
+///
+/// Files are grouped by their own pragmas, so a group's imports may still
+/// need a different version.
+pub fn group_by_compiler(
+    project_files: &[PathBuf],
+    installs: &[SolcInstall],
+) -> BTreeMap<PathBuf, Vec<PathBuf>> {
+    let mut groups = BTreeMap::<PathBuf, Vec<PathBuf>>::new();
+    for file in project_files {
+        let requirement = std::fs::read_to_string(file)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| pragma_requirement(&text));
+
+        let solc = match requirement {
+            Ok(Some(requirements)) => {
+                let install = installs
+                    .iter()
+                    .find(|install| requirements.iter().all(|r| r.matches(install.version)));
+                // without any installs, every file is compiled by the path's solc anyway
+                if install.is_none() && !installs.is_empty() {
+                    eprintln!(
+                        "no installed solc matches the pragma of {}, compiling it with solc",
+                        file.display()
+                    );
+                }
+                install.map(|install| install.path.clone())
+            }
+            Ok(None) => None,
+            Err(e) => {
+                eprintln!("{:#} in {}", e, file.display());
+                None
+            }
+        };
+
+        groups
+            .entry(solc.unwrap_or_else(|| PathBuf::from("solc")))
+            .or_default()
+            .push(file.clone());
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(req: &str, version: &str) -> bool {
+        req.parse::<VersionReq>()
+            .unwrap()
+            .matches(version.parse().unwrap())
+    }
+
+    #[test]
+    fn test_version_req() {
+        assert!(matches("^0.8.0", "0.8.19"));
+        assert!(!matches("^0.8.0", "0.9.0"));
+        assert!(!matches("^0.8.4", "0.8.3"));
+        assert!(matches("0.6.12", "0.6.12"));
+        assert!(!matches("=0.6.12", "0.6.11"));
+        assert!(matches(">=0.6.0 <0.9.0", "0.8.19"));
+        assert!(matches(">= 0.6.0 < 0.9.0", "0.6.0"));
+        assert!(!matches(">=0.6.0 <0.9.0", "0.9.0"));
+        assert!(matches("~0.5.2", "0.5.17"));
+        assert!(!matches("~0.5.2", "0.6.0"));
+        assert!(matches("0.4.24 || ^0.5.0", "0.5.3"));
+        assert!(!matches("0.4.24 || ^0.5.0", "0.4.25"));
+        assert!(matches("0.8", "0.8.7"));
+        assert!(matches(">0.7", "0.8.0"));
+        assert!(!matches(">0.7", "0.7.6"));
+        assert!(matches("<=0.7", "0.7.6"));
+        assert!(matches("0.4.0 - 0.5.0", "0.5.0"));
+        assert!(!matches("0.4.0 - 0.5.0", "0.5.1"));
+
+        assert!("^a".parse::<VersionReq>().is_err());
+    }
+
+    #[test]
+    fn test_pragma_requirement() {
+        let text = "// SPDX-License-Identifier: MIT\n// pragma solidity 0.4.0;\npragma solidity ^0.8.0;\npragma solidity <0.8.20; // no push0\n";
+        let requirements = pragma_requirement(text).unwrap().unwrap();
+        assert_eq!(requirements.len(), 2);
+
+        assert!(pragma_requirement("contract A {}").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_group_by_compiler() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, text: &str| {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text).unwrap();
+            path
+        };
+
+        // svm and solc-select layouts
+        let svm = dir.path().join("svm");
+        let solc_select = dir.path().join("solc-select");
+        let solc_0_6_12 = write("svm/0.6.12/solc-0.6.12", "");
+        let solc_0_8_19 = write("svm/0.8.19/solc-0.8.19", "");
+        write("solc-select/solc-0.8.7/solc-0.8.7", "");
+        write("svm/.global-version", "0.8.19");
+
+        let installs = find_installs(&[svm, solc_select]);
+        assert_eq!(
+            installs
+                .iter()
+                .map(|install| install.version.to_string())
+                .collect::<Vec<_>>(),
+            vec!["0.8.19", "0.8.7", "0.6.12"]
+        );
+
+        let old = write("src/Old.sol", "pragma solidity 0.6.12;\n");
+        let new = write("src/New.sol", "pragma solidity ^0.8.0;\n");
+        let capped = write("src/Capped.sol", "pragma solidity >=0.8.0 <0.8.10;\n");
+        let none = write("src/None.sol", "contract A {}\n");
+        let future = write("src/Future.sol", "pragma solidity ^0.9.0;\n");
+
+        let groups = group_by_compiler(
+            &[
+                old.clone(),
+                new.clone(),
+                capped.clone(),
+                none.clone(),
+                future.clone(),
+            ],
+            &installs,
+        );
+
+        let solc_0_8_7 = installs[1].path.clone();
+        assert_eq!(
+            groups,
+            BTreeMap::from([
+                (solc_0_6_12, vec![old]),
+                (solc_0_8_19, vec![new]),
+                (solc_0_8_7, vec![capped]),
+                (PathBuf::from("solc"), vec![none, future]),
+            ])
+        );
+    }
+}
@@ -1,69 +1,241 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     process::Stdio,
+    sync::Arc,
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
-use lsp_client::client::Client;
+use futures::future::{join_all, try_join_all};
+use lsp_client::{
+    cache::{CachedMetrics, RequestCache},
+    client::Client,
+};
 use lsp_types::{
-    notification::Initialized,
-    request::{GotoDeclarationParams, GotoDefinition, Initialize},
-    GotoDefinitionResponse, InitializeParams, InitializedParams, Location, PartialResultParams,
-    Range, TextDocumentPositionParams, Url, WorkDoneProgressParams,
+    notification::{DidSaveTextDocument, Initialized},
+    request::Initialize,
+    DidSaveTextDocumentParams, InitializeParams, InitializedParams, Location,
+    TextDocumentIdentifier, Url,
 };
 use tokio::{
     process::{Child, Command},
     task::JoinHandle,
 };
-use tree_sitter::Query;
+use tree_sitter::{Node, Query};
 
 use crate::{
     converter::{Convert, Converter},
-    language_provider::{self, LanguageAutomata, LspProvider, SupportedLanguage},
+    engine::{Backend, Engine, EngineOptions},
+    language_provider::{
+        GraphOutput, LanguageAutomata, LspProvider, Queries, ScanOutput, SupportedLanguage, Trace,
+    },
+    languages::{
+        request_definitions,
+        solc::SolcAst,
+        solc_versions::{default_solc_dirs, find_installs, group_by_compiler},
+        solidity_project::SolidityProject,
+        StepMeta,
+    },
+    rules::{parse_rules, Rule},
+    sources::SourceCache,
     utils::{
-        get_breadcrumbs, get_named_child_index, get_node_location, get_query_results,
-        get_uri_content, parse_file,
+        get_breadcrumbs, get_location_node, get_named_child_index, get_node_location,
+        get_query_results, parse_file, LocationKey,
     },
 };
 
+/// Call sites of a project, with their resolved definitions.
+struct CallSiteIndex {
+    queries: Vec<Query>,
+    sites: HashMap<PathBuf, Vec<(Location, Vec<Location>)>>,
+    /// Call sites resolving to each definition.
+    references: HashMap<LocationKey, Vec<Location>>,
+    /// Files whose call sites need to be (re)resolved.
+    stale: HashSet<PathBuf>,
+}
+
+/// Call sites, each capturing the identifier definitions are resolved from.
+///
+/// Compiled one by one, so patterns the grammar doesn't support are skipped.
+const CALL_SITE_PATTERNS: &[&str] = &[
+    "(call_expression function: (identifier) @site)",
+    // also covers `this.f()`, `super.f()` and `Library.f()`
+    "(call_expression function: (member_expression property: (identifier) @site))",
+    // calls with options, e.g. `target.call{value: 1}()`
+    "(call_expression function: (struct_expression type: (identifier) @site))",
+    "(call_expression function: (struct_expression type: (member_expression property: (identifier) @site)))",
+    "(modifier_invocation (identifier) @site)",
+    "(new_expression name: (type_name (user_defined_type (identifier) @site)))",
+    "(new_expression name: (type_name (identifier) @site))",
+];
+
 pub struct SolidityLs {
     client: Client,
+    cache: Arc<RequestCache>,
     project_files: Vec<PathBuf>,
+    call_sites: tokio::sync::Mutex<CallSiteIndex>,
     handles: Vec<JoinHandle<()>>,
 }
 
 impl SolidityLs {
-    fn start_solidity_ls() -> Child {
-        Command::new("solc")
+    fn start_solidity_ls(solc: &Path) -> Result<Child> {
+        Command::new(solc)
             .arg("--lsp")
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .expect("failed to start solidity ls")
+            .context("failed to start solidity ls")
     }
 
+    /// Start the `solc` on the path, see [`SolidityLs::with_solc`].
     pub async fn new(root_dir: &Path, project_files: Vec<PathBuf>) -> Result<Self> {
-        let (client, handles) = lsp_client::clients::child_client(SolidityLs::start_solidity_ls());
+        Self::with_solc(Path::new("solc"), root_dir, project_files).await
+    }
+
+    /// Start `solc --lsp` in `root_dir`, looking imports up in the include
+    /// paths of the [`SolidityProject`] there.
+    ///
+    /// The server has no setting for remappings, imports only resolving
+    /// through them are resolved by [`SolcAst`] only.
+    pub async fn with_solc(
+        solc: &Path,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+    ) -> Result<Self> {
+        let project = SolidityProject::detect(root_dir)?;
+        let (client, handles) =
+            lsp_client::clients::child_client(SolidityLs::start_solidity_ls(solc)?);
+        let cache = Arc::new(RequestCache::new());
+        let client = client.with_middleware(cache.clone());
         client
             .request::<Initialize>(InitializeParams {
                 root_uri: Some(
                     Url::from_file_path(root_dir)
                         .map_err(|_| anyhow!("failed to convert root dir to url"))?,
                 ),
+                initialization_options: Some(serde_json::json!({
+                    "include-paths": project.include_paths,
+                })),
                 ..Default::default()
             })
             .await??;
 
         client.notify::<Initialized>(InitializedParams {})?;
 
+        let queries = CALL_SITE_PATTERNS
+            .iter()
+            .filter_map(|pattern| Query::new(tree_sitter_solidity::language(), pattern).ok())
+            .collect();
+
         Ok(Self {
+            call_sites: tokio::sync::Mutex::new(CallSiteIndex {
+                queries,
+                sites: HashMap::new(),
+                references: HashMap::new(),
+                stale: project_files.iter().cloned().collect(),
+            }),
             client,
+            cache,
             project_files,
             handles,
         })
     }
+
+    /// Re-resolve the call sites of a changed file on the next reference
+    /// lookup, along with call sites in other files resolving into it.
+    ///
+    /// The file is saved to the server, which also drops the cached
+    /// responses of the [`RequestCache`]. A scan reads each file once, so
+    /// this is for whoever watches the project for changes while the server
+    /// runs, e.g. an editor integration.
+    pub async fn file_changed(&self, path: &Path) -> Result<()> {
+        let uri: Url = Converter::convert(path);
+        self.client
+            .notify::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                text: None,
+            })?;
+
+        let mut index = self.call_sites.lock().await;
+        let dependents = index
+            .sites
+            .iter()
+            .filter(|(_, sites)| {
+                sites
+                    .iter()
+                    .any(|(_, definitions)| definitions.iter().any(|d| d.uri == uri))
+            })
+            .map(|(file, _)| file.clone())
+            .collect::<Vec<_>>();
+
+        index.stale.extend(dependents);
+        if self.project_files.iter().any(|f| f == path) {
+            index.stale.insert(path.to_path_buf());
+        }
+
+        Ok(())
+    }
+
+    async fn reindex(&self, index: &mut CallSiteIndex) -> Result<()> {
+        for file in std::mem::take(&mut index.stale) {
+            index.sites.remove(&file);
+
+            let Ok((text, tree)) = parse_file(&file, tree_sitter_solidity::language()) else {
+                continue;
+            };
+
+            let uri: Url = Converter::convert(file.as_path());
+            let mut call_sites = vec![];
+            for query in &index.queries {
+                let capture_index = query
+                    .capture_index_for_name("site")
+                    .expect("call site patterns capture a site");
+
+                for node in get_query_results(&text, tree.root_node(), query, capture_index) {
+                    let location = get_node_location(uri.clone(), &node);
+                    if !call_sites.contains(&location) {
+                        call_sites.push(location);
+                    }
+                }
+            }
+
+            let definitions =
+                join_all(call_sites.iter().map(|site| self.find_definitions(site))).await;
+
+            let sites = call_sites
+                .into_iter()
+                .zip(definitions)
+                .filter_map(|(site, definitions)| Some((site, definitions.ok()?)))
+                .collect();
+
+            index.sites.insert(file, sites);
+        }
+
+        index.references.clear();
+        for sites in index.sites.values() {
+            for (site, definitions) in sites {
+                for definition in definitions {
+                    index
+                        .references
+                        .entry(LocationKey::from(definition))
+                        .or_default()
+                        .push(site.clone());
+                }
+            }
+        }
+
+        for references in index.references.values_mut() {
+            references.sort_by_key(|r| (r.uri.to_string(), r.range.start));
+        }
+
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> CachedMetrics {
+        CachedMetrics::new(&self.client, &self.cache)
+    }
 }
 
 impl Drop for SolidityLs {
@@ -77,122 +249,357 @@ impl Drop for SolidityLs {
 #[async_trait]
 impl LspProvider for SolidityLs {
     async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
-        let definitions = self
-            .client
-            .request::<GotoDefinition>(GotoDeclarationParams {
-                text_document_position_params: TextDocumentPositionParams {
-                    text_document: lsp_types::TextDocumentIdentifier {
-                        uri: location.uri.clone(),
-                    },
-                    position: location.range.start,
-                },
-                work_done_progress_params: WorkDoneProgressParams {
-                    work_done_token: None,
-                },
-                partial_result_params: PartialResultParams {
-                    partial_result_token: None,
-                },
-            })
-            .await
-            .context("awaiting goto definition response")?
-            .context("getting goto definition result")?;
-
-        if let Some(definitions) = definitions {
-            Ok(match definitions {
-                GotoDefinitionResponse::Scalar(definition) => vec![definition],
-                GotoDefinitionResponse::Array(definitions) => definitions,
-                GotoDefinitionResponse::Link(_) => todo!("what is link?"),
-            })
-        } else {
-            Ok(vec![])
-        }
+        request_definitions(&self.client, location).await
     }
 
     async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
-        let query = (
-            Query::new(
-                tree_sitter_solidity::language(),
-                "(call_expression function: [
-                    (identifier) @ident
-                    (member_expression
-                        property: (identifier) @ident
-                    )
-                ])",
-            )
-            .unwrap(),
-            0,
-        );
-
-        let call_locations = {
-            let language = tree_sitter_solidity::language();
-
-            let mut locations = vec![];
-            for project_file in &self.project_files {
-                let content =
-                    String::from_utf8(std::fs::read(project_file).context("failed to read file")?)
-                        .context("got non-utf8 file")?;
-
-                let mut parser = tree_sitter::Parser::new();
-                parser
-                    .set_language(language)
-                    .expect("failed to set language on parser");
-                let tree = parser
-                    .parse(&content, None)
-                    .expect("failed to parse content");
-
-                let results = get_query_results(&content, tree.root_node(), &query.0, query.1);
-
-                for result in results {
-                    let start = Converter::convert(result.start_position());
-                    let end = Converter::convert(result.end_position());
-                    let url = Converter::convert(project_file.as_path());
-
-                    locations.push(Location::new(url, Range::new(start, end)));
-                }
-            }
+        let mut index = self.call_sites.lock().await;
+        if !index.stale.is_empty() {
+            self.reindex(&mut index).await?;
+        }
 
-            locations
-        };
+        Ok(index
+            .references
+            .get(&LocationKey::from(location))
+            .cloned()
+            .unwrap_or_default())
+    }
+}
 
-        let mut references = vec![];
-        for call_location in call_locations {
-            let Ok(definitions) = self.find_definitions(&call_location).await
-            else {
-                continue;
-            };
+/// Definitions and references of a Solidity project, from the compiler's
+/// AST or from `solc --lsp`, see [`Backend`].
+pub enum SolidityServer {
+    Ls(Box<SolidityLs>),
+    Ast(SolcAst),
+}
 
-            for definition in definitions {
-                if &definition == location {
-                    references.push(call_location.clone());
+impl SolidityServer {
+    pub async fn start(
+        solc: &Path,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        backend: Backend,
+    ) -> Result<Self> {
+        let ls = |project_files| async move {
+            SolidityLs::with_solc(solc, root_dir, project_files)
+                .await
+                .map(|ls| Self::Ls(Box::new(ls)))
+        };
+
+        match backend {
+            Backend::TreeSitter => bail!("solidity has no tree-sitter scopes backend"),
+            Backend::LanguageServer => ls(project_files).await,
+            Backend::Compiler => Ok(Self::Ast(
+                SolcAst::with_solc(solc, root_dir, &project_files).await?,
+            )),
+            Backend::Auto => match SolcAst::with_solc(solc, root_dir, &project_files).await {
+                Ok(ast) => Ok(Self::Ast(ast)),
+                Err(e) => {
+                    eprintln!("{:#}, resolving solidity names with solc --lsp", e);
+                    ls(project_files).await
                 }
-            }
+            },
         }
+    }
+
+    pub fn metrics(&self) -> CachedMetrics {
+        match self {
+            Self::Ls(ls) => ls.metrics(),
+            Self::Ast(_) => CachedMetrics::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl LspProvider for SolidityServer {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        match self {
+            Self::Ls(ls) => ls.find_definitions(location).await,
+            Self::Ast(ast) => ast.find_definitions(location).await,
+        }
+    }
+
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        match self {
+            Self::Ls(ls) => ls.find_references(location).await,
+            Self::Ast(ast) => ast.find_references(location).await,
+        }
+    }
+
+    async fn find_implementations(&self, location: &Location) -> Result<Vec<Location>> {
+        match self {
+            Self::Ls(ls) => ls.find_implementations(location).await,
+            Self::Ast(ast) => ast.find_implementations(location).await,
+        }
+    }
+}
+
+/// One [`SolidityServer`] per solc version the pragmas of a project's
+/// files need, see [`group_by_compiler`].
+pub struct SolidityServers {
+    servers: Vec<(HashSet<Url>, SolidityServer)>,
+}
+
+impl SolidityServers {
+    /// Start a server for each group of `project_files`, with the newest
+    /// binary of `solc_dirs` their pragmas allow.
+    pub async fn start(
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        solc_dirs: &[PathBuf],
+        backend: Backend,
+    ) -> Result<Self> {
+        let groups = group_by_compiler(&project_files, &find_installs(solc_dirs));
+        if groups.len() > 1 {
+            eprintln!(
+                "compiling solidity files with {}",
+                groups
+                    .keys()
+                    .map(|solc| solc.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let servers = try_join_all(groups.into_iter().map(|(solc, files)| async move {
+            let uris = files
+                .iter()
+                .map(|f| Converter::convert(f.as_path()))
+                .collect();
+            let server = SolidityServer::start(&solc, root_dir, files, backend)
+                .await
+                .with_context(|| format!("failed to start {}", solc.display()))?;
+
+            anyhow::Ok((uris, server))
+        }))
+        .await?;
+
+        Ok(Self { servers })
+    }
+
+    /// Servers compiling the file of `location`, or all of them for files
+    /// only compiled as imports.
+    fn servers_for(&self, location: &Location) -> Vec<&SolidityServer> {
+        let servers = self
+            .servers
+            .iter()
+            .filter(|(uris, _)| uris.contains(&location.uri))
+            .map(|(_, server)| server)
+            .collect::<Vec<_>>();
+
+        match servers.is_empty() {
+            true => self.servers.iter().map(|(_, server)| server).collect(),
+            false => servers,
+        }
+    }
+
+    pub fn metrics(&self) -> CachedMetrics {
+        let mut servers = self.servers.iter().map(|(_, server)| server.metrics());
+        let mut metrics = servers.next().unwrap_or_default();
+        for other in servers {
+            metrics.merge(&other);
+        }
+
+        metrics
+    }
+}
 
-        Ok(references)
+impl From<SolidityServer> for SolidityServers {
+    /// A single server, for all files.
+    fn from(server: SolidityServer) -> Self {
+        Self {
+            servers: vec![(HashSet::new(), server)],
+        }
     }
 }
 
-pub struct Solidity;
+/// Locations of all `results`, without duplicates, sorted if there's more
+/// than one server.
+fn merge_locations(results: Vec<Result<Vec<Location>>>) -> Result<Vec<Location>> {
+    let sort = results.len() > 1;
+
+    let mut locations: Vec<Location> = vec![];
+    for location in results
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+    {
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
+    }
+    if sort {
+        locations
+            .sort_by(|a, b| (a.uri.as_str(), a.range.start).cmp(&(b.uri.as_str(), b.range.start)));
+    }
+
+    Ok(locations)
+}
 
+#[async_trait]
+impl LspProvider for SolidityServers {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        merge_locations(
+            join_all(
+                self.servers_for(location)
+                    .into_iter()
+                    .map(|server| server.find_definitions(location)),
+            )
+            .await,
+        )
+    }
+
+    /// References from the files of every server, since files of another
+    /// version may call into the same library.
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        merge_locations(
+            join_all(
+                self.servers
+                    .iter()
+                    .map(|(_, server)| server.find_references(location)),
+            )
+            .await,
+        )
+    }
+
+    async fn find_implementations(&self, location: &Location) -> Result<Vec<Location>> {
+        merge_locations(
+            join_all(
+                self.servers
+                    .iter()
+                    .map(|(_, server)| server.find_implementations(location)),
+            )
+            .await,
+        )
+    }
+}
+
+/// Checks protecting a value, which paths through it are sanitized by.
 #[derive(Debug, Clone)]
-pub enum StepMeta {
-    Start,
-    GotoDefinition,
-    GotoArgument(usize),
-    GotoReference,
-    Resolve {
-        /// Use this when a call expression needs to be resolved.
-        ///
-        /// The Location anchor is here so that if the resolved return value
-        /// is a parameter, we can return to the correct call expression
-        anchor: Option<Location>,
-        index: Option<usize>,
-    },
+pub struct Guards {
+    /// Functions asserting their first argument, e.g. `require(allowed[target])`.
+    pub functions: Vec<String>,
+    /// Modifiers restricting the callers of a function, e.g. `onlyOwner`.
+    pub modifiers: Vec<String>,
+    /// Whether `if (...) revert` checks are guards.
+    pub reverts: bool,
+}
+
+impl Default for Guards {
+    fn default() -> Self {
+        Self {
+            functions: vec!["require".to_string(), "assert".to_string()],
+            modifiers: vec![
+                "onlyOwner".to_string(),
+                "onlyAdmin".to_string(),
+                "onlyRole".to_string(),
+            ],
+            reverts: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Solidity {
+    pub guards: Guards,
+    /// Directories of versioned solc binaries, see [`find_installs`].
+    pub solc_dirs: Vec<PathBuf>,
+}
+
+impl Default for Solidity {
+    fn default() -> Self {
+        Self {
+            guards: Guards::default(),
+            solc_dirs: default_solc_dirs(),
+        }
+    }
+}
+
+/// Whether `node` or any of its descendants satisfies `f`.
+fn any_descendant(node: Node, f: &impl Fn(Node) -> bool) -> bool {
+    if f(node) {
+        return true;
+    }
+
+    let mut cursor = node.walk();
+    let found = node
+        .named_children(&mut cursor)
+        .any(|c| any_descendant(c, f));
+    found
+}
+
+/// All descendants of `node` of the given kind.
+fn descendants_of_kind<'a>(node: Node<'a>, kind: &str, nodes: &mut Vec<Node<'a>>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.kind() == kind {
+            nodes.push(child);
+        }
+        descendants_of_kind(child, kind, nodes);
+    }
+}
+
+impl Solidity {
+    /// A modifier guard of `function`.
+    fn find_modifier<'a>(&self, text: &str, function: Node<'a>) -> Option<Node<'a>> {
+        let mut cursor = function.walk();
+        let modifier = function
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "modifier_invocation")
+            .find(|modifier| {
+                modifier
+                    .named_child(0)
+                    .and_then(|name| name.utf8_text(text.as_bytes()).ok())
+                    .is_some_and(|name| self.guards.modifiers.iter().any(|m| m == name))
+            });
+        modifier
+    }
+
+    /// A check on `name` in `function`, since a failed check reverts the
+    /// whole transaction it doesn't have to precede the value's use.
+    fn find_check<'a>(&self, text: &str, function: Node<'a>, name: &str) -> Option<Node<'a>> {
+        let node_text = |node: Node| node.utf8_text(text.as_bytes()).unwrap_or_default();
+        let mentions_name = |node: Node| {
+            any_descendant(node, &|n| n.kind() == "identifier" && node_text(n) == name)
+        };
+
+        let mut calls = vec![];
+        descendants_of_kind(function, "call_expression", &mut calls);
+        let call = calls.into_iter().find(|call| {
+            let is_guard = call
+                .child_by_field_name("function")
+                .is_some_and(|f| self.guards.functions.iter().any(|g| g == node_text(f)));
+
+            let mut cursor = call.walk();
+            let condition = call
+                .named_children(&mut cursor)
+                .find(|c| c.kind() == "call_argument");
+
+            is_guard && condition.is_some_and(mentions_name)
+        });
+        if call.is_some() || !self.guards.reverts {
+            return call;
+        }
+
+        let mut checks = vec![];
+        descendants_of_kind(function, "if_statement", &mut checks);
+        checks.into_iter().find(|check| {
+            let reverts = any_descendant(*check, &|n| {
+                n.kind() == "revert_statement"
+                    || (n.kind() == "call_expression"
+                        && n.child_by_field_name("function")
+                            .is_some_and(|f| node_text(f) == "revert"))
+            });
+
+            reverts && check.named_child(0).is_some_and(mentions_name)
+        })
+    }
 }
 
 impl LanguageAutomata for Solidity {
     type Stack = StepMeta;
-    type LspProvider = SolidityLs;
+    type LspProvider = SolidityServers;
 
     fn get_language(&self) -> tree_sitter::Language {
         tree_sitter_solidity::language()
@@ -202,18 +609,55 @@ impl LanguageAutomata for Solidity {
         vec![StepMeta::Start]
     }
 
+    fn find_guard(&self, location: &Location, sources: &SourceCache) -> Result<Option<Location>> {
+        let file = sources.get(&location.uri)?;
+        let Some(node) = get_location_node(file.tree.root_node(), location) else {
+            return Ok(None);
+        };
+
+        let mut function = node;
+        while !matches!(
+            function.kind(),
+            "function_definition" | "constructor_definition" | "modifier_definition"
+        ) {
+            let Some(parent) = function.parent() else {
+                return Ok(None);
+            };
+            function = parent;
+        }
+
+        if let Some(modifier) = self.find_modifier(&file.text, function) {
+            return Ok(Some(get_node_location(location.uri.clone(), &modifier)));
+        }
+
+        let name = match node.kind() {
+            "identifier" => Some(node),
+            _ => node.child_by_field_name("name"),
+        }
+        .and_then(|name| name.utf8_text(file.text.as_bytes()).ok());
+
+        Ok(name
+            .and_then(|name| self.find_check(&file.text, function, name))
+            .map(|check| get_node_location(location.uri.clone(), &check)))
+    }
+
+    fn required_queries(&self, _location: &Location, state: &Self::Stack) -> Queries {
+        state.queries()
+    }
+
     fn transition(
         &self,
         location: Location,
         state: Self::Stack,
         definitions: Result<Vec<Location>>,
         references: Result<Vec<Location>>,
+        sources: &SourceCache,
     ) -> Result<Vec<(Location, Vec<Self::Stack>)>> {
-        let tree = self.get_tree(&location)?;
-        let root = tree.root_node();
+        let file = sources.get(&location.uri)?;
+        let root = file.tree.root_node();
 
         let Some(breadcrumbs) = get_breadcrumbs(root, &location) else {
-            todo!("when does this happen?")
+            return Ok(vec![]);
         };
 
         let breadcrumbs = breadcrumbs
@@ -221,12 +665,6 @@ impl LanguageAutomata for Solidity {
             .map(|n| (n.kind(), n))
             .collect::<Vec<_>>();
 
-        eprintln!(
-            "Got location:\nbreadcrumbs: {:?}\n\n{}\n",
-            breadcrumbs.iter().map(|(k, _)| k).collect::<Vec<_>>(),
-            crate::test_utils::display_location(&location, &state, Some(5))
-        );
-
         Ok(
             match (state, breadcrumbs.as_slice(), definitions, references) {
                 (_, [("number_literal", _), ..], _, _) => vec![],
@@ -377,9 +815,9 @@ impl LanguageAutomata for Solidity {
                     _,
                     _,
                 ) => get_query_results(
-                    &get_uri_content(&location.uri)?,
+                    &file.text,
                     *function_definition,
-                    &Query::new(self.get_language(), "(return_statement (_) @return)").unwrap(),
+                    &*sources.query("(return_statement (_) @return)")?,
                     0,
                 )
                 .iter()
@@ -444,129 +882,98 @@ impl LanguageAutomata for Solidity {
     }
 }
 
+/// Server [`SolidityLs`] runs, with `--lsp`.
+const SERVERS: &[&str] = &["solc"];
+
+const DEFAULT_RULES: &str = include_str!("../../rules/solidity.toml");
+
 #[async_trait]
 impl SupportedLanguage for Solidity {
-    fn get_start_end(&self, project_files: &[PathBuf]) -> Result<(Vec<Location>, Vec<Location>)> {
-        let mut start_locations = vec![];
-        let mut end_locations = vec![];
-
-        for project_file in project_files {
-            let (text, tree) = parse_file(project_file)?;
-            let root = tree.root_node();
-
-            end_locations.extend(
-                get_query_results(
-                    &text,
-                    root,
-                    &Query::new(
-                        tree_sitter_solidity::language(),
-                        r#"
-            (member_expression
-                object: (identifier) @obj (#match? @obj "msg")
-                property: (identifier) @prop (#match? @prop "sender")
-            ) @pub
-            "#,
-                    )
-                    .unwrap(),
-                    2,
-                )
-                .iter()
-                .map(|n| get_node_location(Converter::convert(project_file.as_path()), n)),
-            );
+    fn name(&self) -> &'static str {
+        "solidity"
+    }
 
-            start_locations.extend(
-                get_query_results(
-                    &text,
-                    root,
-                    &Query::new(
-                        tree_sitter_solidity::language(),
-                        r#"
-        (call_expression
-            function: (struct_expression
-                type: (member_expression
-                    object: (identifier) @hacky
-                    property: (identifier) @method
-                    (#match? @method "call")
-                )
-            )
-        )
-        "#,
-                    )
-                    .unwrap(),
-                    0,
-                )
-                .iter()
-                .map(|n| get_node_location(Converter::convert(project_file.as_path()), n)),
-            );
-        }
+    fn extensions(&self) -> &'static [&'static str] {
+        &["sol"]
+    }
+
+    fn tree_sitter_language(&self) -> tree_sitter::Language {
+        tree_sitter_solidity::language()
+    }
+
+    fn servers(&self) -> &'static [&'static str] {
+        SERVERS
+    }
 
-        Ok((start_locations, end_locations))
+    fn project_files(&self, root_dir: &Path, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let project = SolidityProject::detect(root_dir)?;
+
+        Ok(files
+            .iter()
+            .filter(|f| self.is_project_file(f) && !project.is_excluded(f))
+            .cloned()
+            .collect())
+    }
+
+    fn default_rules(&self) -> Result<Vec<Rule>> {
+        parse_rules(DEFAULT_RULES).context("invalid default solidity rules")
     }
+
     async fn find_paths(
         &self,
         root_dir: &Path,
         project_files: Vec<PathBuf>,
         start_locations: Vec<Location>,
         stop_at: &[Location],
-    ) -> Result<Vec<Vec<Location>>> {
-        let lsp = SolidityLs::new(root_dir, project_files)
+        options: &EngineOptions,
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<ScanOutput> {
+        let lsp = SolidityServers::start(root_dir, project_files, &self.solc_dirs, options.backend)
             .await
-            .context("failed to start solidity ls")?;
-
-        let mut all_paths = vec![];
-        for start_location in start_locations {
-            let paths = language_provider::find_paths(
-                &Solidity,
-                &lsp,
-                start_location,
-                Solidity.initial_state(),
-                stop_at,
-            )
+            .context("failed to start solidity servers")?;
+
+        let mut engine = Engine::new(self, &lsp, options.clone());
+        let (paths, sources) = engine
+            .explore_all(start_locations, stop_at, on_trace)
             .await?;
 
-            all_paths.extend(paths);
-        }
+        Ok(ScanOutput {
+            paths,
+            sources,
+            metrics: lsp.metrics(),
+        })
+    }
+
+    async fn find_graph(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+    ) -> Result<GraphOutput> {
+        let lsp = SolidityServers::start(root_dir, project_files, &self.solc_dirs, options.backend)
+            .await
+            .context("failed to start solidity servers")?;
 
-        Ok(all_paths)
+        let mut engine = Engine::new(self, &lsp, options.clone());
+        let (graph, sources) = engine.explore_graph_all(start_locations, stop_at).await?;
+
+        Ok(GraphOutput {
+            graph: graph.map(|meta| format!("{:?}", meta)),
+            sources,
+            metrics: lsp.metrics(),
+        })
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "test-utils"))]
 mod tests {
     use super::*;
-    use crate::test_utils::display_locations;
-    use crate::test_utils::setup_test_dir;
-
-    macro_rules! snapshot {
-        ($name:tt, $state:expr, $input:literal) => {
-            #[test]
-            fn $name() {
-                let (_root_dir, location, definitions, references) = setup_test_dir($input);
-
-                let solidity = Solidity;
-
-                let next_steps = solidity
-                    .transition(location, $state, Ok(definitions), Ok(references))
-                    .expect("failed");
-
-                let next_steps = display_locations(next_steps, None);
-                let snapshot = format!(
-                    r#"
---- input ---
-{}
-
---- output ---
-{}
-            "#,
-                    $input, next_steps
-                );
-
-                insta::assert_snapshot!(snapshot);
-            }
-        };
-    }
+    use crate::{snapshot, test_utils::setup_test_dir};
 
     snapshot!(
+        Solidity::default(),
         test_start,
         StepMeta::Start,
         r#"
@@ -589,6 +996,7 @@ contract Contract {
     );
 
     snapshot!(
+        Solidity::default(),
         goto_definition,
         StepMeta::GotoDefinition,
         r#"
@@ -609,4 +1017,70 @@ contract Contract {
 }
         "#
     );
+
+    fn guard_line(input: &str) -> Option<u32> {
+        let (_root_dir, location, _, _) = setup_test_dir(input);
+
+        let solidity = Solidity::default();
+        let sources = SourceCache::new(solidity.get_language());
+
+        solidity
+            .find_guard(&location, &sources)
+            .expect("failed to find guard")
+            .map(|guard| guard.range.start.line)
+    }
+
+    #[test]
+    fn test_find_guard() {
+        let require = r#"
+contract.sol
+#@#
+contract Contract {
+    function withdraw(address target) public {
+        require(allowed[target]);
+        (bool sent, ) = target.call{value: 1}("");
+                     // ^^^^^^ start
+    }
+}
+        "#;
+        assert_eq!(guard_line(require), Some(3));
+
+        let modifier = r#"
+contract.sol
+#@#
+contract Contract {
+    function withdraw(address target) public onlyOwner {
+        (bool sent, ) = target.call{value: 1}("");
+                     // ^^^^^^ start
+    }
+}
+        "#;
+        assert_eq!(guard_line(modifier), Some(2));
+
+        let revert = r#"
+contract.sol
+#@#
+contract Contract {
+    function withdraw(address target) public {
+        if (!allowed[target]) revert();
+        (bool sent, ) = target.call{value: 1}("");
+                     // ^^^^^^ start
+    }
+}
+        "#;
+        assert_eq!(guard_line(revert), Some(3));
+
+        let unrelated = r#"
+contract.sol
+#@#
+contract Contract {
+    function withdraw(address target) public {
+        require(msg.value > 0);
+        (bool sent, ) = target.call{value: 1}("");
+                     // ^^^^^^ start
+    }
+}
+        "#;
+        assert_eq!(guard_line(unrelated), None);
+    }
 }
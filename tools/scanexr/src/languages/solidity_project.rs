@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// `[profile.<name>]` settings of a `foundry.toml`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FoundryProfile {
+    test: Option<String>,
+    script: Option<String>,
+    libs: Option<Vec<String>>,
+    remappings: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FoundryConfig {
+    profile: HashMap<String, FoundryProfile>,
+}
+
+const HARDHAT_CONFIGS: &[&str] = &[
+    "hardhat.config.js",
+    "hardhat.config.ts",
+    "hardhat.config.cjs",
+    "hardhat.config.mjs",
+];
+
+/// Prefix a `[context:]prefix=target` remapping applies to.
+fn remapping_prefix(remapping: &str) -> &str {
+    remapping
+        .split_once('=')
+        .map_or(remapping, |(prefix, _)| prefix)
+}
+
+/// How solc resolves the imports of a Solidity project, and which of its
+/// directories aren't scanned.
+///
+/// Detected from a Foundry (`foundry.toml`, `remappings.txt`, `lib`) or
+/// Hardhat (`hardhat.config.*`, `node_modules`) layout, so imports like
+/// `forge-std/Test.sol` or `@openzeppelin/contracts/...` compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityProject {
+    /// Directory source unit names are relative to, the project's root.
+    pub base_path: PathBuf,
+    /// Directories imports are also looked up in, e.g. `node_modules`.
+    pub include_paths: Vec<PathBuf>,
+    /// `[context:]prefix=target` remappings, e.g.
+    /// `forge-std/=lib/forge-std/src/`.
+    pub remappings: Vec<String>,
+    /// Directories of tests, scripts and dependencies.
+    pub excluded_dirs: Vec<PathBuf>,
+}
+
+impl SolidityProject {
+    pub fn detect(root_dir: &Path) -> Result<Self> {
+        let foundry_toml = root_dir.join("foundry.toml");
+        let foundry = match std::fs::read_to_string(&foundry_toml) {
+            Ok(text) => toml::from_str::<FoundryConfig>(&text)
+                .with_context(|| format!("failed to parse {}", foundry_toml.display()))?
+                .profile
+                .remove("default")
+                .unwrap_or_default(),
+            Err(_) => FoundryProfile::default(),
+        };
+
+        let libs = foundry.libs.unwrap_or_else(|| vec!["lib".to_string()]);
+
+        // like forge, a library's `src` if it has one, explicit remappings
+        // of the same prefix replace these
+        let mut remappings = vec![];
+        for lib in &libs {
+            let Ok(entries) = std::fs::read_dir(root_dir.join(lib)) else {
+                continue;
+            };
+
+            let mut names = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+
+            for name in names {
+                let target = match root_dir.join(lib).join(&name).join("src").is_dir() {
+                    true => format!("{}/{}/src/", lib, name),
+                    false => format!("{}/{}/", lib, name),
+                };
+                remappings.push(format!("{}/={}", name, target));
+            }
+        }
+
+        remappings.extend(foundry.remappings);
+        if let Ok(text) = std::fs::read_to_string(root_dir.join("remappings.txt")) {
+            remappings.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let mut deduplicated: Vec<String> = vec![];
+        for remapping in remappings {
+            deduplicated.retain(|r| remapping_prefix(r) != remapping_prefix(&remapping));
+            deduplicated.push(remapping);
+        }
+
+        let is_hardhat = HARDHAT_CONFIGS.iter().any(|f| root_dir.join(f).is_file());
+        let node_modules = root_dir.join("node_modules");
+        let include_paths = match is_hardhat || node_modules.is_dir() {
+            true => vec![node_modules.clone()],
+            false => vec![],
+        };
+
+        let mut excluded_dirs = vec![
+            root_dir.join(foundry.test.as_deref().unwrap_or("test")),
+            root_dir.join(foundry.script.as_deref().unwrap_or("script")),
+            node_modules,
+        ];
+        excluded_dirs.extend(libs.iter().map(|lib| root_dir.join(lib)));
+
+        Ok(Self {
+            base_path: root_dir.to_path_buf(),
+            include_paths,
+            remappings: deduplicated,
+            excluded_dirs,
+        })
+    }
+
+    /// Whether `path` is in a test, script or dependency directory.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.excluded_dirs.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Arguments of a solc compiling the project, to be run in
+    /// [`SolidityProject::base_path`].
+    pub fn solc_args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["--base-path".into(), ".".into()];
+        for include_path in &self.include_paths {
+            args.push("--include-path".into());
+            args.push(include_path.into());
+        }
+        args.extend(self.remappings.iter().map(OsString::from));
+
+        args
+    }
+
+    /// File of the source unit `name` of solc's output.
+    pub fn resolve(&self, name: &str) -> PathBuf {
+        std::iter::once(&self.base_path)
+            .chain(&self.include_paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| self.base_path.join(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, text) in files {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn test_foundry() {
+        let dir = setup(&[
+            (
+                "foundry.toml",
+                "[profile.default]\ntest = \"tests\"\nremappings = [\"solmate/=lib/solmate/src/\"]\n",
+            ),
+            ("remappings.txt", "# comment\n@oz/=lib/openzeppelin-contracts/contracts/\n"),
+            ("lib/forge-std/src/Test.sol", ""),
+            ("lib/openzeppelin-contracts/contracts/token/ERC20.sol", ""),
+            ("lib/solmate/auth/Owned.sol", ""),
+            ("src/Vault.sol", ""),
+        ]);
+        let root = dir.path();
+
+        let project = SolidityProject::detect(root).unwrap();
+        assert_eq!(
+            project.remappings,
+            vec![
+                "forge-std/=lib/forge-std/src/",
+                "openzeppelin-contracts/=lib/openzeppelin-contracts/",
+                "solmate/=lib/solmate/src/",
+                "@oz/=lib/openzeppelin-contracts/contracts/",
+            ]
+        );
+        assert!(project.include_paths.is_empty());
+
+        assert!(project.is_excluded(&root.join("tests/Vault.t.sol")));
+        assert!(project.is_excluded(&root.join("script/Deploy.s.sol")));
+        assert!(project.is_excluded(&root.join("lib/forge-std/src/Test.sol")));
+        assert!(!project.is_excluded(&root.join("src/Vault.sol")));
+        assert!(!project.is_excluded(&root.join("test/Vault.t.sol")));
+    }
+
+    #[test]
+    fn test_hardhat() {
+        let dir = setup(&[
+            ("hardhat.config.ts", ""),
+            (
+                "node_modules/@openzeppelin/contracts/access/Ownable.sol",
+                "",
+            ),
+            ("contracts/Vault.sol", ""),
+        ]);
+        let root = dir.path();
+
+        let project = SolidityProject::detect(root).unwrap();
+        assert_eq!(project.include_paths, vec![root.join("node_modules")]);
+        assert!(project.remappings.is_empty());
+        assert!(project
+            .is_excluded(&root.join("node_modules/@openzeppelin/contracts/access/Ownable.sol")));
+        assert!(!project.is_excluded(&root.join("contracts/Vault.sol")));
+
+        assert_eq!(
+            project.resolve("@openzeppelin/contracts/access/Ownable.sol"),
+            root.join("node_modules/@openzeppelin/contracts/access/Ownable.sol")
+        );
+        assert_eq!(
+            project.resolve("contracts/Vault.sol"),
+            root.join("contracts/Vault.sol")
+        );
+
+        let args = project.solc_args();
+        assert_eq!(args[..3], ["--base-path", ".", "--include-path"]);
+    }
+}
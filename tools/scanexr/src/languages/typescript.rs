@@ -0,0 +1,880 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lsp_types::{Location, Url};
+use tree_sitter::Node;
+
+use crate::{
+    engine::EngineOptions,
+    language_provider::{
+        GraphOutput, LanguageAutomata, Queries, ScanOutput, SupportedLanguage, Trace,
+    },
+    languages::{find_project_graph, find_project_paths, LibraryPaths, ProjectServer, StepMeta},
+    rules::{parse_rules, Rule},
+    sources::SourceCache,
+    utils::{get_breadcrumbs, get_node_location, get_query_results},
+};
+
+/// Language servers tried in order, the first one installed is used.
+const SERVERS: &[&str] = &["typescript-language-server"];
+
+const DEFAULT_RULES: &str = include_str!("../../rules/typescript.toml");
+
+const LOCALS: &str = include_str!("../../queries/typescript/locals.scm");
+
+/// Extensions of files parsed as TypeScript, JavaScript is parsed with
+/// the same grammar.
+const EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+/// Expressions whose value is derived from their operands.
+const COMPOSITE_EXPRESSIONS: &[&str] = &[
+    "binary_expression",
+    "unary_expression",
+    "ternary_expression",
+    "parenthesized_expression",
+    "sequence_expression",
+    "as_expression",
+    "satisfies_expression",
+    "non_null_expression",
+    "await_expression",
+    "template_string",
+    "template_substitution",
+    "array",
+    "object",
+    "spread_element",
+    "new_expression",
+    "return_statement",
+];
+
+/// Patterns that destructure their value, e.g. `{ a, b: [c] }`.
+const DESTRUCTURING_PATTERNS: &[&str] = &[
+    "array_pattern",
+    "object_pattern",
+    "pair_pattern",
+    "rest_pattern",
+    "object_assignment_pattern",
+    "assignment_pattern",
+];
+
+/// Functions whose parameters can be resolved from their calls.
+const FUNCTIONS: &[&str] = &[
+    "function_declaration",
+    "generator_function_declaration",
+    "function_expression",
+    "function",
+    "generator_function",
+    "arrow_function",
+    "method_definition",
+];
+
+pub struct TypeScript;
+
+/// Installed packages and declaration files.
+const LIBRARIES: LibraryPaths = LibraryPaths {
+    dirs: &["node_modules"],
+    suffixes: &[".d.ts"],
+};
+
+fn is_comment(node: &Node) -> bool {
+    node.kind() == "comment"
+}
+
+/// The node whose definition a call's function is resolved by,
+/// e.g. `method` in `obj.method()`.
+fn function_name(call: Node) -> Option<Node> {
+    let function = call.child_by_field_name("function")?;
+    match function.kind() {
+        "member_expression" => function.child_by_field_name("property"),
+        "identifier" => Some(function),
+        _ => None,
+    }
+}
+
+/// The call whose function is resolved by `name`.
+fn call_of(name: Node) -> Option<Node> {
+    let mut node = name;
+    for _ in 0..2 {
+        node = node.parent()?;
+        if node.kind() == "call_expression" {
+            return (function_name(node) == Some(name)).then_some(node);
+        }
+    }
+
+    None
+}
+
+fn call_arguments(call: Node) -> Vec<Node> {
+    let Some(arguments) = call.child_by_field_name("arguments") else {
+        return vec![];
+    };
+
+    let mut cursor = arguments.walk();
+    let arguments = arguments
+        .named_children(&mut cursor)
+        .filter(|a| !is_comment(a))
+        .collect();
+    arguments
+}
+
+/// Name a function is referenced by, e.g. `f` in `const f = () => {}`.
+fn function_reference_name(function: Node) -> Option<Node> {
+    if let Some(name) = function.child_by_field_name("name") {
+        return Some(name);
+    }
+
+    let parent = function.parent()?;
+    match parent.kind() {
+        "variable_declarator" => parent
+            .child_by_field_name("name")
+            .filter(|n| n.kind() == "identifier"),
+        "pair" => parent.child_by_field_name("key"),
+        "public_field_definition" => parent.child_by_field_name("name"),
+        _ => None,
+    }
+}
+
+/// Parameter of a function a binding belongs to, and its position.
+fn parameter_of(binding: Node) -> Option<(Node, usize)> {
+    let parent = binding.parent()?;
+    if parent.kind() == "arrow_function" {
+        return Some((parent, 0));
+    }
+
+    let function = parent.parent()?;
+    if !FUNCTIONS.contains(&function.kind()) {
+        return None;
+    }
+
+    let mut cursor = parent.walk();
+    let index = parent
+        .named_children(&mut cursor)
+        .filter(|p| matches!(p.kind(), "required_parameter" | "optional_parameter"))
+        .filter(|p| {
+            p.child_by_field_name("pattern")
+                .is_some_and(|pattern| pattern.kind() != "this")
+        })
+        .position(|p| p == binding)?;
+
+    Some((function, index))
+}
+
+/// Declaration binding an identifier in its pattern, e.g. a `const` or
+/// a parameter, and the item of a destructured array it is bound to.
+fn binding_of(identifier: Node) -> Option<(Node, Option<usize>)> {
+    let mut node = identifier;
+    let mut index = None;
+
+    loop {
+        let parent = node.parent()?;
+        match parent.kind() {
+            "array_pattern" => {
+                let mut cursor = parent.walk();
+                index = parent.named_children(&mut cursor).position(|p| p == node);
+            }
+            "assignment_pattern" | "object_assignment_pattern"
+                if parent.child_by_field_name("left") != Some(node) =>
+            {
+                return None;
+            }
+            "pair_pattern" if parent.child_by_field_name("value") != Some(node) => return None,
+            kind if DESTRUCTURING_PATTERNS.contains(&kind) => index = None,
+            "variable_declarator" => {
+                return (parent.child_by_field_name("name") == Some(node))
+                    .then_some((parent, index));
+            }
+            "for_in_statement" | "assignment_expression" => {
+                return (parent.child_by_field_name("left") == Some(node))
+                    .then_some((parent, index));
+            }
+            "required_parameter" | "optional_parameter" => {
+                return (parent.child_by_field_name("pattern") == Some(node))
+                    .then_some((parent, index));
+            }
+            "arrow_function" => {
+                return (parent.child_by_field_name("parameter") == Some(node))
+                    .then_some((node, index));
+            }
+            _ => return None,
+        }
+        node = parent;
+    }
+}
+
+/// Sub-expressions the value of a composite expression is derived from.
+fn operands(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    match node.kind() {
+        "ternary_expression" => node
+            .child_by_field_name("consequence")
+            .into_iter()
+            .chain(node.child_by_field_name("alternative"))
+            .collect(),
+        "as_expression" | "satisfies_expression" | "parenthesized_expression" => {
+            node.named_child(0).into_iter().collect()
+        }
+        "new_expression" => node
+            .child_by_field_name("arguments")
+            .map(|arguments| {
+                arguments
+                    .named_children(&mut cursor)
+                    .filter(|a| !is_comment(a))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "object" => node
+            .named_children(&mut cursor)
+            .filter_map(|property| match property.kind() {
+                "pair" => property.child_by_field_name("value"),
+                "shorthand_property_identifier" | "spread_element" => Some(property),
+                _ => None,
+            })
+            .collect(),
+        _ => node
+            .named_children(&mut cursor)
+            .filter(|n| {
+                !is_comment(n) && !matches!(n.kind(), "string_fragment" | "escape_sequence")
+            })
+            .collect(),
+    }
+}
+
+impl TypeScript {
+    /// Values a library call's result is derived from: its arguments,
+    /// and the object of a method call.
+    fn library_inputs(&self, uri: &Url, call: Node) -> Vec<Location> {
+        let object = call
+            .child_by_field_name("function")
+            .filter(|f| f.kind() == "member_expression")
+            .and_then(|f| f.child_by_field_name("object"));
+
+        object
+            .into_iter()
+            .chain(call_arguments(call))
+            .map(|input| get_node_location(uri.clone(), &input))
+            .collect()
+    }
+
+    /// Resolve a parameter from the arguments of the call it was reached
+    /// from, or of all calls to its function.
+    fn resolve_parameter(
+        &self,
+        uri: &Url,
+        anchor: Option<Location>,
+        parameter: Node,
+    ) -> Vec<(Location, Vec<StepMeta>)> {
+        let Some((function, index)) = parameter_of(parameter) else {
+            return vec![];
+        };
+
+        let resolve = StepMeta::Resolve {
+            anchor: None,
+            index: None,
+        };
+
+        match anchor {
+            Some(anchor) => vec![(anchor, vec![resolve, StepMeta::GotoArgument(index)])],
+            None => function_reference_name(function)
+                .map(|name| {
+                    (
+                        get_node_location(uri.clone(), &name),
+                        vec![
+                            resolve,
+                            StepMeta::GotoArgument(index),
+                            StepMeta::GotoReference,
+                        ],
+                    )
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Values a function returns.
+    fn returns<'a>(
+        &self,
+        text: &str,
+        function: Node<'a>,
+        sources: &SourceCache,
+    ) -> Result<Vec<Node<'a>>> {
+        let Some(body) = function.child_by_field_name("body") else {
+            return Ok(vec![]);
+        };
+
+        if body.kind() != "statement_block" {
+            return Ok(vec![body]);
+        }
+
+        Ok(get_query_results(
+            text,
+            body,
+            &*sources.query("(return_statement (_) @return)")?,
+            0,
+        ))
+    }
+}
+
+impl LanguageAutomata for TypeScript {
+    type Stack = StepMeta;
+    type LspProvider = ProjectServer;
+
+    fn get_language(&self) -> tree_sitter::Language {
+        // The TSX grammar also parses JSX, and plain TypeScript and
+        // JavaScript, except for `<Type>value` casts.
+        tree_sitter_typescript::language_tsx()
+    }
+
+    fn initial_state(&self) -> Vec<Self::Stack> {
+        vec![StepMeta::Start]
+    }
+
+    fn required_queries(&self, _location: &Location, state: &Self::Stack) -> Queries {
+        state.queries()
+    }
+
+    fn transition(
+        &self,
+        location: Location,
+        state: Self::Stack,
+        definitions: Result<Vec<Location>>,
+        references: Result<Vec<Location>>,
+        sources: &SourceCache,
+    ) -> Result<Vec<(Location, Vec<Self::Stack>)>> {
+        let file = sources.get(&location.uri)?;
+        let root = file.tree.root_node();
+
+        let Some(breadcrumbs) = get_breadcrumbs(root, &location) else {
+            return Ok(vec![]);
+        };
+
+        let breadcrumbs = breadcrumbs
+            .into_iter()
+            .map(|n| (n.kind(), n))
+            .collect::<Vec<_>>();
+
+        let uri = location.uri.clone();
+        let is_field = |parent: &Node, field: &str, node: &Node| {
+            parent.child_by_field_name(field).as_ref() == Some(node)
+        };
+
+        Ok(
+            match (state, breadcrumbs.as_slice(), definitions, references) {
+                (StepMeta::Start, _, _, _) => {
+                    vec![(
+                        location,
+                        vec![
+                            StepMeta::Start,
+                            StepMeta::Resolve {
+                                anchor: None,
+                                index: None,
+                            },
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::GotoDefinition,
+                    [("identifier" | "property_identifier", name), ..],
+                    Ok(definitions),
+                    _,
+                ) if call_of(*name).is_some()
+                    && definitions.iter().all(|d| LIBRARIES.contains(d)) =>
+                {
+                    call_of(*name)
+                        .map(|call| self.library_inputs(&uri, call))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|input| (input, vec![]))
+                        .collect()
+                }
+                (
+                    StepMeta::GotoDefinition,
+                    [("identifier" | "property_identifier" | "shorthand_property_identifier", _), ..],
+                    Ok(definitions),
+                    _,
+                ) => definitions
+                    .into_iter()
+                    .filter(|d| !LIBRARIES.contains(d))
+                    .map(|d| (d, vec![]))
+                    .collect(),
+                (
+                    StepMeta::GotoReference,
+                    [("identifier" | "property_identifier", _), ..],
+                    _,
+                    Ok(references),
+                ) => references.into_iter().map(|r| (r, vec![])).collect(),
+                (StepMeta::GotoArgument(index), [(_, name), ..], _, _) => call_of(*name)
+                    .and_then(|call| call_arguments(call).into_iter().nth(index))
+                    .map(|argument| (get_node_location(uri, &argument), vec![]))
+                    .into_iter()
+                    .collect(),
+                (
+                    StepMeta::Resolve { anchor, index },
+                    [("identifier" | "shorthand_property_identifier_pattern", identifier), ..],
+                    _,
+                    _,
+                ) if binding_of(*identifier).is_some() => {
+                    let Some((binding, item)) = binding_of(*identifier) else {
+                        return Ok(vec![]);
+                    };
+
+                    let value = match binding.kind() {
+                        "required_parameter" | "optional_parameter" | "identifier" => {
+                            return Ok(self.resolve_parameter(&uri, anchor, binding));
+                        }
+                        "variable_declarator" => binding.child_by_field_name("value"),
+                        _ => binding.child_by_field_name("right"),
+                    };
+
+                    value
+                        .map(|value| {
+                            (
+                                get_node_location(uri, &value),
+                                vec![StepMeta::Resolve {
+                                    anchor,
+                                    index: item.or(index),
+                                }],
+                            )
+                        })
+                        .into_iter()
+                        .collect()
+                }
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("identifier" | "property_identifier", name), (kind, function), ..],
+                    _,
+                    _,
+                ) if FUNCTIONS.contains(kind) && is_field(function, "name", name) => self
+                    .returns(&file.text, *function, sources)?
+                    .iter()
+                    .map(|node| (get_node_location(uri.clone(), node), vec![state.clone()]))
+                    .collect(),
+                (state @ StepMeta::Resolve { .. }, [(kind, function), ..], _, _)
+                    if FUNCTIONS.contains(kind) =>
+                {
+                    self.returns(&file.text, *function, sources)?
+                        .iter()
+                        .map(|node| (get_node_location(uri.clone(), node), vec![state.clone()]))
+                        .collect()
+                }
+                (StepMeta::Resolve { anchor, index }, [("call_expression", call), ..], _, _) => {
+                    let Some(function) = function_name(*call) else {
+                        return Ok(vec![]);
+                    };
+
+                    let function = get_node_location(uri, &function);
+                    vec![(
+                        function.clone(),
+                        vec![
+                            StepMeta::Resolve { anchor, index },
+                            StepMeta::Resolve {
+                                anchor: Some(function),
+                                index,
+                            },
+                            StepMeta::GotoDefinition,
+                        ],
+                    )]
+                }
+                (
+                    StepMeta::Resolve {
+                        anchor,
+                        index: Some(index),
+                    },
+                    [("array", array), ..],
+                    _,
+                    _,
+                ) => {
+                    let mut cursor = array.walk();
+                    let item = array
+                        .named_children(&mut cursor)
+                        .filter(|n| !is_comment(n))
+                        .nth(index);
+
+                    item.map(|item| {
+                        (
+                            get_node_location(uri, &item),
+                            vec![StepMeta::Resolve {
+                                anchor,
+                                index: None,
+                            }],
+                        )
+                    })
+                    .into_iter()
+                    .collect()
+                }
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("member_expression" | "subscript_expression", node), ..],
+                    _,
+                    _,
+                ) => node
+                    .child_by_field_name("object")
+                    .map(|object| (get_node_location(uri, &object), vec![state]))
+                    .into_iter()
+                    .collect(),
+                (state @ StepMeta::Resolve { .. }, [(kind, node), ..], _, _)
+                    if COMPOSITE_EXPRESSIONS.contains(kind) =>
+                {
+                    operands(*node)
+                        .iter()
+                        .map(|n| (get_node_location(uri.clone(), n), vec![state.clone()]))
+                        .collect()
+                }
+                (
+                    state @ StepMeta::Resolve { .. },
+                    [("identifier" | "shorthand_property_identifier", _), ..],
+                    _,
+                    _,
+                ) => {
+                    vec![(location, vec![state, StepMeta::GotoDefinition])]
+                }
+                _ => vec![],
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl SupportedLanguage for TypeScript {
+    fn name(&self) -> &'static str {
+        "typescript"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["javascript"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        EXTENSIONS
+    }
+
+    fn language_id(&self, path: &Path) -> &'static str {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("tsx") => "typescriptreact",
+            Some("js" | "mjs" | "cjs") => "javascript",
+            Some("jsx") => "javascriptreact",
+            _ => "typescript",
+        }
+    }
+
+    fn tree_sitter_language(&self) -> tree_sitter::Language {
+        tree_sitter_typescript::language_tsx()
+    }
+
+    fn servers(&self) -> &'static [&'static str] {
+        SERVERS
+    }
+
+    fn default_rules(&self) -> Result<Vec<Rule>> {
+        parse_rules(DEFAULT_RULES).context("invalid default typescript rules")
+    }
+
+    fn locals_query(&self) -> Option<&'static str> {
+        Some(LOCALS)
+    }
+
+    fn is_project_file(&self, path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|e| EXTENSIONS.iter().any(|extension| e == *extension))
+            && !path.to_string_lossy().ends_with(".d.ts")
+            && !path.components().any(|c| c.as_os_str() == "node_modules")
+    }
+
+    async fn find_paths(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+        on_trace: &mut (dyn for<'t> FnMut(&'t Trace) + Send),
+    ) -> Result<ScanOutput> {
+        find_project_paths(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+            on_trace,
+        )
+        .await
+    }
+
+    async fn find_graph(
+        &self,
+        root_dir: &Path,
+        project_files: Vec<PathBuf>,
+        start_locations: Vec<Location>,
+        stop_at: &[Location],
+        options: &EngineOptions,
+    ) -> Result<GraphOutput> {
+        find_project_graph(
+            self,
+            root_dir,
+            project_files,
+            start_locations,
+            stop_at,
+            options,
+        )
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+    use crate::test_utils::RESOLVE;
+
+    snapshot!(
+        TypeScript,
+        test_start,
+        StepMeta::Start,
+        r#"
+main.ts
+#@#
+function render(html: string) {
+    element.innerHTML = html;
+    //                  ^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_identifier,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+function render(html: string) {
+    element.innerHTML = html;
+    //                  ^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        goto_definition,
+        StepMeta::GotoDefinition,
+        r#"
+main.ts
+#@#
+const html = `<b>${name}</b>`;
+//    ^^^^ definition
+element.innerHTML = html;
+//                  ^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_const,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const html = `<b>${name}</b>`;
+//    ^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_template_string,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const html = `<b>${name}</b>`;
+//           ^^^^^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_object_destructuring,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const { hash, search: query } = window.location;
+//                    ^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_array_destructuring,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const [first, second] = await load();
+//            ^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_await,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const [first, second] = await load();
+//                      ^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_array_item,
+        StepMeta::Resolve {
+            anchor: None,
+            index: Some(1),
+        },
+        r#"
+main.ts
+#@#
+async function load() {
+    return ["", location.hash];
+    //     ^^^^^^^^^^^^^^^^^^^ start
+}
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_returns,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+async function load(name: string) {
+//             ^^^^ start
+    if (!name) {
+        return "";
+    }
+    return `<b>${name}</b>`;
+}
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_arrow_function,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const bold = (name: string) => `<b>${name}</b>`;
+//           ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        library_call,
+        StepMeta::GotoDefinition,
+        r#"
+main.ts
+#@#
+const html = query.replace("+", " ");
+//                 ^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_member,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const html = event.data.html;
+//           ^^^^^^^^^^^^^^^ start
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_parameter,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+class Renderer {
+    render(element: Element, html?: string) {
+        //                   ^^^^ start
+    }
+}
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        resolve_arrow_parameter,
+        RESOLVE,
+        r#"
+main.ts
+#@#
+const render = (element, { html }) => {
+    //                     ^^^^ start
+};
+        "#
+    );
+
+    snapshot!(
+        TypeScript,
+        goto_argument,
+        StepMeta::GotoArgument(1),
+        r#"
+main.ts
+#@#
+renderer.render(element, location.hash);
+//       ^^^^^^ start
+        "#
+    );
+
+    #[test]
+    fn test_default_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.ts");
+        std::fs::write(
+            &path,
+            r#"
+window.addEventListener("message", (event) => {
+    output.innerHTML = event.data;
+});
+document.write(location.hash);
+
+const wallet = new ethers.Wallet("0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef");
+console.log(process.env.PRIVATE_KEY, wallet.address);
+"#,
+        )
+        .unwrap();
+
+        let ignored = dir.path().join("node_modules/lib/index.js");
+        std::fs::create_dir_all(ignored.parent().unwrap()).unwrap();
+        std::fs::write(&ignored, "document.write(location.hash);").unwrap();
+
+        let typescript = TypeScript;
+        let rules = typescript.default_rules().unwrap();
+        let matches = typescript.match_rules(&rules, &[path, ignored]).unwrap();
+
+        let lines = |locations: &[Location]| {
+            locations
+                .iter()
+                .map(|l| l.range.start.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&matches[0].sources), vec![2, 4]);
+        assert_eq!(lines(&matches[0].sinks), vec![1, 4]);
+        assert_eq!(lines(&matches[1].sources), vec![6]);
+        assert_eq!(lines(&matches[1].sinks), vec![6]);
+        assert_eq!(lines(&matches[2].sources), vec![7, 7]);
+        assert_eq!(lines(&matches[2].sinks), vec![7]);
+    }
+}
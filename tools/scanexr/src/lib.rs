@@ -1,6 +1,12 @@
+pub mod cli;
 pub mod converter;
+pub mod engine;
+pub mod graph;
 pub mod language_provider;
 pub mod languages;
+pub mod registry;
+pub mod rules;
+pub mod sources;
 pub mod utils;
 
 #[cfg(feature = "test-utils")]
@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+
+use crate::language_provider::SupportedLanguage;
+
+pub type DynLanguage = dyn SupportedLanguage + Send + Sync;
+
+/// Languages a scan can run on, selected by name or detected from the
+/// extensions of the project files.
+///
+/// Languages of other crates are added with [`LanguageRegistry::register`].
+#[derive(Default)]
+pub struct LanguageRegistry {
+    languages: Vec<Box<DynLanguage>>,
+}
+
+impl LanguageRegistry {
+    /// Registry without any languages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry of the languages enabled by cargo features.
+    pub fn builtin() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = Self::new();
+
+        #[cfg(feature = "solidity")]
+        registry.register(crate::languages::solidity::Solidity::default());
+        #[cfg(feature = "python")]
+        registry.register(crate::languages::python::Python);
+        #[cfg(feature = "rust")]
+        registry.register(crate::languages::rust::Rust);
+        #[cfg(feature = "typescript")]
+        registry.register(crate::languages::typescript::TypeScript);
+        #[cfg(feature = "go")]
+        registry.register(crate::languages::go::Go);
+
+        registry
+    }
+
+    /// Add `language`, replacing a registered language of the same name.
+    pub fn register(
+        &mut self,
+        language: impl SupportedLanguage + Send + Sync + 'static,
+    ) -> &mut Self {
+        let language: Box<DynLanguage> = Box::new(language);
+        match self
+            .languages
+            .iter_mut()
+            .find(|registered| registered.name() == language.name())
+        {
+            Some(registered) => *registered = language,
+            None => self.languages.push(language),
+        }
+
+        self
+    }
+
+    /// Registered languages, in registration order.
+    pub fn languages(&self) -> impl Iterator<Item = &DynLanguage> {
+        self.languages.iter().map(|language| language.as_ref())
+    }
+
+    /// Language named or aliased `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&DynLanguage> {
+        let name = name.trim().to_lowercase();
+        self.languages().find(|language| {
+            language.name() == name || language.aliases().iter().any(|alias| *alias == name)
+        })
+    }
+
+    /// Languages with any of `project_files`, most files first.
+    pub fn detect(&self, project_files: &[PathBuf]) -> Vec<&DynLanguage> {
+        let mut detected = self
+            .languages()
+            .map(|language| {
+                let count = project_files
+                    .iter()
+                    .filter(|f| language.is_project_file(f))
+                    .count();
+                (language, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<_>>();
+
+        // stable, so languages with as many files stay in registration order
+        detected.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        detected.into_iter().map(|(language, _)| language).collect()
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "solidity",
+    feature = "python",
+    feature = "typescript",
+    feature = "go"
+))]
+mod tests {
+    use super::*;
+
+    fn names(languages: &[&DynLanguage]) -> Vec<&'static str> {
+        languages.iter().map(|language| language.name()).collect()
+    }
+
+    #[test]
+    fn test_get() {
+        let registry = LanguageRegistry::builtin();
+
+        assert_eq!(registry.get("Python").unwrap().name(), "python");
+        assert_eq!(registry.get(" javascript ").unwrap().name(), "typescript");
+        assert!(registry.get("cobol").is_none());
+    }
+
+    #[test]
+    fn test_detect() {
+        let registry = LanguageRegistry::builtin();
+        let files = [
+            "contracts/Token.sol",
+            "scripts/deploy.ts",
+            "scripts/verify.js",
+            "scripts/types.d.ts",
+            "node_modules/lib/index.js",
+            "README.md",
+        ]
+        .map(PathBuf::from);
+
+        assert_eq!(
+            names(&registry.detect(&files)),
+            vec!["typescript", "solidity"]
+        );
+        assert!(registry.detect(&files[5..]).is_empty());
+    }
+
+    #[test]
+    fn test_locals_queries() {
+        for language in LanguageRegistry::builtin().languages() {
+            if let Some(locals) = language.locals_query() {
+                tree_sitter::Query::new(language.tree_sitter_language(), locals)
+                    .unwrap_or_else(|e| panic!("invalid {} locals query: {}", language.name(), e));
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_replaces() {
+        let mut registry = LanguageRegistry::builtin();
+        let count = registry.languages().count();
+
+        registry.register(crate::languages::python::Python);
+
+        assert_eq!(registry.languages().count(), count);
+        assert_eq!(
+            registry.languages().last().unwrap().name(),
+            crate::languages::go::Go.name()
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use lsp_types::Location;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Language;
+
+use crate::{
+    converter::{Convert, Converter},
+    language_provider::Trace,
+    sources::SourceCache,
+    utils::{get_node_location, get_query_results, location_contains, visit_dirs, LocationKey},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+fn default_severity() -> Severity {
+    Severity::Medium
+}
+
+/// A tree-sitter query, and the name of the capture to report.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleQuery {
+    pub query: String,
+    pub capture: String,
+}
+
+/// A source to sink pattern, e.g. a `.call` target controlled by `msg.sender`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    /// Name of the language the queries are written for, e.g. `solidity`.
+    pub language: String,
+    pub message: String,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    pub cwe: Option<String>,
+    pub swc: Option<String>,
+    pub source: RuleQuery,
+    pub sink: RuleQuery,
+    /// Findings with a step inside a sanitizer are reported as sanitized.
+    #[serde(default)]
+    pub sanitizers: Vec<RuleQuery>,
+}
+
+/// A rule file, holding any number of `[[rule]]` tables.
+#[derive(Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+pub fn parse_rules(content: &str) -> Result<Vec<Rule>> {
+    Ok(toml::from_str::<RuleFile>(content)?.rule)
+}
+
+/// Load the rules of all `.toml` files in `dir`, recursively.
+///
+/// Rule ids are unique per language, so packs of different languages can
+/// share an id, e.g. `command-injection`.
+pub fn load_rules(dir: &Path) -> Result<Vec<Rule>> {
+    let mut paths = vec![];
+    visit_dirs(dir, &mut |f| {
+        if f.path().extension().is_some_and(|e| e == "toml") {
+            paths.push(f.path());
+        }
+    })
+    .context("failed to get rule files")?;
+    paths.sort();
+
+    let mut rules: Vec<Rule> = vec![];
+    for path in paths {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        for rule in
+            parse_rules(&content).with_context(|| format!("invalid rules in {}", path.display()))?
+        {
+            if rules
+                .iter()
+                .any(|r| r.language == rule.language && r.id == rule.id)
+            {
+                bail!(
+                    "duplicate {} rule id {} in {}",
+                    rule.language,
+                    rule.id,
+                    path.display()
+                );
+            }
+            rules.push(rule);
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Locations a rule's queries matched in a project.
+#[derive(Debug, Clone, Default)]
+pub struct RuleMatches {
+    pub sources: Vec<Location>,
+    pub sinks: Vec<Location>,
+    pub sanitizers: Vec<Location>,
+}
+
+fn find_captures(
+    sources: &SourceCache,
+    project_files: &[PathBuf],
+    rule_query: &RuleQuery,
+    rule_id: &str,
+) -> Result<Vec<Location>> {
+    let query = sources
+        .query(&rule_query.query)
+        .with_context(|| format!("invalid query in rule {}", rule_id))?;
+    let capture_index = query
+        .capture_index_for_name(&rule_query.capture)
+        .with_context(|| format!("rule {} has no @{} capture", rule_id, rule_query.capture))?;
+
+    let mut locations = vec![];
+    for project_file in project_files {
+        let uri = Converter::convert(project_file.as_path());
+        let file = sources.get(&uri)?;
+
+        locations.extend(
+            get_query_results(&file.text, file.tree.root_node(), &query, capture_index)
+                .iter()
+                .map(|n| get_node_location(uri.clone(), n)),
+        );
+    }
+
+    Ok(locations)
+}
+
+/// Match all rules against the project files in one pass, parsing each
+/// file once.
+pub fn match_rules(
+    language: Language,
+    rules: &[Rule],
+    project_files: &[PathBuf],
+) -> Result<Vec<RuleMatches>> {
+    let sources = SourceCache::new(language);
+
+    rules
+        .iter()
+        .map(|rule| {
+            Ok(RuleMatches {
+                sources: find_captures(&sources, project_files, &rule.source, &rule.id)?,
+                sinks: find_captures(&sources, project_files, &rule.sink, &rule.id)?,
+                sanitizers: rule
+                    .sanitizers
+                    .iter()
+                    .map(|s| find_captures(&sources, project_files, s, &rule.id))
+                    .collect::<Result<Vec<_>>>()?
+                    .concat(),
+            })
+        })
+        .collect()
+}
+
+/// Start and end locations of all rules, without duplicates.
+pub fn starts_and_sinks(matches: &[RuleMatches]) -> (Vec<Location>, Vec<Location>) {
+    let mut starts: Vec<Location> = vec![];
+    let mut sinks: Vec<Location> = vec![];
+
+    for matches in matches {
+        for source in &matches.sources {
+            if !starts.contains(source) {
+                starts.push(source.clone());
+            }
+        }
+        for sink in &matches.sinks {
+            if !sinks.contains(sink) {
+                sinks.push(sink.clone());
+            }
+        }
+    }
+
+    (starts, sinks)
+}
+
+/// A trace from a rule's source to one of its sinks.
+#[derive(Debug)]
+pub struct Finding<'a> {
+    pub rule: &'a Rule,
+    pub trace: &'a Trace,
+    /// Whether the trace passed a guard, or a step of it is inside one
+    /// of the rule's sanitizers.
+    pub sanitized: bool,
+}
+
+/// Attribute traces to the rules whose source they start at, and whose
+/// sink they reached.
+pub fn find_findings<'a>(
+    rules: &'a [Rule],
+    matches: &[RuleMatches],
+    traces: &'a [Trace],
+) -> Vec<Finding<'a>> {
+    let mut findings = vec![];
+
+    for (rule, matches) in rules.iter().zip(matches) {
+        let sources = matches
+            .sources
+            .iter()
+            .map(LocationKey::from)
+            .collect::<HashSet<_>>();
+        let sinks = matches
+            .sinks
+            .iter()
+            .map(LocationKey::from)
+            .collect::<HashSet<_>>();
+
+        for trace in traces {
+            let (Some(start), Some(sink)) = (trace.steps.first(), trace.sink()) else {
+                continue;
+            };
+
+            if sources.contains(&LocationKey::from(start))
+                && sinks.contains(&LocationKey::from(sink))
+            {
+                findings.push(Finding {
+                    rule,
+                    trace,
+                    sanitized: !trace.guards.is_empty()
+                        || trace.steps.iter().any(|step| {
+                            matches
+                                .sanitizers
+                                .iter()
+                                .any(|sanitizer| location_contains(sanitizer, step))
+                        }),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(all(test, feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::{language_provider::TraceEnd, test_utils::line_location as location};
+
+    const RULES: &str = r#"
+[[rule]]
+id = "eval-input"
+language = "python"
+severity = "high"
+message = "eval argument is derived from input()"
+cwe = "CWE-95"
+
+# like the shipped rules, paths go from the dangerous call back to the input
+[rule.source]
+query = '(call function: (identifier) @f (#eq? @f "eval") arguments: (argument_list (_) @arg))'
+capture = "arg"
+
+[rule.sink]
+query = '(call function: (identifier) @f (#eq? @f "input")) @call'
+capture = "call"
+
+[[rule.sanitizers]]
+query = '(call function: (identifier) @f (#eq? @f "int")) @call'
+capture = "call"
+
+[[rule]]
+id = "exec-input"
+language = "python"
+message = "exec argument is derived from input()"
+
+[rule.source]
+query = "(identifier) @id"
+capture = "id"
+
+[rule.sink]
+query = "(identifier) @id"
+capture = "id"
+"#;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(RULES).unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].severity, Severity::High);
+        assert_eq!(rules[0].cwe.as_deref(), Some("CWE-95"));
+        assert_eq!(rules[0].sanitizers.len(), 1);
+        assert_eq!(rules[1].severity, Severity::Medium);
+        assert!(rules[1].sanitizers.is_empty());
+
+        assert!(parse_rules("[[rule]]\nid = \"missing-fields\"").is_err());
+    }
+
+    #[test]
+    fn test_load_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.toml"), RULES).unwrap();
+        std::fs::write(dir.path().join("notes.md"), "not a rule").unwrap();
+        assert_eq!(load_rules(dir.path()).unwrap().len(), 2);
+
+        std::fs::create_dir(dir.path().join("more")).unwrap();
+        std::fs::write(dir.path().join("more/b.toml"), RULES).unwrap();
+        let error = load_rules(dir.path()).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("duplicate python rule id eval-input"));
+    }
+
+    #[test]
+    fn test_load_shipped_rules() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("rules");
+        let rules = load_rules(&dir).unwrap();
+
+        let mut languages = rules
+            .iter()
+            .map(|r| r.language.as_str())
+            .collect::<Vec<_>>();
+        languages.dedup();
+        assert_eq!(
+            languages,
+            vec!["go", "python", "rust", "solidity", "typescript"]
+        );
+        assert_eq!(
+            rules.iter().filter(|r| r.id == "command-injection").count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_match_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.py");
+        std::fs::write(&path, "x = input()\ny = int(x)\neval(x)\neval(y)\n").unwrap();
+
+        let rules = parse_rules(RULES).unwrap();
+        let matches = match_rules(tree_sitter_python::language(), &rules[..1], &[path]).unwrap();
+
+        let lines = |locations: &[Location]| {
+            locations
+                .iter()
+                .map(|l| l.range.start.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&matches[0].sources), vec![2, 3]);
+        assert_eq!(lines(&matches[0].sinks), vec![0]);
+        assert_eq!(lines(&matches[0].sanitizers), vec![1]);
+    }
+
+    #[test]
+    fn test_find_findings() {
+        let rules = parse_rules(RULES).unwrap();
+        let matches = vec![
+            RuleMatches {
+                sources: vec![location(1)],
+                sinks: vec![location(3)],
+                sanitizers: vec![location(2)],
+            },
+            RuleMatches::default(),
+        ];
+
+        let traces = vec![
+            Trace {
+                steps: vec![location(1), location(3)],
+                end: TraceEnd::Sink(location(3)),
+                guards: vec![],
+            },
+            Trace {
+                steps: vec![location(1), location(2), location(3)],
+                end: TraceEnd::Sink(location(3)),
+                guards: vec![],
+            },
+            Trace {
+                steps: vec![location(1), location(4)],
+                end: TraceEnd::DeadEnd,
+                guards: vec![],
+            },
+        ];
+
+        let findings = find_findings(&rules, &matches, &traces)
+            .into_iter()
+            .map(|f| (f.rule.id.as_str(), f.trace.steps.len(), f.sanitized))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            findings,
+            vec![("eval-input", 2, false), ("eval-input", 3, true)]
+        );
+    }
+}
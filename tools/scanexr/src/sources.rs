@@ -0,0 +1,239 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context, Result};
+use lsp_types::Url;
+use tree_sitter::{InputEdit, Language, Parser, Point, Query, Tree};
+
+use crate::utils::get_uri_content;
+
+/// Text of a file and its parse tree.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub text: String,
+    pub tree: Tree,
+    hash: u64,
+    /// Stamp of the file on disk when the text was read or set.
+    stamp: Option<Stamp>,
+}
+
+/// Modification time and length of a file on disk.
+type Stamp = (SystemTime, u64);
+
+fn stamp(uri: &Url) -> Option<Stamp> {
+    let metadata = std::fs::metadata(uri.to_file_path().ok()?).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Scan-wide cache of parsed files and compiled queries.
+///
+/// A cached file is only read again once its modification time or length
+/// on disk changes, and only re-parsed if its content changed, incrementally
+/// from its previous tree.
+pub struct SourceCache {
+    language: Language,
+    files: Mutex<HashMap<Url, Arc<SourceFile>>>,
+    queries: Mutex<HashMap<String, Arc<Query>>>,
+}
+
+fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Position of a byte offset in `text`.
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text.as_bytes()[..byte];
+    let row = before.iter().filter(|b| **b == b'\n').count();
+    let column = match before.iter().rposition(|b| *b == b'\n') {
+        Some(newline) => byte - newline - 1,
+        None => byte,
+    };
+
+    Point { row, column }
+}
+
+/// Edit turning `old` into `new`, spanning everything between their
+/// common prefix and suffix.
+fn diff(old: &str, new: &str) -> InputEdit {
+    let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+
+    let prefix = old_bytes
+        .iter()
+        .zip(new_bytes)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_bytes[prefix..]
+        .iter()
+        .rev()
+        .zip(new_bytes[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old, prefix),
+        old_end_position: point_at(old, old_end),
+        new_end_position: point_at(new, new_end),
+    }
+}
+
+impl SourceCache {
+    pub fn new(language: Language) -> Self {
+        Self {
+            language,
+            files: Mutex::new(HashMap::new()),
+            queries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get a file, reading it from disk only if it isn't cached or was
+    /// modified since.
+    pub fn get(&self, uri: &Url) -> Result<Arc<SourceFile>> {
+        let stamp = stamp(uri);
+        if let Some(file) = self.files.lock().unwrap().get(uri) {
+            if stamp.is_some() && file.stamp == stamp {
+                return Ok(file.clone());
+            }
+        }
+
+        self.insert(uri, get_uri_content(uri)?, stamp)
+    }
+
+    /// Set the content of a file, e.g. an unsaved editor buffer, which
+    /// [`SourceCache::get`] returns until the file is modified on disk.
+    pub fn update(&self, uri: &Url, text: String) -> Result<Arc<SourceFile>> {
+        self.insert(uri, text, stamp(uri))
+    }
+
+    fn insert(&self, uri: &Url, text: String, stamp: Option<Stamp>) -> Result<Arc<SourceFile>> {
+        let hash = content_hash(&text);
+
+        let mut files = self.files.lock().unwrap();
+        let previous = match files.get(uri) {
+            Some(file) if file.hash == hash && file.text == text && file.stamp == stamp => {
+                return Ok(file.clone())
+            }
+            previous => previous,
+        };
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(self.language)
+            .context("failed to set language")?;
+
+        let old_tree = previous.map(|file| {
+            let mut tree = file.tree.clone();
+            tree.edit(&diff(&file.text, &text));
+            tree
+        });
+
+        let tree = parser
+            .parse(&text, old_tree.as_ref())
+            .ok_or_else(|| anyhow!("failed to parse {}", uri))?;
+
+        let file = Arc::new(SourceFile {
+            text,
+            tree,
+            hash,
+            stamp,
+        });
+        files.insert(uri.clone(), file.clone());
+
+        Ok(file)
+    }
+
+    /// Get a compiled query, compiling it on first use.
+    pub fn query(&self, source: &str) -> Result<Arc<Query>> {
+        let mut queries = self.queries.lock().unwrap();
+        if let Some(query) = queries.get(source) {
+            return Ok(query.clone());
+        }
+
+        let query = Arc::new(
+            Query::new(self.language, source)
+                .with_context(|| format!("failed to compile query {}", source))?,
+        );
+        queries.insert(source.to_string(), query.clone());
+
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reparse() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.py");
+        std::fs::write(&path, "def foo():\n    return 1\n").unwrap();
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let cache = SourceCache::new(tree_sitter_python::language());
+        let first = cache.get(&uri).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get(&uri).unwrap()));
+
+        std::fs::write(&path, "def foo():\n    return bar(1)\n").unwrap();
+        let second = cache.get(&uri).unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_python::language()).unwrap();
+        let fresh = parser.parse(&second.text, None).unwrap();
+        assert_eq!(
+            second.tree.root_node().to_sexp(),
+            fresh.root_node().to_sexp()
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.py");
+        std::fs::write(&path, "x = 1\n").unwrap();
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let cache = SourceCache::new(tree_sitter_python::language());
+        cache.get(&uri).unwrap();
+
+        // an unsaved buffer is kept until the file changes on disk
+        cache.update(&uri, "x = 2\n".to_string()).unwrap();
+        assert_eq!(cache.get(&uri).unwrap().text, "x = 2\n");
+
+        std::fs::write(&path, "x = 33\n").unwrap();
+        assert_eq!(cache.get(&uri).unwrap().text, "x = 33\n");
+    }
+
+    #[test]
+    fn test_diff() {
+        let edit = diff("a\nbc\nd", "a\nbxyc\nd");
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (3, 3, 5)
+        );
+        assert_eq!(edit.start_position, Point { row: 1, column: 1 });
+        assert_eq!(edit.new_end_position, Point { row: 1, column: 3 });
+    }
+
+    #[test]
+    fn test_query() {
+        let cache = SourceCache::new(tree_sitter_python::language());
+        let query = cache.query("(identifier) @id").unwrap();
+        assert!(Arc::ptr_eq(
+            &query,
+            &cache.query("(identifier) @id").unwrap()
+        ));
+        assert!(cache.query("(not_a_node) @id").is_err());
+    }
+}
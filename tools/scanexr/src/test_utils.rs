@@ -1,11 +1,61 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Mutex};
 
+use anyhow::Result;
+use async_trait::async_trait;
 use lsp_types::{Location, Position, Range, Url};
 use tempfile::{tempdir, TempDir};
 
+use crate::{
+    language_provider::{LanguageAutomata, LspProvider, Queries, Trace},
+    languages::StepMeta,
+    sources::SourceCache,
+};
+
 pub const FILE_SEP: &str = "---";
 pub const FILENAME_SEP: &str = "#@#";
 
+/// Resolve a location that wasn't reached from a call.
+pub const RESOLVE: StepMeta = StepMeta::Resolve {
+    anchor: None,
+    index: None,
+};
+
+/// Snapshot test of a language's transition of the location marked in
+/// `$input` with `$state` on top of the stack, see [`setup_test_dir`].
+#[macro_export]
+macro_rules! snapshot {
+    ($language:expr, $name:tt, $state:expr, $input:literal) => {
+        #[test]
+        fn $name() {
+            use $crate::language_provider::LanguageAutomata;
+
+            let (_root_dir, location, definitions, references) =
+                $crate::test_utils::setup_test_dir($input);
+
+            let language = $language;
+            let sources = $crate::sources::SourceCache::new(language.get_language());
+
+            let next_steps = language
+                .transition(location, $state, Ok(definitions), Ok(references), &sources)
+                .expect("failed");
+
+            let next_steps = $crate::test_utils::display_locations(next_steps, None);
+            let snapshot = format!(
+                r#"
+--- input ---
+{}
+
+--- output ---
+{}
+            "#,
+                $input, next_steps
+            );
+
+            insta::assert_snapshot!(snapshot);
+        }
+    };
+}
+
 pub fn display_location<M: Debug>(location: &Location, meta: &M, scrolloff: Option<u32>) -> String {
     let path = location.uri.to_file_path().unwrap();
     let filename: &str = path.file_name().unwrap().to_str().unwrap();
@@ -143,3 +193,141 @@ pub fn setup_test_dir(content: &str) -> (TempDir, Location, Vec<Location>, Vec<L
 
     (tempdir, start, definitions, references)
 }
+
+/// Language server without any definitions, references or
+/// implementations, recording the line of each query.
+#[derive(Default)]
+pub struct NoLsp {
+    pub definitions: Mutex<Vec<u32>>,
+    pub references: Mutex<Vec<u32>>,
+    pub implementations: Mutex<Vec<u32>>,
+}
+
+#[async_trait]
+impl LspProvider for NoLsp {
+    async fn find_definitions(&self, location: &Location) -> Result<Vec<Location>> {
+        self.definitions
+            .lock()
+            .unwrap()
+            .push(location.range.start.line);
+        Ok(vec![])
+    }
+
+    async fn find_references(&self, location: &Location) -> Result<Vec<Location>> {
+        self.references
+            .lock()
+            .unwrap()
+            .push(location.range.start.line);
+        Ok(vec![])
+    }
+
+    async fn find_implementations(&self, location: &Location) -> Result<Vec<Location>> {
+        self.implementations
+            .lock()
+            .unwrap()
+            .push(location.range.start.line);
+        Ok(vec![])
+    }
+}
+
+pub fn line_location(line: u32) -> Location {
+    Location::new(
+        Url::parse("file:///contract.sol").unwrap(),
+        Range::new(Position::new(line, 0), Position::new(line, 1)),
+    )
+}
+
+/// Automaton stepping from each line to the lines in its edges,
+/// recording the line of each transition.
+pub struct Graph {
+    edges: Vec<(u32, Vec<u32>)>,
+    pub queries: Queries,
+    /// Lines guarded by themselves.
+    pub guards: Vec<u32>,
+    /// Lines whose transitions push onto the stack, like a call does.
+    pub pushes: Vec<u32>,
+    pub transitions: Mutex<Vec<u32>>,
+}
+
+impl Graph {
+    pub fn new(edges: &[(u32, &[u32])]) -> Self {
+        Self {
+            edges: edges.iter().map(|(f, t)| (*f, t.to_vec())).collect(),
+            queries: Queries::ALL,
+            guards: vec![],
+            pushes: vec![],
+            transitions: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl LanguageAutomata for Graph {
+    type Stack = ();
+    type LspProvider = NoLsp;
+
+    fn get_language(&self) -> tree_sitter::Language {
+        tree_sitter_solidity::language()
+    }
+
+    fn initial_state(&self) -> Vec<Self::Stack> {
+        vec![()]
+    }
+
+    fn required_queries(&self, _input: &Location, _stack: &Self::Stack) -> Queries {
+        self.queries
+    }
+
+    fn find_guard(&self, location: &Location, _sources: &SourceCache) -> Result<Option<Location>> {
+        let line = location.range.start.line;
+        Ok(self.guards.contains(&line).then(|| line_location(line)))
+    }
+
+    fn transition(
+        &self,
+        input: Location,
+        _stack: Self::Stack,
+        _definitions: Result<Vec<Location>>,
+        _references: Result<Vec<Location>>,
+        _sources: &SourceCache,
+    ) -> Result<Vec<(Location, Vec<Self::Stack>)>> {
+        let line = input.range.start.line;
+        self.transitions.lock().unwrap().push(line);
+
+        let pushed = match self.pushes.contains(&line) {
+            true => vec![(), ()],
+            false => vec![()],
+        };
+
+        Ok(self
+            .edges
+            .iter()
+            .filter(|(from, _)| *from == line)
+            .flat_map(|(_, to)| to.iter().map(|to| (line_location(*to), pushed.clone())))
+            .collect())
+    }
+}
+
+/// Display traces of [`line_location`]s as `1 -> 2 -> 3 End`, followed
+/// by the lines of their guards, if any.
+pub fn display_traces(traces: &[Trace]) -> Vec<String> {
+    traces
+        .iter()
+        .map(|t| {
+            let steps = t
+                .steps
+                .iter()
+                .map(|s| s.range.start.line.to_string())
+                .collect::<Vec<_>>();
+            let guards = t
+                .guards
+                .iter()
+                .map(|g| g.range.start.line)
+                .collect::<Vec<_>>();
+
+            match guards.is_empty() {
+                true => format!("{} {:?}", steps.join(" -> "), t.end),
+                false => format!("{} {:?} guards {:?}", steps.join(" -> "), t.end, guards),
+            }
+        })
+        .collect()
+}
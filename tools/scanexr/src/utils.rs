@@ -1,8 +1,9 @@
 use std::{fs::DirEntry, path::Path};
 
 use anyhow::{anyhow, Result};
-use lsp_types::{Location, Url};
-use tree_sitter::{Node, Parser, Point, Query, QueryCursor, Tree};
+use lsp_types::{Location, Range, Url};
+use serde_json::{json, Value};
+use tree_sitter::{Language, Node, Parser, Point, Query, QueryCursor, Tree};
 
 use crate::converter::{Convert, Converter};
 
@@ -16,6 +17,48 @@ pub fn get_node_location(uri: Url, node: &Node) -> Location {
     }
 }
 
+/// Hashable identity of a [`Location`], which doesn't implement [`std::hash::Hash`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocationKey {
+    uri: Url,
+    start: (u32, u32),
+    end: (u32, u32),
+}
+
+impl From<&Location> for LocationKey {
+    fn from(location: &Location) -> Self {
+        let Range { start, end } = location.range;
+        Self {
+            uri: location.uri.clone(),
+            start: (start.line, start.character),
+            end: (end.line, end.character),
+        }
+    }
+}
+
+/// Whether `inner` is within `outer`, e.g. a `msg` identifier within
+/// a `msg.sender` sink.
+pub fn location_contains(outer: &Location, inner: &Location) -> bool {
+    outer.uri == inner.uri
+        && outer.range.start <= inner.range.start
+        && inner.range.end <= outer.range.end
+}
+
+/// A location as `{path, start, end}`, as used by the viz tool.
+pub fn location_json(location: &Location) -> Value {
+    json!({
+        "path": location.uri.to_file_path().expect("failed to convert uri to path"),
+        "start": {
+            "line": location.range.start.line,
+            "character": location.range.start.character
+        },
+        "end": {
+            "line": location.range.end.line,
+            "character": location.range.end.character
+        },
+    })
+}
+
 pub fn get_location_node<'a>(root: Node<'a>, location: &Location) -> Option<Node<'a>> {
     let start = Point {
         row: location.range.start.line as usize,
@@ -58,15 +101,13 @@ pub fn get_query_results<'a>(
     capture_index: u32,
 ) -> Vec<Node<'a>> {
     let mut query_cursor = QueryCursor::new();
-    let captures = query_cursor.captures(query, root, text.as_bytes());
+    let matches = query_cursor.matches(query, root, text.as_bytes());
 
+    // Captures are only complete once their match is, so they are read
+    // from whole matches, and ordered like they appear in the text.
     let mut nodes = vec![];
 
-    for (q_match, index) in captures {
-        if index != 0 {
-            continue;
-        }
-
+    for q_match in matches {
         for capture in q_match.captures {
             if capture.index == capture_index {
                 nodes.push(capture.node);
@@ -74,6 +115,8 @@ pub fn get_query_results<'a>(
         }
     }
 
+    nodes.sort_by_key(|node| node.start_byte());
+
     nodes
 }
 
@@ -99,11 +142,11 @@ pub fn visit_dirs(dir: &Path, cb: &mut impl FnMut(&DirEntry)) -> std::io::Result
     Ok(())
 }
 
-pub fn parse_file(path: &Path) -> Result<(String, Tree)> {
+pub fn parse_file(path: &Path, language: Language) -> Result<(String, Tree)> {
     let text = String::from_utf8(std::fs::read(path)?)?;
 
     let mut parser = Parser::new();
-    parser.set_language(tree_sitter_solidity::language())?;
+    parser.set_language(language)?;
 
     let tree = parser
         .parse(&text, None)
@@ -111,3 +154,27 @@ pub fn parse_file(path: &Path) -> Result<(String, Tree)> {
 
     Ok((text, tree))
 }
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::*;
+
+    #[test]
+    fn test_location_contains() {
+        let uri = Url::parse("file:///contract.sol").unwrap();
+        let sink = Location::new(
+            uri.clone(),
+            Range::new(Position::new(3, 8), Position::new(3, 18)),
+        );
+        let mut inner = sink.clone();
+        inner.range.end.character = 11;
+        let other_line = Location::new(uri, Range::new(Position::new(3, 0), Position::new(3, 1)));
+
+        assert!(location_contains(&sink, &sink));
+        assert!(location_contains(&sink, &inner));
+        assert!(!location_contains(&inner, &sink));
+        assert!(!location_contains(&sink, &other_line));
+    }
+}
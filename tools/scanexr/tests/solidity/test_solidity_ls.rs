@@ -1,6 +1,6 @@
 use scanexr::{
     language_provider::{find_paths, LanguageAutomata, LspProvider},
-    languages::solidity::{Solidity, SolidityLs},
+    languages::solidity::{Solidity, SolidityLs, SolidityServer, SolidityServers},
     test_utils::{display_locations, setup_test_dir},
     utils::visit_dirs,
 };
@@ -135,6 +135,149 @@ contract Contract {
     );
 }
 
+#[tokio::test]
+async fn test_find_references_call_sites() {
+    macro_rules! test_call_sites {
+        ($input:literal) => {
+            let (root_dir, location, _, expected) = setup_test_dir($input);
+
+            let mut project_files = vec![];
+            visit_dirs(root_dir.path(), &mut |f| project_files.push(f.path()))
+                .expect("failed to get project files");
+
+            let lsp = SolidityLs::new(root_dir.path(), project_files)
+                .await
+                .expect("failed to start solidity ls");
+
+            let references = lsp
+                .find_references(&location)
+                .await
+                .expect("failed to find references");
+
+            assert_eq!(references, expected);
+        };
+    }
+
+    test_call_sites!(
+        r#"
+contract.sol
+#@#
+pragma solidity ^0.8.19;
+
+contract Base {
+    function foo() public virtual {}
+         //  ^^^ start
+}
+
+contract Contract is Base {
+    function foo() public override {
+        super.foo();
+           // ^^^ reference
+    }
+
+    function withdraw() public {
+        this.foo();
+          // ^^^ reference
+    }
+}
+        "#
+    );
+
+    test_call_sites!(
+        r#"
+contract.sol
+#@#
+pragma solidity ^0.8.19;
+
+contract Contract {
+    modifier onlyOwner() {
+          // ^^^^^^^^^ start
+        _;
+    }
+
+    function withdraw() public onlyOwner {
+                            // ^^^^^^^^^ reference
+    }
+}
+        "#
+    );
+
+    test_call_sites!(
+        r#"
+contract.sol
+#@#
+pragma solidity ^0.8.19;
+
+contract Vault {}
+      // ^^^^^ start
+
+contract Factory {
+    function create() public returns (Vault) {
+        return new Vault();
+                // ^^^^^ reference
+    }
+}
+        "#
+    );
+}
+
+#[tokio::test]
+async fn test_find_references_file_changed() {
+    let input = r#"
+contract.sol
+#@#
+pragma solidity ^0.8.19;
+
+contract Contract {
+    function foo() public {}
+         //  ^^^ start
+
+    function withdraw() public {}
+}
+        "#;
+
+    let (root_dir, location, _, _) = setup_test_dir(input);
+    let mut project_files = vec![];
+    visit_dirs(root_dir.path(), &mut |f| project_files.push(f.path()))
+        .expect("failed to get project files");
+    let lsp = SolidityLs::new(root_dir.path(), project_files)
+        .await
+        .expect("failed to start solidity ls");
+
+    let references = lsp
+        .find_references(&location)
+        .await
+        .expect("failed to find references");
+    assert!(references.is_empty());
+
+    let path = root_dir.path().join("contract.sol");
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(
+        &path,
+        content.replace(
+            "function withdraw() public {}",
+            "function withdraw() public { foo(); }",
+        ),
+    )
+    .unwrap();
+
+    // the index is only rebuilt for changed files
+    let references = lsp
+        .find_references(&location)
+        .await
+        .expect("failed to find references");
+    assert!(references.is_empty());
+
+    lsp.file_changed(&path)
+        .await
+        .expect("failed to notify file change");
+    let references = lsp
+        .find_references(&location)
+        .await
+        .expect("failed to find references");
+    assert_eq!(references.len(), 1);
+}
+
 #[tokio::test]
 async fn test_contract() {
     let contract = include_str!("contract/contract.sol");
@@ -158,8 +301,9 @@ other_file.sol
         .expect("failed to get project files");
     let lsp = SolidityLs::new(root_dir.path(), project_files)
         .await
+        .map(|lsp| SolidityServers::from(SolidityServer::Ls(Box::new(lsp))))
         .expect("failed to start solidity ls");
-    let strategy = Solidity;
+    let strategy = Solidity::default();
 
     let paths = find_paths(&strategy, &lsp, start, strategy.initial_state(), &stop_at)
         .await
@@ -171,7 +315,7 @@ other_file.sol
         .into_iter()
         .enumerate()
         .map(|(i, path)| {
-            let path = path.into_iter().map(|p| (p, ())).collect();
+            let path = path.steps.into_iter().map(|p| (p, ())).collect();
             format!("Path: {i}\n{}", display_locations(path, Some(3)))
         })
         .collect::<Vec<_>>()